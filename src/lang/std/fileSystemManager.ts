@@ -1,6 +1,6 @@
 import { readFile, exists as tauriExists } from '@tauri-apps/plugin-fs'
 import { isTauri } from 'lib/isTauri'
-import { join } from '@tauri-apps/api/path'
+import { isAbsolute, join } from '@tauri-apps/api/path'
 import { readDirRecursive } from 'lib/tauri'
 
 /// FileSystemManager is a class that provides a way to read files from the local file system.
@@ -21,6 +21,19 @@ class FileSystemManager {
     this._dir = dir
   }
 
+  /// The current project's directory, or null when no project is open.
+  get dirIfSet(): string | null {
+    return this._dir
+  }
+
+  /// Resolve a path relative to the current project.
+  /// Absolute paths, e.g. from the LSP's workspace folders, don't need a project.
+  private resolve(path: string): Promise<string> {
+    return isAbsolute(path).then((absolute) =>
+      absolute ? path : join(this.dir, path)
+    )
+  }
+
   readFile(path: string): Promise<Uint8Array | void> {
     // Using local file system only works from Tauri.
     if (!isTauri()) {
@@ -29,7 +42,7 @@ class FileSystemManager {
       )
     }
 
-    return join(this.dir, path)
+    return this.resolve(path)
       .catch((error) => {
         throw new Error(`Error reading file: ${error}`)
       })
@@ -46,7 +59,7 @@ class FileSystemManager {
       )
     }
 
-    return join(this.dir, path)
+    return this.resolve(path)
       .catch((error) => {
         throw new Error(`Error checking file exists: ${error}`)
       })
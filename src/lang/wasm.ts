@@ -187,7 +187,10 @@ export const _executor = async (
       baseUnit,
       engineCommandManager,
       fileSystemManager,
-      isMock
+      isMock,
+      // Imports are relative to the project's root, where its project.toml is,
+      // as in the language server and the kcl command line tool.
+      fileSystemManager.dirIfSet ?? undefined
     )
     return memory
   } catch (e: any) {
@@ -45,6 +45,14 @@ pub enum CompileError {
     NoReturnStmt,
     #[error("You used the %, which means \"substitute this argument for the value to the left in this |> pipeline\". But there is no such value, because you're not calling a pipeline.")]
     NotInPipeline,
+    #[error("you used a for loop, but loops can't be compiled into an execution plan yet")]
+    LoopsNotSupported,
+    #[error("you imported another KCL file, but imports can't be compiled into an execution plan yet")]
+    ImportsNotSupported,
+    #[error("the {0} operator can't be compiled into an execution plan yet")]
+    UnsupportedOperator(String),
+    #[error("you used an if expression whose condition isn't known until the program runs. Only `true` or `false` can be used as conditions here")]
+    UnknownCondition,
     #[error("The function '{fn_name}' expects a parameter of type {expected} as argument number {arg_number} but you supplied {actual}")]
     ArgWrongType {
         fn_name: &'static str,
@@ -77,6 +77,8 @@ impl Planner {
                         retval = Some(binding);
                         instructions
                     }
+                    BodyItem::ForStatement(_) => return Err(CompileError::LoopsNotSupported),
+                    BodyItem::ImportStatement(_) => return Err(CompileError::ImportsNotSupported),
                 };
                 instructions.extend(instructions_for_this_node);
                 Ok((instructions, retval))
@@ -209,6 +211,23 @@ impl Planner {
                         "you tried to use a composite value (e.g. array or object) as the operand to some math",
                     ));
                 };
+                let operation = match expr.operator {
+                    ast::types::BinaryOperator::Add => ep::BinaryOperation::Add,
+                    ast::types::BinaryOperator::Sub => ep::BinaryOperation::Sub,
+                    ast::types::BinaryOperator::Mul => ep::BinaryOperation::Mul,
+                    ast::types::BinaryOperator::Div => ep::BinaryOperation::Div,
+                    ast::types::BinaryOperator::Mod => ep::BinaryOperation::Mod,
+                    ast::types::BinaryOperator::Pow => ep::BinaryOperation::Pow,
+                    // Execution plans have no comparison or boolean arithmetic yet.
+                    op @ (ast::types::BinaryOperator::Eq
+                    | ast::types::BinaryOperator::Neq
+                    | ast::types::BinaryOperator::Lt
+                    | ast::types::BinaryOperator::Lte
+                    | ast::types::BinaryOperator::Gt
+                    | ast::types::BinaryOperator::Gte
+                    | ast::types::BinaryOperator::And
+                    | ast::types::BinaryOperator::Or) => return Err(CompileError::UnsupportedOperator(op.to_string())),
+                };
                 let destination = self.next_addr.offset_by(1);
                 let mut plan = Vec::with_capacity(l.instructions.len() + r.instructions.len() + 1);
                 plan.extend(l.instructions);
@@ -216,14 +235,7 @@ impl Planner {
                 plan.push(Instruction::from_range(
                     InstructionKind::BinaryArithmetic {
                         arithmetic: ep::BinaryArithmetic {
-                            operation: match expr.operator {
-                                ast::types::BinaryOperator::Add => ep::BinaryOperation::Add,
-                                ast::types::BinaryOperator::Sub => ep::BinaryOperation::Sub,
-                                ast::types::BinaryOperator::Mul => ep::BinaryOperation::Mul,
-                                ast::types::BinaryOperator::Div => ep::BinaryOperation::Div,
-                                ast::types::BinaryOperator::Mod => ep::BinaryOperation::Mod,
-                                ast::types::BinaryOperator::Pow => ep::BinaryOperation::Pow,
-                            },
+                            operation,
                             operand0: ep::Operand::Reference(l_binding),
                             operand1: ep::Operand::Reference(r_binding),
                         },
@@ -496,6 +508,60 @@ impl Planner {
                     binding: current_value,
                 })
             }
+            SingleValue::IfExpression(expr) => {
+                // Execution plans can't branch at runtime, so the taken branch has to be known
+                // while compiling, i.e. every condition checked must be a boolean literal.
+                let ast::types::IfExpression {
+                    start,
+                    end,
+                    cond,
+                    then_val,
+                    else_ifs,
+                    final_else,
+                } = *expr;
+                let branches = std::iter::once((cond, then_val))
+                    .chain(else_ifs.into_iter().map(|else_if| (else_if.cond, else_if.then_val)));
+                let mut taken = None;
+                for (cond, block) in branches {
+                    match cond {
+                        SingleValue::Literal(literal) if literal.value == LiteralValue::Bool(true) => {
+                            taken = Some(block);
+                            break;
+                        }
+                        SingleValue::Literal(literal) if literal.value == LiteralValue::Bool(false) => continue,
+                        _ => return Err(CompileError::UnknownCondition),
+                    }
+                }
+                let Some(mut block) = taken.or(final_else) else {
+                    return self.plan_to_compute_single(ctx, SingleValue::None(KclNone { start, end }));
+                };
+
+                // The block's value is its trailing expression, so treat that like a return.
+                if let Some(last) = block.body.last_mut() {
+                    if let BodyItem::ExpressionStatement(tail) = last {
+                        *last = BodyItem::ReturnStatement(ast::types::ReturnStatement {
+                            start: tail.start,
+                            end: tail.end,
+                            argument: tail.expression.clone(),
+                        });
+                    }
+                }
+                self.binding_scope.add_scope();
+                let (instructions, retval) = self.build_plan(block)?;
+                self.binding_scope.remove_scope();
+                match retval {
+                    Some(binding) => Ok(EvalPlan { instructions, binding }),
+                    None => {
+                        let EvalPlan {
+                            instructions: nil_instructions,
+                            binding,
+                        } = self.plan_to_compute_single(ctx, SingleValue::None(KclNone { start, end }))?;
+                        let mut instructions = instructions;
+                        instructions.extend(nil_instructions);
+                        Ok(EvalPlan { instructions, binding })
+                    }
+                }
+            }
             SingleValue::ObjectExpression(expr) => {
                 let length_at = self.next_addr.offset_by(1);
                 let key_count = expr.properties.len();
@@ -314,6 +314,47 @@ fn assign_bool() {
     }
 }
 
+#[test]
+fn if_with_known_condition() {
+    let program = "let x = if false { 1 } else if true { 2 } else { 3 }";
+    let (plan, scope, _) = must_plan(program);
+    assert_eq!(
+        plan,
+        vec![Instruction::from_range(
+            InstructionKind::SetPrimitive {
+                address: Address::ZERO,
+                value: 2i64.into(),
+            },
+            SourceRange([38, 39])
+        )]
+    );
+    assert_eq!(scope.get("x"), Some(&EpBinding::Single(Address::ZERO)));
+}
+
+#[test]
+fn if_with_unknown_condition() {
+    let err = should_not_compile("let c = true\nlet x = if c { 1 } else { 2 }");
+    assert_eq!(err, CompileError::UnknownCondition);
+}
+
+#[test]
+fn comparisons_not_supported() {
+    let err = should_not_compile("let x = 1 < 2");
+    assert_eq!(err, CompileError::UnsupportedOperator("<".to_owned()));
+}
+
+#[test]
+fn loops_not_supported() {
+    let err = should_not_compile("for i in [0..2] {}");
+    assert_eq!(err, CompileError::LoopsNotSupported);
+}
+
+#[test]
+fn imports_not_supported() {
+    let err = should_not_compile("import { cube } from \"shapes.kcl\"");
+    assert_eq!(err, CompileError::ImportsNotSupported);
+}
+
 #[test]
 fn aliases() {
     let program = "
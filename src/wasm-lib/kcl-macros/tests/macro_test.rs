@@ -1,7 +1,7 @@
 extern crate alloc;
 use kcl_lib::ast::types::{
-    BodyItem, Identifier, Literal, LiteralValue, NonCodeMeta, Program, Value, VariableDeclaration, VariableDeclarator,
-    VariableKind,
+    BodyItem, Identifier, ItemVisibility, Literal, LiteralValue, NonCodeMeta, Program, Value, VariableDeclaration,
+    VariableDeclarator, VariableKind,
 };
 use kcl_macros::parse;
 use pretty_assertions::assert_eq;
@@ -30,6 +30,7 @@ fn basic() {
                     raw: "4".to_owned(),
                 })),
             }],
+            visibility: ItemVisibility::Default,
             kind: VariableKind::Const,
         })],
         non_code_meta: NonCodeMeta::default(),
@@ -0,0 +1,979 @@
+//! Finds mistakes in a program without executing it, so they can be reported without an engine, and
+//! works out where each name is declared and used, for going to definitions and finding references.
+//!
+//! Names are resolved the same way the executor resolves them: each function call, loop iteration and
+//! if branch gets its own scope, and function bodies can use any name declared around the function,
+//! even after it.
+
+use std::collections::HashMap;
+
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+use crate::{
+    ast::{
+        types::{
+            BinaryPart, BodyItem, CallExpression, ForIterable, FunctionExpression, Identifier, IfExpression,
+            ItemVisibility, LiteralIdentifier, LiteralValue, MemberExpression, MemberObject, Program, Value,
+        },
+        walk::{walk, Node},
+    },
+    executor::SourceRange,
+    lint::Suggestion,
+    std::{FunctionKind, StdLib},
+};
+
+/// How serious a finding is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The program works, but probably not as intended.
+    Warning,
+    /// The program will fail when it's executed.
+    Error,
+}
+
+/// A problem found in a program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    pub source_range: SourceRange,
+    /// A change which fixes the problem, if there's an obvious one.
+    pub suggestion: Option<Suggestion>,
+}
+
+impl Finding {
+    fn error(message: String, source_range: SourceRange) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            source_range,
+            suggestion: None,
+        }
+    }
+
+    fn warning(message: String, source_range: SourceRange) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            source_range,
+            suggestion: None,
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: Option<Suggestion>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    pub fn to_lsp_diagnostic(&self, code: &str) -> Diagnostic {
+        Diagnostic {
+            range: self.source_range.to_lsp_range(code),
+            severity: Some(match self.severity {
+                Severity::Warning => DiagnosticSeverity::WARNING,
+                Severity::Error => DiagnosticSeverity::ERROR,
+            }),
+            code: None,
+            code_description: None,
+            source: Some("kcl".to_string()),
+            message: self.message.clone(),
+            related_information: None,
+            tags: None,
+            data: self.suggestion.as_ref().map(|suggestion| suggestion.to_lsp_data(code)),
+        }
+    }
+}
+
+/// Find undefined names, unused names, redefinitions, calls with the wrong number of arguments,
+/// and `%` used outside of a pipe expression.
+/// Findings are sorted by where they are in the program.
+pub fn analyze(program: &Program, stdlib: &StdLib) -> Vec<Finding> {
+    let mut findings = run(program, stdlib).findings;
+    findings.sort_by_key(|finding| finding.source_range.start());
+    findings
+}
+
+/// A name or segment tag, where it's declared, and everywhere it's used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub declaration: SourceRange,
+    /// In the order they appear in the program.
+    pub uses: Vec<SourceRange>,
+}
+
+impl Symbol {
+    /// Whether the position is on the symbol's declaration, or on one of its uses.
+    pub fn is_at(&self, pos: usize) -> bool {
+        std::iter::once(&self.declaration)
+            .chain(&self.uses)
+            .any(|source_range| source_range.start() <= pos && pos <= source_range.end())
+    }
+}
+
+/// Every name declared in the program and every segment tag, in the order they're declared.
+/// Uses of names are resolved through scopes the way the executor resolves them, and tags are
+/// matched by their string to the closest tag with that name before them.
+pub fn symbols(program: &Program, stdlib: &StdLib) -> Vec<Symbol> {
+    let analyzer = run(program, stdlib);
+    let mut symbols: Vec<Symbol> = analyzer
+        .declarations
+        .into_iter()
+        .map(|(name, declaration)| Symbol {
+            name,
+            declaration,
+            uses: Vec::new(),
+        })
+        .collect();
+    let index_of: HashMap<SourceRange, usize> = symbols
+        .iter()
+        .enumerate()
+        .map(|(index, symbol)| (symbol.declaration, index))
+        .collect();
+    for (use_range, declaration) in analyzer.references {
+        if let Some(index) = index_of.get(&declaration) {
+            symbols[*index].uses.push(use_range);
+        }
+    }
+
+    symbols.extend(tag_symbols(program, stdlib));
+    for symbol in &mut symbols {
+        symbol.uses.sort_by_key(|source_range| source_range.start());
+    }
+    symbols.sort_by_key(|symbol| symbol.declaration.start());
+    symbols
+}
+
+/// The symbol at a position, whether that's where it's declared or where it's used.
+pub fn symbol_at(program: &Program, stdlib: &StdLib, pos: usize) -> Option<Symbol> {
+    symbols(program, stdlib).into_iter().find(|symbol| symbol.is_at(pos))
+}
+
+/// Segment tags, which are declared by passing a string as the tag of a sketch function, and used by
+/// passing the same string to functions like `segLen`, or looking it up as a property.
+fn tag_symbols(program: &Program, stdlib: &StdLib) -> Vec<Symbol> {
+    let mut tag_indexes: HashMap<&str, Option<usize>> = HashMap::new();
+    let mut tags: Vec<Symbol> = Vec::new();
+    let mut strings: Vec<(&str, SourceRange)> = Vec::new();
+    walk(program, &mut |node, ancestors| match node {
+        Node::CallExpression(call) => {
+            let index = *tag_indexes
+                .entry(call.callee.name.as_str())
+                .or_insert_with(|| stdlib.tag_arg_index(&call.callee.name));
+            if let Some(Value::Literal(tag)) = index.and_then(|index| call.arguments.get(index)) {
+                if let LiteralValue::String(name) = &tag.value {
+                    tags.push(Symbol {
+                        name: name.clone(),
+                        declaration: tag.as_ref().into(),
+                        uses: Vec::new(),
+                    });
+                }
+            }
+        }
+        Node::Literal(literal) => {
+            if let LiteralValue::String(s) = &literal.value {
+                strings.push((s, literal.into()));
+            }
+        }
+        // Tags can also be looked up as properties, e.g. `part.value.seg01`.
+        Node::Identifier(identifier) => {
+            if let Some(Node::MemberExpression(member)) = ancestors.last() {
+                if !member.computed && SourceRange::from(&member.property) == identifier.into() {
+                    strings.push((&identifier.name, identifier.into()));
+                }
+            }
+        }
+        _ => {}
+    });
+
+    for (name, source_range) in strings {
+        let same_name = || tags.iter().enumerate().filter(|(_, tag)| tag.name == name);
+        if same_name().any(|(_, tag)| tag.declaration == source_range) {
+            continue;
+        }
+        let closest_before = same_name()
+            .rfind(|(_, tag)| tag.declaration.start() < source_range.start())
+            .or_else(|| same_name().next());
+        if let Some((index, _)) = closest_before {
+            tags[index].uses.push(source_range);
+        }
+    }
+    tags
+}
+
+/// Resolve every name in the program, noting mistakes along the way.
+fn run<'a>(program: &Program, stdlib: &'a StdLib) -> Analyzer<'a> {
+    let mut analyzer = Analyzer {
+        stdlib,
+        scopes: Vec::new(),
+        arg_counts: HashMap::new(),
+        findings: Vec::new(),
+        declarations: Vec::new(),
+        references: Vec::new(),
+    };
+    let root = analyzer.new_scope(None);
+    for name in crate::executor::prelude().into_keys() {
+        analyzer.scopes[root].bindings.insert(
+            name,
+            Binding {
+                source_range: SourceRange::default(),
+                kind: BindingKind::Prelude,
+                used: false,
+            },
+        );
+    }
+    analyzer.analyze_body(program, root, true);
+    analyzer
+}
+
+#[derive(Debug)]
+struct Scope {
+    bindings: HashMap<String, Binding>,
+    parent: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Binding {
+    source_range: SourceRange,
+    kind: BindingKind,
+    used: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    /// Declared for every program, e.g. `ZERO`.
+    Prelude,
+    /// Declared with `const`, `let` or `var`.
+    Variable {
+        exported: bool,
+    },
+    /// Declared with `fn`, taking between `min` and `max` arguments.
+    Function {
+        min: usize,
+        max: usize,
+        exported: bool,
+    },
+    Parameter,
+    LoopVariable,
+    Import,
+}
+
+struct Analyzer<'a> {
+    stdlib: &'a StdLib,
+    /// Every scope in the program. Each one refers to its parent by its index.
+    scopes: Vec<Scope>,
+    /// How many arguments each stdlib function that's been called takes, at least and at most.
+    arg_counts: HashMap<String, (usize, usize)>,
+    findings: Vec<Finding>,
+    /// Every name declared in the program, and where.
+    declarations: Vec<(String, SourceRange)>,
+    /// Where each name is used, and where the name it refers to was declared.
+    references: Vec<(SourceRange, SourceRange)>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new_scope(&mut self, parent: Option<usize>) -> usize {
+        self.scopes.push(Scope {
+            bindings: HashMap::new(),
+            parent,
+        });
+        self.scopes.len() - 1
+    }
+
+    /// Check each item in a body, in a scope that has already been created for it.
+    fn analyze_body<'p>(&mut self, program: &'p Program, scope: usize, is_root: bool) {
+        // Function bodies run later, once every name around them has been declared,
+        // so they're checked after the rest of the body.
+        let mut functions: Vec<&'p FunctionExpression> = Vec::new();
+
+        for item in &program.body {
+            match item {
+                BodyItem::ExpressionStatement(expression_statement) => {
+                    self.visit_value(&expression_statement.expression, scope, false, &mut functions);
+                }
+                BodyItem::VariableDeclaration(variable_declaration) => {
+                    let exported = variable_declaration.visibility == ItemVisibility::Export;
+                    for declaration in &variable_declaration.declarations {
+                        let kind = if let Value::FunctionExpression(function_expression) = &declaration.init {
+                            let range = function_expression.number_of_args();
+                            BindingKind::Function {
+                                min: *range.start(),
+                                max: *range.end(),
+                                exported,
+                            }
+                        } else {
+                            BindingKind::Variable { exported }
+                        };
+                        // The value is evaluated before its name is declared.
+                        self.visit_value(&declaration.init, scope, false, &mut functions);
+                        self.declare(scope, &declaration.id, kind);
+                    }
+                }
+                BodyItem::ReturnStatement(return_statement) => {
+                    self.visit_value(&return_statement.argument, scope, false, &mut functions);
+                }
+                BodyItem::ForStatement(for_statement) => {
+                    match &for_statement.iterable {
+                        ForIterable::Range { from, to, .. } => {
+                            self.visit_value(from, scope, false, &mut functions);
+                            self.visit_value(to, scope, false, &mut functions);
+                        }
+                        ForIterable::Array { value } => self.visit_value(value, scope, false, &mut functions),
+                    }
+                    let loop_scope = self.new_scope(Some(scope));
+                    self.declare(loop_scope, &for_statement.variable, BindingKind::LoopVariable);
+                    self.analyze_body(&for_statement.body, loop_scope, false);
+                }
+                BodyItem::ImportStatement(import_statement) => {
+                    for item in &import_statement.items {
+                        self.declare(scope, item.identifier(), BindingKind::Import);
+                    }
+                }
+            }
+        }
+
+        for function_expression in functions {
+            let function_scope = self.new_scope(Some(scope));
+            for param in &function_expression.params {
+                self.declare(function_scope, &param.identifier, BindingKind::Parameter);
+            }
+            self.analyze_body(&function_expression.body, function_scope, false);
+        }
+
+        self.report_unused(scope, is_root);
+    }
+
+    fn report_unused(&mut self, scope: usize, is_root: bool) {
+        let mut unused: Vec<_> = self.scopes[scope]
+            .bindings
+            .iter()
+            .filter(|(name, binding)| !binding.used && !name.starts_with('_'))
+            .filter_map(|(name, binding)| {
+                let what = match binding.kind {
+                    // Values at the top level of a file are what it models, so they're used by being there.
+                    BindingKind::Variable { exported: false } if !is_root => "variable",
+                    BindingKind::Function { exported: false, .. } => "function",
+                    BindingKind::Import => "import",
+                    _ => return None,
+                };
+                Some(Finding::warning(
+                    format!("The {} `{}` is never used", what, name),
+                    binding.source_range,
+                ))
+            })
+            .collect();
+        unused.sort_by_key(|finding| finding.source_range.start());
+        self.findings.extend(unused);
+    }
+
+    fn declare(&mut self, scope: usize, identifier: &Identifier, kind: BindingKind) {
+        let source_range = identifier.into();
+        let bindings = &mut self.scopes[scope].bindings;
+        if bindings.contains_key(&identifier.name) {
+            self.findings.push(Finding::error(
+                format!("Cannot redefine {}", identifier.name),
+                source_range,
+            ));
+            return;
+        }
+        bindings.insert(
+            identifier.name.clone(),
+            Binding {
+                source_range,
+                kind,
+                used: false,
+            },
+        );
+        self.declarations.push((identifier.name.clone(), source_range));
+    }
+
+    /// Find the binding a name used at `use_range` refers to from the given scope, and mark it as used.
+    fn resolve(&mut self, name: &str, use_range: SourceRange, scope: usize) -> Option<BindingKind> {
+        let mut current = Some(scope);
+        while let Some(index) = current {
+            if let Some(binding) = self.scopes[index].bindings.get_mut(name) {
+                binding.used = true;
+                if binding.kind != BindingKind::Prelude {
+                    self.references.push((use_range, binding.source_range));
+                }
+                return Some(binding.kind);
+            }
+            current = self.scopes[index].parent;
+        }
+        None
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier, scope: usize) {
+        if self.resolve(&identifier.name, identifier.into(), scope).is_none() {
+            self.report_undefined(identifier, scope, false);
+        }
+    }
+
+    /// Report a name which isn't declared, suggesting a similar name which is.
+    fn report_undefined(&mut self, identifier: &Identifier, scope: usize, is_call: bool) {
+        let mut candidates: Vec<String> = Vec::new();
+        let mut current = Some(scope);
+        while let Some(index) = current {
+            candidates.extend(self.scopes[index].bindings.keys().cloned());
+            current = self.scopes[index].parent;
+        }
+        if is_call {
+            candidates.extend(self.stdlib.combined().into_keys());
+        }
+
+        let suggestion = closest_name(&identifier.name, &candidates).map(|name| Suggestion {
+            title: format!("Did you mean `{}`?", name),
+            edits: vec![(identifier.into(), name.to_string())],
+        });
+        self.findings.push(
+            Finding::error(format!("`{}` is not defined", identifier.name), identifier.into())
+                .with_suggestion(suggestion),
+        );
+    }
+
+    /// Check a value, and every value inside it.
+    /// `in_pipe` is whether `%` refers to something here.
+    fn visit_value<'p>(
+        &mut self,
+        value: &'p Value,
+        scope: usize,
+        in_pipe: bool,
+        functions: &mut Vec<&'p FunctionExpression>,
+    ) {
+        match value {
+            Value::Literal(_) | Value::None(_) => {}
+            Value::Identifier(identifier) => self.visit_identifier(identifier, scope),
+            Value::BinaryExpression(binary_expression) => {
+                self.visit_part(&binary_expression.left, scope, in_pipe, functions);
+                self.visit_part(&binary_expression.right, scope, in_pipe, functions);
+            }
+            Value::UnaryExpression(unary_expression) => {
+                self.visit_part(&unary_expression.argument, scope, in_pipe, functions)
+            }
+            Value::CallExpression(call_expression) => self.visit_call(call_expression, scope, in_pipe, functions),
+            Value::PipeExpression(pipe_expression) => {
+                // The first expression can only use the `%` of a pipe around this one.
+                for (index, value) in pipe_expression.body.iter().enumerate() {
+                    self.visit_value(value, scope, in_pipe || index > 0, functions);
+                }
+            }
+            Value::PipeSubstitution(pipe_substitution) => {
+                if !in_pipe {
+                    self.findings.push(Finding::error(
+                        "`%` can only be used inside a pipe expression, after its first `|>`".to_string(),
+                        pipe_substitution.into(),
+                    ));
+                }
+            }
+            Value::ArrayExpression(array_expression) => {
+                for element in &array_expression.elements {
+                    self.visit_value(element, scope, in_pipe, functions);
+                }
+            }
+            Value::ObjectExpression(object_expression) => {
+                for property in &object_expression.properties {
+                    self.visit_value(&property.value, scope, in_pipe, functions);
+                }
+            }
+            Value::MemberExpression(member_expression) => self.visit_member(member_expression, scope),
+            Value::FunctionExpression(function_expression) => functions.push(function_expression),
+            Value::IfExpression(if_expression) => self.visit_if(if_expression, scope, in_pipe, functions),
+        }
+    }
+
+    fn visit_part<'p>(
+        &mut self,
+        part: &'p BinaryPart,
+        scope: usize,
+        in_pipe: bool,
+        functions: &mut Vec<&'p FunctionExpression>,
+    ) {
+        match part {
+            BinaryPart::Literal(_) => {}
+            BinaryPart::Identifier(identifier) => self.visit_identifier(identifier, scope),
+            BinaryPart::BinaryExpression(binary_expression) => {
+                self.visit_part(&binary_expression.left, scope, in_pipe, functions);
+                self.visit_part(&binary_expression.right, scope, in_pipe, functions);
+            }
+            BinaryPart::CallExpression(call_expression) => self.visit_call(call_expression, scope, in_pipe, functions),
+            BinaryPart::UnaryExpression(unary_expression) => {
+                self.visit_part(&unary_expression.argument, scope, in_pipe, functions)
+            }
+            BinaryPart::MemberExpression(member_expression) => self.visit_member(member_expression, scope),
+        }
+    }
+
+    fn visit_member(&mut self, member_expression: &MemberExpression, scope: usize) {
+        match &member_expression.object {
+            MemberObject::MemberExpression(object) => self.visit_member(object, scope),
+            MemberObject::Identifier(identifier) => self.visit_identifier(identifier, scope),
+        }
+        if member_expression.computed {
+            if let LiteralIdentifier::Identifier(identifier) = &member_expression.property {
+                self.visit_identifier(identifier, scope);
+            }
+        }
+    }
+
+    fn visit_if<'p>(
+        &mut self,
+        if_expression: &'p IfExpression,
+        scope: usize,
+        in_pipe: bool,
+        functions: &mut Vec<&'p FunctionExpression>,
+    ) {
+        self.visit_value(&if_expression.cond, scope, in_pipe, functions);
+        let branch_scope = self.new_scope(Some(scope));
+        self.analyze_body(&if_expression.then_val, branch_scope, false);
+        for else_if in &if_expression.else_ifs {
+            self.visit_value(&else_if.cond, scope, in_pipe, functions);
+            let branch_scope = self.new_scope(Some(scope));
+            self.analyze_body(&else_if.then_val, branch_scope, false);
+        }
+        if let Some(final_else) = &if_expression.final_else {
+            let branch_scope = self.new_scope(Some(scope));
+            self.analyze_body(final_else, branch_scope, false);
+        }
+    }
+
+    fn visit_call<'p>(
+        &mut self,
+        call_expression: &'p CallExpression,
+        scope: usize,
+        in_pipe: bool,
+        functions: &mut Vec<&'p FunctionExpression>,
+    ) {
+        for arg in &call_expression.arguments {
+            self.visit_value(arg, scope, in_pipe, functions);
+        }
+
+        // The stdlib takes priority over user-defined functions with the same name, like it does in the executor.
+        let name = &call_expression.callee.name;
+        let (min, max) = if self.stdlib.contains_key(name) {
+            self.stdlib_arg_count(name)
+        } else {
+            match self.resolve(name, (&call_expression.callee).into(), scope) {
+                Some(BindingKind::Function { min, max, .. }) => (min, max),
+                Some(_) => return,
+                None => {
+                    self.report_undefined(&call_expression.callee, scope, true);
+                    return;
+                }
+            }
+        };
+
+        let n = call_expression.arguments.len();
+        if n < min || n > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{min}-{max}")
+            };
+            self.findings.push(Finding::error(
+                format!("`{name}` expects {expected} arguments, but it was given {n}"),
+                call_expression.into(),
+            ));
+        }
+    }
+
+    fn stdlib_arg_count(&mut self, name: &str) -> (usize, usize) {
+        if let Some(count) = self.arg_counts.get(name) {
+            return *count;
+        }
+        let args = match self.stdlib.get_either(name) {
+            FunctionKind::Core(func) => func.args(),
+            FunctionKind::Std(func) => func.std_lib().args(),
+            FunctionKind::UserDefined => Vec::new(),
+        };
+        let count = match args.as_slice() {
+            // Functions like `min` take any number of arguments, and collect them into an array.
+            [arg] if arg.name == "args" => (0, usize::MAX),
+            _ => (args.iter().filter(|arg| arg.required).count(), args.len()),
+        };
+        self.arg_counts.insert(name.to_string(), count);
+        count
+    }
+}
+
+/// The name most like `name`, if any are close enough to be a typo of it.
+/// Differences in case don't count, so `lineto` is as close as can be to `lineTo`.
+fn closest_name<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .iter()
+        .filter(|candidate| candidate.as_str() != name)
+        .map(|candidate| {
+            (
+                edit_distance(&candidate.to_lowercase(), &name.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// How many characters have to be inserted, removed, replaced or swapped with their neighbour to
+/// turn one string into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // `distances[i][j]` is the distance between the first `i` characters of `a` and the first `j` of `b`.
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let replace = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = replace.min(distances[i - 1][j] + 1).min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn analyze_code(code: &str) -> Vec<(Severity, String, SourceRange)> {
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        analyze(&program, &StdLib::new())
+            .into_iter()
+            .map(|finding| (finding.severity, finding.message, finding.source_range))
+            .collect()
+    }
+
+    #[test]
+    fn valid_program_has_no_findings() {
+        let findings = analyze_code(
+            r#"const width = 4
+fn box = (w, h?) => {
+  const base = startSketchOn('XY')
+    |> startProfileAt([0, 0], %)
+    |> line([w, 0], %)
+    |> line([0, later], %)
+    |> close(%)
+  return extrude(h, base)
+}
+const later = QUARTER_TURN
+fn fact = (n) => { return if n <= 1 { 1 } else { n * fact(n - 1) } }
+const sizes = [1, 2, 3]
+for i in [0..2] {
+  box(sizes[i], fact(i))
+}
+const part = box(width)"#,
+        );
+        assert_eq!(findings, vec![]);
+    }
+
+    #[test]
+    fn undefined_names() {
+        let findings = analyze_code(
+            r#"const a = b + 1
+const sg = startSketchOn('XY') |> lineto([1, 1], %)
+fn f = () => {
+  const inner = 1
+  return inner
+}
+const c = inner
+const d = a.x[missing]
+const g = f()"#,
+        );
+        assert_eq!(
+            findings,
+            vec![
+                (Severity::Error, "`b` is not defined".to_string(), SourceRange([10, 11])),
+                (
+                    Severity::Error,
+                    "`lineto` is not defined".to_string(),
+                    SourceRange([50, 56])
+                ),
+                (
+                    Severity::Error,
+                    "`inner` is not defined".to_string(),
+                    SourceRange([128, 133])
+                ),
+                (
+                    Severity::Error,
+                    "`missing` is not defined".to_string(),
+                    SourceRange([148, 155])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn undefined_names_suggest_similar_names() {
+        let code = r#"const width = 4
+const sg = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> lineto([widht, 1], %)
+  |> line([qqq, 1], %)"#;
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let suggestions: Vec<_> = analyze(&program, &StdLib::new())
+            .into_iter()
+            .map(|finding| (finding.message, finding.suggestion))
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                (
+                    "`lineto` is not defined".to_string(),
+                    Some(Suggestion {
+                        title: "Did you mean `lineTo`?".to_string(),
+                        edits: vec![(SourceRange([83, 89]), "lineTo".to_string())],
+                    })
+                ),
+                (
+                    "`widht` is not defined".to_string(),
+                    Some(Suggestion {
+                        title: "Did you mean `width`?".to_string(),
+                        edits: vec![(SourceRange([91, 96]), "width".to_string())],
+                    })
+                ),
+                ("`qqq` is not defined".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_names() {
+        let findings = analyze_code(
+            r#"import { used, unused } from "lib.kcl"
+fn helper = (x) => {
+  const temp = x
+  const _ignored = x
+  return x
+}
+fn check = () => { return used }
+const result = check()"#,
+        );
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    Severity::Warning,
+                    "The import `unused` is never used".to_string(),
+                    SourceRange([15, 21])
+                ),
+                (
+                    Severity::Warning,
+                    "The function `helper` is never used".to_string(),
+                    SourceRange([42, 48])
+                ),
+                (
+                    Severity::Warning,
+                    "The variable `temp` is never used".to_string(),
+                    SourceRange([68, 72])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn redefinitions_and_shadowing() {
+        let findings = analyze_code(
+            r#"const a = 1
+const a = 2
+fn f = (a) => {
+  const b = a
+  const b = 2
+  return b
+}
+const c = f(a)"#,
+        );
+        assert_eq!(
+            findings,
+            vec![
+                (Severity::Error, "Cannot redefine a".to_string(), SourceRange([18, 19])),
+                (Severity::Error, "Cannot redefine b".to_string(), SourceRange([62, 63])),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_argument_counts() {
+        let findings = analyze_code(
+            r#"fn f = (a, b?) => { return a }
+const x = f()
+const y = f(1, 2, 3)
+const z = legLen(1)
+const sg = startSketchOn('XY') |> startProfileAt([0, 0], %) |> line(%)"#,
+        );
+        assert_eq!(
+            findings,
+            vec![
+                (
+                    Severity::Error,
+                    "`f` expects 1-2 arguments, but it was given 0".to_string(),
+                    SourceRange([41, 44])
+                ),
+                (
+                    Severity::Error,
+                    "`f` expects 1-2 arguments, but it was given 3".to_string(),
+                    SourceRange([55, 65])
+                ),
+                (
+                    Severity::Error,
+                    "`legLen` expects 2 arguments, but it was given 1".to_string(),
+                    SourceRange([76, 85])
+                ),
+                (
+                    Severity::Error,
+                    "`line` expects 2-3 arguments, but it was given 1".to_string(),
+                    SourceRange([149, 156])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipe_substitution_outside_pipe() {
+        let findings = analyze_code(
+            r#"const a = line([1, 1], %)
+const b = startSketchOn('XY') |> startProfileAt([0, 0], %)
+fn f = () => { return % }
+const c = f()"#,
+        );
+        let message = "`%` can only be used inside a pipe expression, after its first `|>`".to_string();
+        assert_eq!(
+            findings,
+            vec![
+                (Severity::Error, message.clone(), SourceRange([23, 24])),
+                (Severity::Error, message, SourceRange([107, 108])),
+            ]
+        );
+    }
+
+    /// A 0-indexed line and column.
+    type LineColumn = (usize, usize);
+
+    /// Each symbol's name, and the lines and columns of its declaration and uses.
+    fn symbols_in(code: &str) -> Vec<(String, LineColumn, Vec<LineColumn>)> {
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let line_column = |source_range: SourceRange| {
+            let before = &code[..source_range.start()];
+            let line = before.matches('\n').count();
+            (
+                line,
+                before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or_default(),
+            )
+        };
+        symbols(&program, &StdLib::new())
+            .into_iter()
+            .map(|symbol| {
+                (
+                    symbol.name,
+                    line_column(symbol.declaration),
+                    symbol.uses.into_iter().map(line_column).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn symbols_follow_scopes() {
+        let symbols = symbols_in(
+            r#"const size = 4
+fn double = (size) => {
+  return size * 2
+}
+const total = double(size) + double(ZERO)
+for i in [0..size] {
+  const size = i
+  const twice = double(size)
+}
+"#,
+        );
+        let symbol = |name: &str, declaration, uses: &[(usize, usize)]| (name.to_string(), declaration, uses.to_vec());
+        assert_eq!(
+            symbols,
+            vec![
+                symbol("size", (0, 6), &[(4, 21), (5, 13)]),
+                symbol("double", (1, 3), &[(4, 14), (4, 29), (7, 16)]),
+                symbol("size", (1, 13), &[(2, 9)]),
+                symbol("total", (4, 6), &[]),
+                symbol("i", (5, 4), &[(6, 15)]),
+                symbol("size", (6, 8), &[(7, 23)]),
+                symbol("twice", (7, 8), &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn symbols_include_tags() {
+        let symbols = symbols_in(
+            r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "side")
+  |> angledLine([45, segLen("side", %)], %, "top")
+  |> close(%)
+const sideEnd = part.value.side
+const other = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 5], %, "side")
+  |> line([segLen("side", %), 0], %)
+"#,
+        );
+        let symbol = |name: &str, declaration, uses: &[(usize, usize)]| (name.to_string(), declaration, uses.to_vec());
+        assert_eq!(
+            symbols,
+            vec![
+                symbol("part", (0, 6), &[(5, 16)]),
+                symbol("side", (2, 22), &[(3, 28), (5, 27)]),
+                symbol("top", (3, 44), &[]),
+                symbol("sideEnd", (5, 6), &[]),
+                symbol("other", (6, 6), &[]),
+                symbol("side", (8, 21), &[(9, 18)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn symbol_at_use_or_declaration() {
+        let code = "const width = 4\nconst area = width * width\n";
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let stdlib = StdLib::new();
+
+        let width = Symbol {
+            name: "width".to_string(),
+            declaration: SourceRange([6, 11]),
+            uses: vec![SourceRange([29, 34]), SourceRange([37, 42])],
+        };
+        assert_eq!(symbol_at(&program, &stdlib, 8), Some(width.clone()));
+        // Just after a use still counts, like an editor's cursor at the end of a word.
+        assert_eq!(symbol_at(&program, &stdlib, 42), Some(width));
+        assert_eq!(symbol_at(&program, &stdlib, 35).map(|symbol| symbol.name), None);
+        assert_eq!(symbol_at(&program, &stdlib, 4), None);
+    }
+
+    /// The examples in the stdlib docs should all be free of mistakes.
+    #[test]
+    fn stdlib_examples_have_no_errors() {
+        let stdlib = StdLib::new();
+        for (name, func) in stdlib.combined() {
+            for example in func.examples() {
+                let tokens = crate::token::lexer(&example).unwrap();
+                let program = crate::parser::Parser::new(tokens).ast().unwrap();
+                let errors: Vec<_> = analyze(&program, &stdlib)
+                    .into_iter()
+                    .filter(|finding| finding.severity == Severity::Error)
+                    .collect();
+                assert_eq!(errors, vec![], "errors in example for {name}:\n{example}");
+            }
+        }
+    }
+}
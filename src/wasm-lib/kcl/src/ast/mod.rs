@@ -1,2 +1,6 @@
+pub mod analysis;
 pub mod modify;
+pub mod refactor;
+pub mod type_check;
 pub mod types;
+pub mod walk;
@@ -256,7 +256,7 @@ fn create_start_sketch_on(
     Ok(VariableDeclarator::new(name, PipeExpression::new(pipe_body).into()))
 }
 
-fn round_before_recast(num: f64) -> f64 {
+pub(crate) fn round_before_recast(num: f64) -> f64 {
     // We use 2 decimal places.
     (num * 100.0).round() / 100.0
 }
@@ -0,0 +1,559 @@
+//! Changes to a program which keep what it does the same, like extracting an expression into a variable,
+//! for the language server to offer as code actions.
+//!
+//! Each refactor edits a copy of the program with [`Program::replace_value`] and friends, so the result
+//! is the whole program, recast the same way the app recasts it after editing a sketch.
+
+use std::collections::HashSet;
+
+use crate::{
+    ast::{
+        analysis,
+        modify::round_before_recast,
+        types::{
+            ArrayExpression, BinaryPart, BodyItem, CallExpression, FunctionExpression, Identifier, ItemVisibility,
+            Literal, LiteralValue, NonCodeValue, Program, ReturnStatement, Value, VariableDeclaration,
+            VariableDeclarator, VariableKind,
+        },
+        walk::{is_top_level, walk, Node},
+    },
+    executor::{MemoryItem, Path, ProgramMemory, SourceRange},
+    std::StdLib,
+};
+
+/// What sort of change a refactor makes, which editors use to group them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefactorKind {
+    /// Move part of the program into a new variable or function.
+    Extract,
+    /// Replace a variable with its value.
+    Inline,
+    /// Write the same thing a different way.
+    Rewrite,
+}
+
+/// A change to a program, and what the program is after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refactor {
+    pub title: String,
+    pub kind: RefactorKind,
+    pub program: Program,
+}
+
+/// Every refactor which applies to the code in `range`.
+/// Some refactors need the values from executing the program, which are in `memory` if it was executed.
+pub fn refactors(
+    program: &Program,
+    stdlib: &StdLib,
+    range: SourceRange,
+    memory: Option<&ProgramMemory>,
+) -> Vec<Refactor> {
+    [
+        extract_to_variable(program, range),
+        inline_variable(program, stdlib, range),
+        line_to_line_to(program, range, memory),
+        pipe_to_function(program, range),
+        add_tag(program, stdlib, range),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Move the expression in `range` into a new variable, declared before the statement it's in.
+fn extract_to_variable(program: &Program, range: SourceRange) -> Option<Refactor> {
+    let expression = innermost(program, range, |node, ancestors| {
+        let extractable = matches!(
+            node,
+            Node::Literal(_)
+                | Node::BinaryExpression(_)
+                | Node::CallExpression(_)
+                | Node::ArrayExpression(_)
+                | Node::ObjectExpression(_)
+                | Node::MemberExpression(_)
+                | Node::UnaryExpression(_)
+                | Node::IfExpression(_)
+                | Node::PipeExpression(_)
+        );
+        // Moving something out of a function or loop would change which names it refers to, and
+        // moving a whole value or a step of a pipe doesn't make anything clearer.
+        extractable
+            && is_top_level(ancestors)
+            && !matches!(
+                ancestors.last(),
+                Some(Node::VariableDeclarator(_) | Node::PipeExpression(_) | Node::MemberExpression(_))
+            )
+            && !contains_pipe_substitution(node)
+    })?;
+
+    let name = unique_name(program, "newVar");
+    let index = body_index(program, expression.source_range())?;
+    let value = node_to_value(expression)?;
+
+    let mut new_program = program.clone();
+    new_program.replace_value(expression.source_range(), identifier(&name));
+    if new_program == *program {
+        return None;
+    }
+    insert_item(
+        &mut new_program,
+        index,
+        declaration(VariableDeclarator::new(&name, value), VariableKind::Const),
+    );
+
+    Some(Refactor {
+        title: format!("Extract to variable `{}`", name),
+        kind: RefactorKind::Extract,
+        program: new_program,
+    })
+}
+
+/// Replace every use of the top level variable in `range` with its value, and remove the variable.
+fn inline_variable(program: &Program, stdlib: &StdLib, range: SourceRange) -> Option<Refactor> {
+    let symbol = analysis::symbol_at(program, stdlib, range.start())?;
+    let index = program.body.iter().position(|item| match item {
+        BodyItem::VariableDeclaration(declaration) => {
+            declaration.declarations.len() == 1
+                && SourceRange::from(&declaration.declarations[0].id) == symbol.declaration
+        }
+        _ => false,
+    })?;
+    let BodyItem::VariableDeclaration(declaration) = &program.body[index] else {
+        return None;
+    };
+    let init = &declaration.declarations[0].init;
+    // Other files can use exported variables, and functions are called rather than used as values.
+    if declaration.visibility == ItemVisibility::Export
+        || declaration.kind == VariableKind::Fn
+        || matches!(init, Value::FunctionExpression(_))
+        || symbol.uses.is_empty()
+    {
+        return None;
+    }
+
+    let init_node = Node::from(init);
+    // Calls would happen once for every use, instead of once.
+    if symbol.uses.len() > 1 && contains(init_node, &|node| matches!(node, Node::CallExpression(_))) {
+        return None;
+    }
+    let init_has_names = contains(init_node, &|node| matches!(node, Node::Identifier(_)));
+    let init_is_operand = BinaryPart::from_value(init.clone()).is_some();
+
+    // Check every use can be replaced by the value.
+    let mut replaceable = 0;
+    walk(program, &mut |node, ancestors| {
+        let Node::Identifier(identifier) = node else {
+            return;
+        };
+        if !symbol.uses.contains(&SourceRange::from(identifier)) {
+            return;
+        }
+        let fits = match ancestors.last() {
+            // Operators can only be used with some kinds of values.
+            Some(Node::BinaryExpression(_) | Node::UnaryExpression(_)) => init_is_operand,
+            Some(Node::CallExpression(call)) => !std::ptr::eq(&call.callee, identifier),
+            Some(
+                Node::ArrayExpression(_)
+                | Node::PipeExpression(_)
+                | Node::ExpressionStatement(_)
+                | Node::ReturnStatement(_)
+                | Node::IfExpression(_)
+                | Node::ElseIf(_)
+                | Node::VariableDeclarator(_)
+                | Node::ObjectProperty(_)
+                | Node::ForStatement(_),
+            ) => true,
+            _ => false,
+        };
+        // Inside a function or loop, the names the value uses might mean something else.
+        if fits && (is_top_level(ancestors) || !init_has_names) {
+            replaceable += 1;
+        }
+    });
+    if replaceable != symbol.uses.len() {
+        return None;
+    }
+
+    let mut new_program = program.clone();
+    for use_range in &symbol.uses {
+        new_program.replace_value(*use_range, init.clone());
+    }
+    remove_item(&mut new_program, index);
+
+    Some(Refactor {
+        title: format!("Inline variable `{}`", symbol.name),
+        kind: RefactorKind::Inline,
+        program: new_program,
+    })
+}
+
+/// Replace the `line` call in `range` with a `lineTo` call to where the line ended when the program was
+/// last executed.
+fn line_to_line_to(program: &Program, range: SourceRange, memory: Option<&ProgramMemory>) -> Option<Refactor> {
+    let memory = memory?;
+    let node = innermost(program, range, |node, ancestors| {
+        matches!(node, Node::CallExpression(call) if call.callee.name == "line") && is_top_level(ancestors)
+    })?;
+    let Node::CallExpression(call) = node else {
+        return None;
+    };
+    let call_range = SourceRange::from(call);
+
+    // Find the segment the call drew, in whichever sketch it ended up in.
+    let mut ends: Vec<[f64; 2]> = memory
+        .root
+        .values()
+        .flat_map(|item| -> Vec<&Path> {
+            match item {
+                MemoryItem::SketchGroup(sketch_group) => sketch_group.value.iter().collect(),
+                MemoryItem::SketchGroups { value } => value.iter().flat_map(|group| &group.value).collect(),
+                MemoryItem::ExtrudeGroup(extrude_group) => extrude_group.sketch_group_values.iter().collect(),
+                MemoryItem::ExtrudeGroups { value } => {
+                    value.iter().flat_map(|group| &group.sketch_group_values).collect()
+                }
+                _ => Vec::new(),
+            }
+        })
+        .filter(|path| path.get_base().geo_meta.metadata.source_range == call_range)
+        .map(|path| path.get_base().to)
+        .collect();
+    ends.dedup();
+    let [end] = ends.as_slice() else {
+        return None;
+    };
+
+    let mut arguments = vec![ArrayExpression::new(vec![
+        Literal::new(round_before_recast(end[0]).into()).into(),
+        Literal::new(round_before_recast(end[1]).into()).into(),
+    ])
+    .into()];
+    arguments.extend(call.arguments.iter().skip(1).cloned());
+    let line_to = CallExpression::new("lineTo", arguments).ok()?;
+
+    let mut new_program = program.clone();
+    new_program.replace_value(call_range, line_to.into());
+
+    Some(Refactor {
+        title: "Convert to `lineTo`".to_string(),
+        kind: RefactorKind::Rewrite,
+        program: new_program,
+    })
+}
+
+/// Move the top level pipe expression in `range` into a new function, and call it instead.
+fn pipe_to_function(program: &Program, range: SourceRange) -> Option<Refactor> {
+    let index = body_index(program, range)?;
+    let (pipe, base_name) = match &program.body[index] {
+        BodyItem::VariableDeclaration(declaration) if declaration.declarations.len() == 1 => {
+            let declarator = &declaration.declarations[0];
+            let Value::PipeExpression(pipe) = &declarator.init else {
+                return None;
+            };
+            let mut chars = declarator.id.name.chars();
+            let first = chars.next()?;
+            (pipe, format!("make{}{}", first.to_uppercase(), chars.as_str()))
+        }
+        BodyItem::ExpressionStatement(statement) => {
+            let Value::PipeExpression(pipe) = &statement.expression else {
+                return None;
+            };
+            (pipe, "newFunction".to_string())
+        }
+        _ => return None,
+    };
+    let name = unique_name(program, &base_name);
+
+    let function = FunctionExpression {
+        start: 0,
+        end: 0,
+        params: Vec::new(),
+        body: Program {
+            body: vec![BodyItem::ReturnStatement(ReturnStatement {
+                start: 0,
+                end: 0,
+                argument: Value::PipeExpression(pipe.clone()),
+            })],
+            ..Default::default()
+        },
+        return_type: None,
+    };
+
+    let mut new_program = program.clone();
+    new_program.replace_value(
+        SourceRange::from(pipe),
+        CallExpression::new(&name, Vec::new()).ok()?.into(),
+    );
+    insert_item(
+        &mut new_program,
+        index,
+        declaration(
+            VariableDeclarator::new(&name, Value::FunctionExpression(Box::new(function))),
+            VariableKind::Fn,
+        ),
+    );
+
+    Some(Refactor {
+        title: format!("Extract pipe to function `{}`", name),
+        kind: RefactorKind::Extract,
+        program: new_program,
+    })
+}
+
+/// Give the segment drawn by the call in `range` a tag, if it doesn't have one.
+fn add_tag(program: &Program, stdlib: &StdLib, range: SourceRange) -> Option<Refactor> {
+    let node = innermost(program, range, |node, _| {
+        // The tag has to be the next argument, so it can be added without giving the ones before it.
+        matches!(node, Node::CallExpression(call) if stdlib.tag_arg_index(&call.callee.name) == Some(call.arguments.len()))
+    })?;
+    let Node::CallExpression(call) = node else {
+        return None;
+    };
+
+    // Tags are named like the app names them, `seg01`, `seg02` and so on.
+    let mut last = 0;
+    walk(program, &mut |node, _| {
+        if let Node::Literal(Literal {
+            value: LiteralValue::String(tag),
+            ..
+        }) = node
+        {
+            if let Some(number) = tag.strip_prefix("seg").and_then(|number| number.parse::<u32>().ok()) {
+                last = last.max(number);
+            }
+        }
+    });
+    let tag = format!("seg{:02}", last + 1);
+
+    let mut tagged = call.clone();
+    tagged.arguments.push(Literal::new(tag.clone().into()).into());
+    let mut new_program = program.clone();
+    new_program.replace_value(call.into(), tagged.into());
+
+    Some(Refactor {
+        title: format!("Tag the segment `{}`", tag),
+        kind: RefactorKind::Rewrite,
+        program: new_program,
+    })
+}
+
+/// The smallest node around `range` which `accept` accepts, given the node and its ancestors.
+fn innermost<'a>(
+    program: &'a Program,
+    range: SourceRange,
+    accept: impl Fn(Node<'a>, &[Node<'a>]) -> bool,
+) -> Option<Node<'a>> {
+    let mut found: Option<Node<'a>> = None;
+    walk(program, &mut |node, ancestors| {
+        let source_range = node.source_range();
+        if source_range.start() > range.start() || range.end() > source_range.end() || !accept(node, ancestors) {
+            return;
+        }
+        let is_smaller = match found {
+            Some(found) => {
+                let found = found.source_range();
+                source_range.end() - source_range.start() <= found.end() - found.start()
+            }
+            None => true,
+        };
+        if is_smaller {
+            found = Some(node);
+        }
+    });
+    found
+}
+
+/// Whether the node, or any node inside it, is one `predicate` accepts.
+fn contains(node: Node, predicate: &impl Fn(Node) -> bool) -> bool {
+    predicate(node) || node.children().into_iter().any(|child| contains(child, predicate))
+}
+
+fn contains_pipe_substitution(node: Node) -> bool {
+    contains(node, &|node| matches!(node, Node::PipeSubstitution(_)))
+}
+
+/// The index of the top level item in `range`.
+fn body_index(program: &Program, range: SourceRange) -> Option<usize> {
+    program
+        .body
+        .iter()
+        .position(|item| SourceRange::from(item).contains(range.start()))
+}
+
+fn node_to_value(node: Node) -> Option<Value> {
+    Some(match node {
+        Node::Literal(literal) => Value::Literal(Box::new(literal.clone())),
+        Node::BinaryExpression(expression) => Value::BinaryExpression(Box::new(expression.clone())),
+        Node::CallExpression(call) => Value::CallExpression(Box::new(call.clone())),
+        Node::ArrayExpression(array) => Value::ArrayExpression(Box::new(array.clone())),
+        Node::ObjectExpression(object) => Value::ObjectExpression(Box::new(object.clone())),
+        Node::MemberExpression(member) => Value::MemberExpression(Box::new(member.clone())),
+        Node::UnaryExpression(expression) => Value::UnaryExpression(Box::new(expression.clone())),
+        Node::IfExpression(expression) => Value::IfExpression(Box::new(expression.clone())),
+        Node::PipeExpression(pipe) => Value::PipeExpression(Box::new(pipe.clone())),
+        _ => return None,
+    })
+}
+
+fn identifier(name: &str) -> Value {
+    Value::Identifier(Box::new(Identifier::new(name)))
+}
+
+fn declaration(declarator: VariableDeclarator, kind: VariableKind) -> BodyItem {
+    BodyItem::VariableDeclaration(VariableDeclaration::new(vec![declarator], kind))
+}
+
+/// `name`, or `name` followed by a number if the program already uses `name`.
+fn unique_name(program: &Program, name: &str) -> String {
+    let mut names = HashSet::new();
+    walk(program, &mut |node, _| {
+        if let Node::Identifier(identifier) = node {
+            names.insert(identifier.name.clone());
+        }
+    });
+    let mut unique = name.to_string();
+    let mut number = 1;
+    while names.contains(&unique) {
+        number += 1;
+        unique = format!("{}{}", name, number);
+    }
+    unique
+}
+
+/// Insert an item before the one at `index`.
+/// The comments and blank lines before that item stay before it, rather than going before the new one.
+fn insert_item(program: &mut Program, index: usize, item: BodyItem) {
+    program.body.insert(index, item);
+    // Non-code nodes are kept with the item they come after, so the ones after the item before it
+    // move after the new item.
+    let first_moved = index.saturating_sub(1);
+    let non_code_nodes = std::mem::take(&mut program.non_code_meta.non_code_nodes);
+    program.non_code_meta.non_code_nodes = non_code_nodes
+        .into_iter()
+        .map(|(i, nodes)| if i >= first_moved { (i + 1, nodes) } else { (i, nodes) })
+        .collect();
+}
+
+/// Remove the item at `index`, keeping the comments around it.
+fn remove_item(program: &mut Program, index: usize) {
+    program.body.remove(index);
+    let mut non_code_nodes = std::mem::take(&mut program.non_code_meta.non_code_nodes);
+    let after = non_code_nodes.remove(&index).unwrap_or_default();
+    program.non_code_meta.non_code_nodes = non_code_nodes
+        .into_iter()
+        .map(|(i, nodes)| if i > index { (i - 1, nodes) } else { (i, nodes) })
+        .collect();
+
+    // Blank lines after the item are only kept if there was nothing before it.
+    let before = if index == 0 {
+        &mut program.non_code_meta.start
+    } else {
+        program.non_code_meta.non_code_nodes.entry(index - 1).or_default()
+    };
+    if before.is_empty() {
+        before.extend(after);
+    } else {
+        before.extend(after.into_iter().filter(|node| node.value != NonCodeValue::NewLine));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// The program after the refactor with the given title, applied to the first `selected` in the code.
+    fn refactor(code: &str, selected: &str, title: &str) -> Option<String> {
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let start = code.find(selected).unwrap();
+        let range = SourceRange([start, start + selected.len()]);
+        refactors(&program, &StdLib::new(), range, None)
+            .into_iter()
+            .find(|refactor| refactor.title == title)
+            .map(|refactor| refactor.program.recast(&Default::default(), 0))
+    }
+
+    #[test]
+    fn extract_to_variable() {
+        let code = r#"const width = 4
+// The part.
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width * 2, 0], %)
+"#;
+        assert_eq!(
+            refactor(code, "width * 2", "Extract to variable `newVar`").unwrap(),
+            r#"const width = 4
+const newVar = width * 2
+// The part.
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([newVar, 0], %)
+"#
+        );
+        // Steps of a pipe need the `%` from the steps before them.
+        assert_eq!(refactor(code, "line(", "Extract to variable `newVar`"), None);
+    }
+
+    #[test]
+    fn inline_variable() {
+        let code = r#"const width = 4
+const area = width * width
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width, area], %)
+"#;
+        assert_eq!(
+            refactor(code, "width", "Inline variable `width`").unwrap(),
+            r#"const area = 4 * 4
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([4, area], %)
+"#
+        );
+        // Inlining the sketch would make it twice.
+        let code = r#"const sketch = startSketchOn('XY')
+const a = startProfileAt([0, 0], sketch)
+const b = startProfileAt([1, 1], sketch)
+"#;
+        assert_eq!(refactor(code, "sketch", "Inline variable `sketch`"), None);
+    }
+
+    #[test]
+    fn pipe_to_function() {
+        let code = r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %)
+"#;
+        assert_eq!(
+            refactor(code, "part", "Extract pipe to function `makePart`").unwrap(),
+            r#"fn makePart = () => {
+  return startSketchOn('XY')
+    |> startProfileAt([0, 0], %)
+    |> line([1, 0], %)
+}
+const part = makePart()
+"#
+        );
+    }
+
+    #[test]
+    fn add_tag() {
+        let code = r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %, "seg01")
+  |> line([0, 1], %)
+"#;
+        assert_eq!(
+            refactor(code, "line([0, 1]", "Tag the segment `seg02`").unwrap(),
+            r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %, "seg01")
+  |> line([0, 1], %, "seg02")
+"#
+        );
+        // The first segment already has a tag.
+        assert_eq!(refactor(code, "line([1, 0]", "Tag the segment `seg02`"), None);
+    }
+}
@@ -0,0 +1,393 @@
+//! Checks the types declared by user-defined functions, without executing the program.
+//!
+//! Only values whose type is clear from the code itself are checked, e.g. literals, arithmetic
+//! and calls to functions that declare what they return. Everything else is left to the executor,
+//! which checks the same rules when the function is called.
+
+use std::collections::HashMap;
+
+use crate::{
+    ast::types::{
+        BinaryExpression, BinaryOperator, BinaryPart, BodyItem, CallExpression, FnArgPrimitive, FnArgType, ForIterable,
+        FunctionExpression, LiteralValue, Program, UnaryOperator, Value,
+    },
+    errors::{KclError, KclErrorDetails},
+};
+
+/// Find arguments and return values which don't have the types their function declared.
+pub fn check_types(program: &Program) -> Vec<KclError> {
+    let mut checker = TypeChecker::default();
+    checker.check_program(program, &mut Scope::default(), None);
+    checker.errors
+}
+
+/// The names visible at some point in the program.
+#[derive(Debug, Clone, Default)]
+struct Scope<'a> {
+    /// Values, and their type if it's known.
+    values: HashMap<String, Option<FnArgType>>,
+    /// User-defined functions.
+    functions: HashMap<String, &'a FunctionExpression>,
+}
+
+impl<'a> Scope<'a> {
+    fn bind_value(&mut self, name: &str, type_: Option<FnArgType>) {
+        self.functions.remove(name);
+        self.values.insert(name.to_string(), type_);
+    }
+
+    fn bind_function(&mut self, name: &str, function: &'a FunctionExpression) {
+        self.values.remove(name);
+        self.functions.insert(name.to_string(), function);
+    }
+}
+
+#[derive(Debug, Default)]
+struct TypeChecker {
+    errors: Vec<KclError>,
+    /// The type of `%` in the pipe expression being checked, if it's known.
+    pipe_value: Option<FnArgType>,
+}
+
+impl TypeChecker {
+    /// Check every item in the program.
+    /// `return_type` is the type declared by the function this is the body of, if any.
+    fn check_program<'a>(&mut self, program: &'a Program, scope: &mut Scope<'a>, return_type: Option<&FnArgType>) {
+        for item in &program.body {
+            match item {
+                BodyItem::ExpressionStatement(expression_statement) => {
+                    self.infer(&expression_statement.expression, scope);
+                }
+                BodyItem::VariableDeclaration(variable_declaration) => {
+                    for declaration in &variable_declaration.declarations {
+                        if let Value::FunctionExpression(function_expression) = &declaration.init {
+                            self.check_function(function_expression, scope);
+                            scope.bind_function(&declaration.id.name, function_expression);
+                        } else {
+                            let type_ = self.infer(&declaration.init, scope);
+                            scope.bind_value(&declaration.id.name, type_);
+                        }
+                    }
+                }
+                BodyItem::ReturnStatement(return_statement) => {
+                    let actual = self.infer(&return_statement.argument, scope);
+                    if let (Some(expected), Some(actual)) = (return_type, actual) {
+                        if !is_compatible(expected, &actual) {
+                            self.errors.push(KclError::Type(KclErrorDetails {
+                                message: format!(
+                                    "This function was declared to return `{}`, but it returned a `{}`",
+                                    expected, actual
+                                ),
+                                source_ranges: vec![(&return_statement.argument).into()],
+                            }));
+                        }
+                    }
+                }
+                BodyItem::ForStatement(for_statement) => {
+                    let element_type = match &for_statement.iterable {
+                        ForIterable::Range { from, to, .. } => {
+                            self.infer(from, scope);
+                            self.infer(to, scope);
+                            Some(FnArgType::Primitive(FnArgPrimitive::Number))
+                        }
+                        ForIterable::Array { value } => match self.infer(value, scope) {
+                            Some(FnArgType::Array(primitive)) => Some(FnArgType::Primitive(primitive)),
+                            _ => None,
+                        },
+                    };
+                    let mut body_scope = scope.clone();
+                    body_scope.bind_value(&for_statement.variable.name, element_type);
+                    self.check_program(&for_statement.body, &mut body_scope, None);
+                }
+                BodyItem::ImportStatement(import_statement) => {
+                    // The imported file isn't checked, so nothing is known about these names.
+                    for item in &import_statement.items {
+                        scope.bind_value(&item.identifier().name, None);
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_function<'a>(&mut self, function_expression: &'a FunctionExpression, scope: &Scope<'a>) {
+        let mut body_scope = scope.clone();
+        for param in &function_expression.params {
+            // Optional parameters might be none, so their type isn't certain.
+            let type_ = if param.optional { None } else { param.type_.clone() };
+            body_scope.bind_value(&param.identifier.name, type_);
+        }
+        self.check_program(
+            &function_expression.body,
+            &mut body_scope,
+            function_expression.return_type.as_ref(),
+        );
+    }
+
+    /// Check the arguments of a call to a user-defined function against its parameters.
+    fn check_call(&mut self, call_expression: &CallExpression, arg_types: Vec<Option<FnArgType>>, scope: &Scope) {
+        let Some(function_expression) = scope.functions.get(&call_expression.callee.name) else {
+            return;
+        };
+        for ((arg, actual), param) in call_expression
+            .arguments
+            .iter()
+            .zip(arg_types)
+            .zip(&function_expression.params)
+        {
+            let (Some(expected), Some(actual)) = (&param.type_, actual) else {
+                continue;
+            };
+            if !is_compatible(expected, &actual) {
+                self.errors.push(KclError::Type(KclErrorDetails {
+                    message: format!(
+                        "The parameter `{}` was declared as `{}`, but this argument is a `{}`",
+                        param.identifier.name, expected, actual
+                    ),
+                    source_ranges: vec![arg.into(), (&param.identifier).into()],
+                }));
+            }
+        }
+    }
+
+    /// Check everything inside a value, and return its type if it's known.
+    fn infer(&mut self, value: &Value, scope: &Scope) -> Option<FnArgType> {
+        match value {
+            Value::Literal(literal) => Some(FnArgType::Primitive(match literal.value {
+                LiteralValue::IInteger(_) | LiteralValue::Fractional(_) => FnArgPrimitive::Number,
+                LiteralValue::String(_) => FnArgPrimitive::String,
+                LiteralValue::Bool(_) => FnArgPrimitive::Boolean,
+            })),
+            Value::Identifier(identifier) => scope.values.get(&identifier.name).cloned().flatten(),
+            Value::BinaryExpression(binary_expression) => self.infer_binary(binary_expression, scope),
+            Value::UnaryExpression(unary_expression) => {
+                self.infer_part(&unary_expression.argument, scope);
+                Some(FnArgType::Primitive(match unary_expression.operator {
+                    UnaryOperator::Neg => FnArgPrimitive::Number,
+                    UnaryOperator::Not => FnArgPrimitive::Boolean,
+                }))
+            }
+            Value::CallExpression(call_expression) => self.infer_call(call_expression, scope),
+            Value::PipeExpression(pipe_expression) => {
+                let outer_pipe_value = self.pipe_value.take();
+                let mut result = None;
+                for value in &pipe_expression.body {
+                    result = self.infer(value, scope);
+                    self.pipe_value = result.clone();
+                }
+                self.pipe_value = outer_pipe_value;
+                result
+            }
+            Value::PipeSubstitution(_) => self.pipe_value.clone(),
+            Value::ArrayExpression(array_expression) => {
+                let element_types: Vec<_> = array_expression
+                    .elements
+                    .iter()
+                    .map(|element| self.infer(element, scope))
+                    .collect();
+                match element_types.first() {
+                    Some(Some(FnArgType::Primitive(first)))
+                        if element_types
+                            .iter()
+                            .all(|t| matches!(t, Some(FnArgType::Primitive(p)) if p == first)) =>
+                    {
+                        Some(FnArgType::Array(first.clone()))
+                    }
+                    _ => None,
+                }
+            }
+            Value::ObjectExpression(object_expression) => {
+                for property in &object_expression.properties {
+                    self.infer(&property.value, scope);
+                }
+                None
+            }
+            Value::IfExpression(if_expression) => {
+                self.infer(&if_expression.cond, scope);
+                self.check_program(&if_expression.then_val, &mut scope.clone(), None);
+                for else_if in &if_expression.else_ifs {
+                    self.infer(&else_if.cond, scope);
+                    self.check_program(&else_if.then_val, &mut scope.clone(), None);
+                }
+                if let Some(final_else) = &if_expression.final_else {
+                    self.check_program(final_else, &mut scope.clone(), None);
+                }
+                None
+            }
+            Value::FunctionExpression(function_expression) => {
+                self.check_function(function_expression, scope);
+                None
+            }
+            Value::MemberExpression(_) | Value::None(_) => None,
+        }
+    }
+
+    fn infer_part(&mut self, part: &BinaryPart, scope: &Scope) -> Option<FnArgType> {
+        match part {
+            BinaryPart::Literal(literal) => self.infer(&Value::Literal(literal.clone()), scope),
+            BinaryPart::Identifier(identifier) => scope.values.get(&identifier.name).cloned().flatten(),
+            BinaryPart::BinaryExpression(binary_expression) => self.infer_binary(binary_expression, scope),
+            BinaryPart::CallExpression(call_expression) => self.infer_call(call_expression, scope),
+            BinaryPart::UnaryExpression(unary_expression) => {
+                self.infer(&Value::UnaryExpression(unary_expression.clone()), scope)
+            }
+            BinaryPart::MemberExpression(_) => None,
+        }
+    }
+
+    fn infer_binary(&mut self, binary_expression: &BinaryExpression, scope: &Scope) -> Option<FnArgType> {
+        let left = self.infer_part(&binary_expression.left, scope);
+        let right = self.infer_part(&binary_expression.right, scope);
+        binary_operator_result(&binary_expression.operator, left, right)
+    }
+
+    fn infer_call(&mut self, call_expression: &CallExpression, scope: &Scope) -> Option<FnArgType> {
+        let arg_types = call_expression
+            .arguments
+            .iter()
+            .map(|arg| self.infer(arg, scope))
+            .collect();
+        self.check_call(call_expression, arg_types, scope);
+
+        if let Some(function_expression) = scope.functions.get(&call_expression.callee.name) {
+            return function_expression.return_type.clone();
+        }
+        let return_value = crate::std::get_core_fn(&call_expression.callee.name)?.return_value()?;
+        stdlib_type(&return_value.type_)
+    }
+}
+
+/// What type of value the operator produces, given the types of its operands.
+/// `+` also joins strings, so it's only known to give a number when both operands are numbers.
+fn binary_operator_result(
+    operator: &BinaryOperator,
+    left: Option<FnArgType>,
+    right: Option<FnArgType>,
+) -> Option<FnArgType> {
+    let number = FnArgType::Primitive(FnArgPrimitive::Number);
+    if operator.is_comparison() || matches!(operator, BinaryOperator::And | BinaryOperator::Or) {
+        Some(FnArgType::Primitive(FnArgPrimitive::Boolean))
+    } else if *operator == BinaryOperator::Add {
+        (left.as_ref() == Some(&number) && right.as_ref() == Some(&number)).then_some(number)
+    } else {
+        Some(number)
+    }
+}
+
+/// Convert the type name of a stdlib function's return value, if KCL has a type for it.
+fn stdlib_type(name: &str) -> Option<FnArgType> {
+    let type_ = match name {
+        "number" => FnArgType::Primitive(FnArgPrimitive::Number),
+        "string" => FnArgType::Primitive(FnArgPrimitive::String),
+        "bool" => FnArgType::Primitive(FnArgPrimitive::Boolean),
+        "SketchGroup" => FnArgType::Primitive(FnArgPrimitive::SketchGroup),
+        "SketchSurface" => FnArgType::Primitive(FnArgPrimitive::SketchSurface),
+        "ExtrudeGroup" => FnArgType::Primitive(FnArgPrimitive::ExtrudeGroup),
+        "[SketchGroup]" => FnArgType::Array(FnArgPrimitive::SketchGroup),
+        "[ExtrudeGroup]" => FnArgType::Array(FnArgPrimitive::ExtrudeGroup),
+        _ => return None,
+    };
+    Some(type_)
+}
+
+/// Could a value of the `actual` type be passed where `expected` was declared?
+/// Object types are never inferred, so they're only checked when the program runs.
+fn is_compatible(expected: &FnArgType, actual: &FnArgType) -> bool {
+    matches!(expected, FnArgType::Object { .. }) || expected == actual
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::executor::SourceRange;
+
+    fn check(code: &str) -> Vec<KclError> {
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        check_types(&program)
+    }
+
+    #[test]
+    fn well_typed_program_has_no_errors() {
+        let errors = check(
+            r#"fn area = (w: number, h: number) => number { return w * h }
+fn label = (name: string, sizes: number[]) => string { return name }
+fn grow = (sg: sketch_group) => sketch_group { return sg }
+const a = area(3, 4 + 1)
+const b = label("box", [1, 2, 3])
+const c = area(a, -b)
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %)
+  |> grow(%)"#,
+        );
+        assert_eq!(errors, vec![]);
+    }
+
+    #[test]
+    fn wrong_argument_types() {
+        let code = r#"fn area = (w: number, h: number) => { return w * h }
+const sg = startSketchAt([0, 0])
+const a = area(sg, "tall")"#;
+        let errors = check(code);
+        assert_eq!(
+            errors,
+            vec![
+                KclError::Type(KclErrorDetails {
+                    message: "The parameter `w` was declared as `number`, but this argument is a `sketch_group`"
+                        .to_string(),
+                    source_ranges: vec![SourceRange([101, 103]), SourceRange([11, 12])],
+                }),
+                KclError::Type(KclErrorDetails {
+                    message: "The parameter `h` was declared as `number`, but this argument is a `string`".to_string(),
+                    source_ranges: vec![SourceRange([105, 111]), SourceRange([22, 23])],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn wrong_return_type() {
+        let errors = check(r#"fn name = () => string { return 1 + 2 }"#);
+        assert_eq!(
+            errors,
+            vec![KclError::Type(KclErrorDetails {
+                message: "This function was declared to return `string`, but it returned a `number`".to_string(),
+                source_ranges: vec![SourceRange([32, 37])],
+            })]
+        );
+    }
+
+    #[test]
+    fn string_concatenation() {
+        let errors = check(
+            r#"fn greet = (name: string) => string { return "hello " + name }
+fn total = (a: number, b: number) => number { return a + b }
+const g = greet("a" + "b")"#,
+        );
+        assert_eq!(errors, vec![]);
+
+        // Only `+` joins strings, so the other operators still give numbers.
+        let errors = check(r#"fn f = () => string { return "a" * 2 }"#);
+        assert_eq!(
+            errors,
+            vec![KclError::Type(KclErrorDetails {
+                message: "This function was declared to return `string`, but it returned a `number`".to_string(),
+                source_ranges: vec![SourceRange([29, 36])],
+            })]
+        );
+    }
+
+    #[test]
+    fn unknown_types_are_not_errors() {
+        let errors = check(
+            r#"fn area = (w: number, h?: number) => { return w }
+fn useOptional = (h?: number) => { return area(1, h) }
+const obj = { w: 1 }
+const a = area(obj.w, 2)
+const b = area(if true { 1 } else { 2 }, 3)"#,
+        );
+        assert_eq!(errors, vec![]);
+    }
+}
@@ -16,7 +16,11 @@ pub use crate::ast::types::{literal_value::LiteralValue, none::KclNone};
 use crate::{
     docs::StdLibFn,
     errors::{KclError, KclErrorDetails},
-    executor::{BodyType, ExecutorContext, MemoryItem, Metadata, PipeInfo, ProgramMemory, SourceRange, UserVal},
+    executor::{
+        BodyType, ExecutorContext, MemoryItem, Metadata, Module, PipeInfo, ProgramMemory, ProgramReturn, ScopeRef,
+        SourceRange, UserVal,
+    },
+    fs::FileSystem,
     parser::PIPE_OPERATOR,
     std::{kcl_stdlib::KclStdLibFn, FunctionKind},
 };
@@ -36,6 +40,16 @@ pub struct Program {
 }
 
 impl Program {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for item in &mut self.body {
+            item.shift_source_ranges(offset);
+        }
+        self.non_code_meta.shift_source_ranges(offset);
+    }
+
     pub fn get_hover_value_for_position(&self, pos: usize, code: &str) -> Option<Hover> {
         // Check if we are in the non code meta.
         if let Some(meta) = self.get_non_code_meta_for_position(pos) {
@@ -65,9 +79,13 @@ impl Program {
             .iter()
             .map(|statement| match statement.clone() {
                 BodyItem::ExpressionStatement(expression_statement) => {
-                    expression_statement
-                        .expression
-                        .recast(options, indentation_level, false)
+                    format!(
+                        "{}{}",
+                        indentation,
+                        expression_statement
+                            .expression
+                            .recast(options, indentation_level, false)
+                    )
                 }
                 BodyItem::VariableDeclaration(variable_declaration) => {
                     variable_declaration.recast(options, indentation_level)
@@ -76,9 +94,11 @@ impl Program {
                     format!(
                         "{}return {}",
                         indentation,
-                        return_statement.argument.recast(options, 0, false)
+                        return_statement.argument.recast(options, indentation_level, false)
                     )
                 }
+                BodyItem::ForStatement(for_statement) => for_statement.recast(options, indentation_level),
+                BodyItem::ImportStatement(import_statement) => import_statement.recast(options, indentation_level),
             })
             .enumerate()
             .fold(String::new(), |mut output, (index, recast_str)| {
@@ -179,6 +199,8 @@ impl Program {
             BodyItem::ExpressionStatement(expression_statement) => Some(&expression_statement.expression),
             BodyItem::VariableDeclaration(variable_declaration) => variable_declaration.get_value_for_position(pos),
             BodyItem::ReturnStatement(return_statement) => Some(&return_statement.argument),
+            BodyItem::ForStatement(for_statement) => for_statement.get_value_for_position(pos),
+            BodyItem::ImportStatement(_import_statement) => None,
         }
     }
 
@@ -195,6 +217,8 @@ impl Program {
             BodyItem::ExpressionStatement(expression_statement) => Some(&expression_statement.expression),
             BodyItem::VariableDeclaration(variable_declaration) => variable_declaration.get_value_for_position(pos),
             BodyItem::ReturnStatement(return_statement) => Some(&return_statement.argument),
+            BodyItem::ForStatement(for_statement) => for_statement.get_value_for_position(pos),
+            BodyItem::ImportStatement(_import_statement) => None,
         };
 
         // Check if the value's non code meta contains the position.
@@ -221,6 +245,8 @@ impl Program {
                     symbols.extend(variable_declaration.get_lsp_symbols(code))
                 }
                 BodyItem::ReturnStatement(_return_statement) => continue,
+                BodyItem::ForStatement(_for_statement) => continue,
+                BodyItem::ImportStatement(_import_statement) => continue,
             }
         }
 
@@ -244,6 +270,12 @@ impl Program {
                     }
                 }
                 BodyItem::ReturnStatement(_return_statement) => continue,
+                BodyItem::ForStatement(for_statement) => {
+                    if let Some(folding_range) = for_statement.get_lsp_folding_range() {
+                        ranges.push(folding_range)
+                    }
+                }
+                BodyItem::ImportStatement(_import_statement) => continue,
             }
         }
 
@@ -266,6 +298,13 @@ impl Program {
                     }
                 }
                 BodyItem::ReturnStatement(_return_statement) => continue,
+                BodyItem::ForStatement(_for_statement) => continue,
+                BodyItem::ImportStatement(ref mut import_statement) => {
+                    if let Some(import_old_name) = import_statement.rename_symbol(new_name, pos) {
+                        old_name = Some(import_old_name);
+                        break;
+                    }
+                }
             }
         }
 
@@ -289,6 +328,8 @@ impl Program {
                     variable_declaration.get_mut_value_for_position(pos)
                 }
                 BodyItem::ReturnStatement(ref mut return_statement) => Some(&mut return_statement.argument),
+                BodyItem::ForStatement(_for_statement) => None,
+                BodyItem::ImportStatement(_import_statement) => None,
             };
 
             // Check if we have a function expression.
@@ -322,6 +363,10 @@ impl Program {
                 BodyItem::ReturnStatement(ref mut return_statement) => {
                     return_statement.argument.rename_identifiers(old_name, new_name);
                 }
+                BodyItem::ForStatement(ref mut for_statement) => {
+                    for_statement.rename_identifiers(old_name, new_name);
+                }
+                BodyItem::ImportStatement(_import_statement) => {}
             }
         }
     }
@@ -342,6 +387,8 @@ impl Program {
                     }
                 }
                 BodyItem::ReturnStatement(_return_statement) => continue,
+                BodyItem::ForStatement(_for_statement) => continue,
+                BodyItem::ImportStatement(_import_statement) => continue,
             }
         }
     }
@@ -359,6 +406,10 @@ impl Program {
                 BodyItem::ReturnStatement(ref mut return_statement) => {
                     return_statement.argument.replace_value(source_range, new_value.clone())
                 }
+                BodyItem::ForStatement(ref mut for_statement) => {
+                    for_statement.replace_value(source_range, new_value.clone())
+                }
+                BodyItem::ImportStatement(_import_statement) => {}
             }
         }
     }
@@ -378,6 +429,8 @@ impl Program {
                     }
                 }
                 BodyItem::ReturnStatement(_return_statement) => continue,
+                BodyItem::ForStatement(_for_statement) => continue,
+                BodyItem::ImportStatement(_import_statement) => continue,
             }
         }
 
@@ -433,14 +486,29 @@ pub enum BodyItem {
     ExpressionStatement(ExpressionStatement),
     VariableDeclaration(VariableDeclaration),
     ReturnStatement(ReturnStatement),
+    ForStatement(Box<ForStatement>),
+    ImportStatement(Box<ImportStatement>),
 }
 
 impl BodyItem {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            BodyItem::ExpressionStatement(expression_statement) => expression_statement.shift_source_ranges(offset),
+            BodyItem::VariableDeclaration(variable_declaration) => variable_declaration.shift_source_ranges(offset),
+            BodyItem::ReturnStatement(return_statement) => return_statement.shift_source_ranges(offset),
+            BodyItem::ForStatement(for_statement) => for_statement.shift_source_ranges(offset),
+            BodyItem::ImportStatement(import_statement) => import_statement.shift_source_ranges(offset),
+        }
+    }
+
     pub fn start(&self) -> usize {
         match self {
             BodyItem::ExpressionStatement(expression_statement) => expression_statement.start(),
             BodyItem::VariableDeclaration(variable_declaration) => variable_declaration.start(),
             BodyItem::ReturnStatement(return_statement) => return_statement.start(),
+            BodyItem::ForStatement(for_statement) => for_statement.start(),
+            BodyItem::ImportStatement(import_statement) => import_statement.start(),
         }
     }
 
@@ -449,6 +517,8 @@ impl BodyItem {
             BodyItem::ExpressionStatement(expression_statement) => expression_statement.end(),
             BodyItem::VariableDeclaration(variable_declaration) => variable_declaration.end(),
             BodyItem::ReturnStatement(return_statement) => return_statement.end(),
+            BodyItem::ForStatement(for_statement) => for_statement.end(),
+            BodyItem::ImportStatement(import_statement) => import_statement.end(),
         }
     }
 }
@@ -481,10 +551,30 @@ pub enum Value {
     ObjectExpression(Box<ObjectExpression>),
     MemberExpression(Box<MemberExpression>),
     UnaryExpression(Box<UnaryExpression>),
+    IfExpression(Box<IfExpression>),
     None(KclNone),
 }
 
 impl Value {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            Value::Literal(literal) => literal.shift_source_ranges(offset),
+            Value::Identifier(identifier) => identifier.shift_source_ranges(offset),
+            Value::BinaryExpression(binary_expression) => binary_expression.shift_source_ranges(offset),
+            Value::FunctionExpression(function_expression) => function_expression.shift_source_ranges(offset),
+            Value::CallExpression(call_expression) => call_expression.shift_source_ranges(offset),
+            Value::PipeExpression(pipe_expression) => pipe_expression.shift_source_ranges(offset),
+            Value::PipeSubstitution(pipe_substitution) => pipe_substitution.shift_source_ranges(offset),
+            Value::ArrayExpression(array_expression) => array_expression.shift_source_ranges(offset),
+            Value::ObjectExpression(object_expression) => object_expression.shift_source_ranges(offset),
+            Value::MemberExpression(member_expression) => member_expression.shift_source_ranges(offset),
+            Value::UnaryExpression(unary_expression) => unary_expression.shift_source_ranges(offset),
+            Value::IfExpression(if_expression) => if_expression.shift_source_ranges(offset),
+            Value::None(none) => none.shift_source_ranges(offset),
+        }
+    }
+
     fn recast(&self, options: &FormatOptions, indentation_level: usize, is_in_pipe: bool) -> String {
         match &self {
             Value::BinaryExpression(bin_exp) => bin_exp.recast(options),
@@ -497,6 +587,7 @@ impl Value {
             Value::Identifier(ident) => ident.name.to_string(),
             Value::PipeExpression(pipe_exp) => pipe_exp.recast(options, indentation_level),
             Value::UnaryExpression(unary_exp) => unary_exp.recast(options),
+            Value::IfExpression(if_exp) => if_exp.recast(options, indentation_level),
             Value::PipeSubstitution(_) => crate::parser::PIPE_SUBSTITUTION_OPERATOR.to_string(),
             Value::None(_) => {
                 unimplemented!("there is no literal None, see https://github.com/KittyCAD/modeling-app/issues/1115")
@@ -537,6 +628,7 @@ impl Value {
             Value::Identifier(_ident) => None,
             Value::PipeExpression(pipe_exp) => Some(&pipe_exp.non_code_meta),
             Value::UnaryExpression(_unary_exp) => None,
+            Value::IfExpression(_if_exp) => None,
             Value::PipeSubstitution(_pipe_substitution) => None,
             Value::None(_none) => None,
         }
@@ -559,6 +651,7 @@ impl Value {
             Value::Identifier(_) => {}
             Value::PipeExpression(ref mut pipe_exp) => pipe_exp.replace_value(source_range, new_value),
             Value::UnaryExpression(ref mut unary_exp) => unary_exp.replace_value(source_range, new_value),
+            Value::IfExpression(ref mut if_exp) => if_exp.replace_value(source_range, new_value),
             Value::PipeSubstitution(_) => {}
             Value::None(_) => {}
         }
@@ -577,6 +670,7 @@ impl Value {
             Value::ObjectExpression(object_expression) => object_expression.start(),
             Value::MemberExpression(member_expression) => member_expression.start(),
             Value::UnaryExpression(unary_expression) => unary_expression.start(),
+            Value::IfExpression(if_expression) => if_expression.start(),
             Value::None(none) => none.start,
         }
     }
@@ -594,6 +688,7 @@ impl Value {
             Value::ObjectExpression(object_expression) => object_expression.end(),
             Value::MemberExpression(member_expression) => member_expression.end(),
             Value::UnaryExpression(unary_expression) => unary_expression.end(),
+            Value::IfExpression(if_expression) => if_expression.end(),
             Value::None(none) => none.end,
         }
     }
@@ -612,6 +707,7 @@ impl Value {
             Value::ObjectExpression(object_expression) => object_expression.get_hover_value_for_position(pos, code),
             Value::MemberExpression(member_expression) => member_expression.get_hover_value_for_position(pos, code),
             Value::UnaryExpression(unary_expression) => unary_expression.get_hover_value_for_position(pos, code),
+            Value::IfExpression(if_expression) => if_expression.get_hover_value_for_position(pos, code),
             // TODO: LSP hover information for values/types. https://github.com/KittyCAD/modeling-app/issues/1126
             Value::None(_) => None,
             Value::Literal(_) => None,
@@ -629,7 +725,16 @@ impl Value {
             Value::BinaryExpression(ref mut binary_expression) => {
                 binary_expression.rename_identifiers(old_name, new_name)
             }
-            Value::FunctionExpression(_function_identifier) => {}
+            Value::FunctionExpression(ref mut function_expression) => {
+                // A parameter with the old name hides it from the body.
+                if !function_expression
+                    .params
+                    .iter()
+                    .any(|param| param.identifier.name == old_name)
+                {
+                    function_expression.body.rename_identifiers(old_name, new_name);
+                }
+            }
             Value::CallExpression(ref mut call_expression) => call_expression.rename_identifiers(old_name, new_name),
             Value::PipeExpression(ref mut pipe_expression) => pipe_expression.rename_identifiers(old_name, new_name),
             Value::PipeSubstitution(_) => {}
@@ -641,6 +746,7 @@ impl Value {
                 member_expression.rename_identifiers(old_name, new_name)
             }
             Value::UnaryExpression(ref mut unary_expression) => unary_expression.rename_identifiers(old_name, new_name),
+            Value::IfExpression(ref mut if_expression) => if_expression.rename_identifiers(old_name, new_name),
             Value::None(_) => {}
         }
     }
@@ -662,6 +768,7 @@ impl Value {
             Value::ObjectExpression(object_expression) => object_expression.get_constraint_level(),
             Value::MemberExpression(member_expression) => member_expression.get_constraint_level(),
             Value::UnaryExpression(unary_expression) => unary_expression.get_constraint_level(),
+            Value::IfExpression(if_expression) => if_expression.get_constraint_level(),
             Value::None(none) => none.get_constraint_level(),
         }
     }
@@ -705,6 +812,18 @@ impl From<&BinaryPart> for SourceRange {
 }
 
 impl BinaryPart {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            BinaryPart::Literal(literal) => literal.shift_source_ranges(offset),
+            BinaryPart::Identifier(identifier) => identifier.shift_source_ranges(offset),
+            BinaryPart::BinaryExpression(binary_expression) => binary_expression.shift_source_ranges(offset),
+            BinaryPart::CallExpression(call_expression) => call_expression.shift_source_ranges(offset),
+            BinaryPart::UnaryExpression(unary_expression) => unary_expression.shift_source_ranges(offset),
+            BinaryPart::MemberExpression(member_expression) => member_expression.shift_source_ranges(offset),
+        }
+    }
+
     /// Get the constraint level.
     pub fn get_constraint_level(&self) -> ConstraintLevel {
         match self {
@@ -717,7 +836,28 @@ impl BinaryPart {
         }
     }
 
+    /// The value as an operand, if it's a kind of value operators can be used with.
+    pub fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Literal(literal) => Some(BinaryPart::Literal(literal)),
+            Value::Identifier(identifier) => Some(BinaryPart::Identifier(identifier)),
+            Value::BinaryExpression(binary_expression) => Some(BinaryPart::BinaryExpression(binary_expression)),
+            Value::CallExpression(call_expression) => Some(BinaryPart::CallExpression(call_expression)),
+            Value::UnaryExpression(unary_expression) => Some(BinaryPart::UnaryExpression(unary_expression)),
+            Value::MemberExpression(member_expression) => Some(BinaryPart::MemberExpression(member_expression)),
+            _ => None,
+        }
+    }
+
     pub fn replace_value(&mut self, source_range: SourceRange, new_value: Value) {
+        if source_range == SourceRange::from(&*self) {
+            // Values which can't be operands are left alone.
+            if let Some(new_part) = BinaryPart::from_value(new_value) {
+                *self = new_part;
+            }
+            return;
+        }
+
         match self {
             BinaryPart::Literal(_) => {}
             BinaryPart::Identifier(_) => {}
@@ -849,6 +989,12 @@ impl From<&NonCodeNode> for SourceRange {
 }
 
 impl NonCodeNode {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+    }
+
     pub fn contains(&self, pos: usize) -> bool {
         self.start <= pos && pos <= self.end
     }
@@ -997,6 +1143,13 @@ impl<'de> Deserialize<'de> for NonCodeMeta {
 }
 
 impl NonCodeMeta {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        for node in self.non_code_nodes.values_mut().flatten().chain(&mut self.start) {
+            node.shift_source_ranges(offset);
+        }
+    }
+
     pub fn insert(&mut self, i: usize, new: NonCodeNode) {
         self.non_code_nodes.entry(i).or_default().push(new);
     }
@@ -1024,6 +1177,15 @@ pub struct ExpressionStatement {
 
 impl_value_meta!(ExpressionStatement);
 
+impl ExpressionStatement {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.expression.shift_source_ranges(offset);
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
 #[databake(path = kcl_lib::ast::types)]
 #[ts(export)]
@@ -1045,6 +1207,16 @@ impl From<CallExpression> for Value {
 }
 
 impl CallExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.callee.shift_source_ranges(offset);
+        for argument in &mut self.arguments {
+            argument.shift_source_ranges(offset);
+        }
+    }
+
     pub fn new(name: &str, arguments: Vec<Value>) -> Result<Self, KclError> {
         Ok(Self {
             start: 0,
@@ -1108,6 +1280,7 @@ impl CallExpression {
                 }
                 Value::CallExpression(call_expression) => call_expression.execute(memory, pipe_info, ctx).await?,
                 Value::UnaryExpression(unary_expression) => unary_expression.get_result(memory, pipe_info, ctx).await?,
+                Value::IfExpression(if_expression) => if_expression.get_result(memory, pipe_info, ctx).await?,
                 Value::ObjectExpression(object_expression) => object_expression.execute(memory, pipe_info, ctx).await?,
                 Value::ArrayExpression(array_expression) => array_expression.execute(memory, pipe_info, ctx).await?,
                 Value::PipeExpression(pipe_expression) => {
@@ -1164,42 +1337,22 @@ impl CallExpression {
                     }));
                 }
 
-                // Add the arguments to the memory.
-                let mut fn_memory = memory.clone();
-                for (index, param) in parts.params_required.iter().enumerate() {
-                    fn_memory.add(
-                        &param.identifier.name,
-                        fn_args.get(index).unwrap().clone(),
-                        param.identifier.clone().into(),
-                    )?;
-                }
-                // Add the optional arguments to the memory.
-                for (index, param) in parts.params_optional.iter().enumerate() {
-                    if let Some(arg) = fn_args.get(index + parts.params_required.len()) {
-                        fn_memory.add(&param.identifier.name, arg.clone(), param.identifier.clone().into())?;
-                    } else {
-                        fn_memory.add(
-                            &param.identifier.name,
-                            MemoryItem::UserVal(UserVal {
-                                value: serde_json::value::Value::Null,
-                                meta: Default::default(),
-                            }),
-                            param.identifier.clone().into(),
-                        )?;
-                    }
+                // Add the arguments to the memory, in a scope of their own.
+                let entry = memory.enter_scope(ScopeRef::ROOT);
+                if let Err(err) = assign_std_args(&parts, &fn_args, memory) {
+                    memory.exit_scope(entry);
+                    return Err(err);
                 }
 
                 // Call the stdlib function
                 let p = func.function().clone().body;
-                let results = match ctx.inner_execute(p, &mut fn_memory, BodyType::Block).await {
-                    Ok(results) => results,
-                    Err(err) => {
-                        // We need to override the source ranges so we don't get the embedded kcl
-                        // function from the stdlib.
-                        return Err(err.override_source_ranges(vec![self.into()]));
-                    }
-                };
-                let out = results.return_;
+                let result = ctx.inner_execute(p, memory, BodyType::Block).await;
+                let out = memory.exit_scope(entry);
+                if let Err(err) = result {
+                    // We need to override the source ranges so we don't get the embedded kcl
+                    // function from the stdlib.
+                    return Err(err.override_source_ranges(vec![self.into()]));
+                }
                 let result = out.ok_or_else(|| {
                     KclError::UndefinedValue(KclErrorDetails {
                         message: format!("Result of stdlib function {} is undefined", fn_name),
@@ -1211,16 +1364,13 @@ impl CallExpression {
                 Ok(result)
             }
             FunctionKind::UserDefined => {
-                let func = memory.get(&fn_name, self.into())?;
-                let result = func
-                    .call_fn(fn_args, memory.clone(), ctx.clone())
-                    .await?
-                    .ok_or_else(|| {
-                        KclError::UndefinedValue(KclErrorDetails {
-                            message: format!("Result of user-defined function {} is undefined", fn_name),
-                            source_ranges: vec![self.into()],
-                        })
-                    })?;
+                let func = memory.get(&fn_name, self.into())?.clone();
+                let result = func.call_fn(fn_args, memory, ctx.clone()).await?.ok_or_else(|| {
+                    KclError::UndefinedValue(KclErrorDetails {
+                        message: format!("Result of user-defined function {} is undefined", fn_name),
+                        source_ranges: vec![self.into()],
+                    })
+                })?;
 
                 let result = result.get_value()?;
 
@@ -1318,17 +1468,29 @@ pub struct VariableDeclaration {
     pub start: usize,
     pub end: usize,
     pub declarations: Vec<VariableDeclarator>,
+    #[serde(default, skip_serializing_if = "ItemVisibility::is_default")]
+    pub visibility: ItemVisibility,
     pub kind: VariableKind, // Change to enum if there are specific values
 }
 
 impl_value_meta!(VariableDeclaration);
 
 impl VariableDeclaration {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for declaration in &mut self.declarations {
+            declaration.shift_source_ranges(offset);
+        }
+    }
+
     pub fn new(declarations: Vec<VariableDeclarator>, kind: VariableKind) -> Self {
         Self {
             start: 0,
             end: 0,
             declarations,
+            visibility: ItemVisibility::Default,
             kind,
         }
     }
@@ -1358,8 +1520,9 @@ impl VariableDeclaration {
         self.declarations.iter().fold(String::new(), |mut output, declaration| {
             let _ = write!(
                 output,
-                "{}{} {} = {}",
+                "{}{}{} {} = {}",
                 indentation,
+                self.visibility,
                 self.kind,
                 declaration.id.name,
                 declaration.init.recast(options, indentation_level, false)
@@ -1512,6 +1675,35 @@ pub enum VariableKind {
     Var,
 }
 
+/// Whether other files can import a declaration.
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemVisibility {
+    /// Only visible in the file that declares it.
+    #[default]
+    Default,
+    /// Declared with `export`, so other files can import it.
+    Export,
+}
+
+impl ItemVisibility {
+    fn is_default(&self) -> bool {
+        matches!(self, ItemVisibility::Default)
+    }
+}
+
+impl std::fmt::Display for ItemVisibility {
+    /// The prefix for a declaration with this visibility, including any trailing space.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemVisibility::Default => Ok(()),
+            ItemVisibility::Export => write!(f, "export "),
+        }
+    }
+}
+
 impl VariableKind {
     pub fn to_completion_items() -> Result<Vec<CompletionItem>> {
         let mut settings = schemars::gen::SchemaSettings::openapi3();
@@ -1557,6 +1749,14 @@ pub struct VariableDeclarator {
 impl_value_meta!(VariableDeclarator);
 
 impl VariableDeclarator {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.id.shift_source_ranges(offset);
+        self.init.shift_source_ranges(offset);
+    }
+
     pub fn new(name: &str, init: Value) -> Self {
         Self {
             start: 0,
@@ -1585,6 +1785,12 @@ pub struct Literal {
 impl_value_meta!(Literal);
 
 impl Literal {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+    }
+
     pub fn new(value: LiteralValue) -> Self {
         Self {
             start: 0,
@@ -1656,6 +1862,12 @@ pub struct Identifier {
 impl_value_meta!(Identifier);
 
 impl Identifier {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+    }
+
     pub fn new(name: &str) -> Self {
         Self {
             start: 0,
@@ -1692,6 +1904,12 @@ pub struct PipeSubstitution {
 impl_value_meta!(PipeSubstitution);
 
 impl PipeSubstitution {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+    }
+
     pub fn new() -> Self {
         Self { start: 0, end: 0 }
     }
@@ -1728,6 +1946,15 @@ impl From<ArrayExpression> for Value {
 }
 
 impl ArrayExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for element in &mut self.elements {
+            element.shift_source_ranges(offset);
+        }
+    }
+
     pub fn new(elements: Vec<Value>) -> Self {
         Self {
             start: 0,
@@ -1826,6 +2053,7 @@ impl ArrayExpression {
                 }
                 Value::CallExpression(call_expression) => call_expression.execute(memory, pipe_info, ctx).await?,
                 Value::UnaryExpression(unary_expression) => unary_expression.get_result(memory, pipe_info, ctx).await?,
+                Value::IfExpression(if_expression) => if_expression.get_result(memory, pipe_info, ctx).await?,
                 Value::ObjectExpression(object_expression) => object_expression.execute(memory, pipe_info, ctx).await?,
                 Value::ArrayExpression(array_expression) => array_expression.execute(memory, pipe_info, ctx).await?,
                 Value::PipeExpression(pipe_expression) => pipe_expression.get_result(memory, pipe_info, ctx).await?,
@@ -1875,6 +2103,15 @@ pub struct ObjectExpression {
 }
 
 impl ObjectExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for property in &mut self.properties {
+            property.shift_source_ranges(offset);
+        }
+    }
+
     pub fn new(properties: Vec<ObjectProperty>) -> Self {
         Self {
             start: 0,
@@ -1984,6 +2221,7 @@ impl ObjectExpression {
                 }
                 Value::CallExpression(call_expression) => call_expression.execute(memory, pipe_info, ctx).await?,
                 Value::UnaryExpression(unary_expression) => unary_expression.get_result(memory, pipe_info, ctx).await?,
+                Value::IfExpression(if_expression) => if_expression.get_result(memory, pipe_info, ctx).await?,
                 Value::ObjectExpression(object_expression) => object_expression.execute(memory, pipe_info, ctx).await?,
                 Value::ArrayExpression(array_expression) => array_expression.execute(memory, pipe_info, ctx).await?,
                 Value::PipeExpression(pipe_expression) => pipe_expression.get_result(memory, pipe_info, ctx).await?,
@@ -2037,6 +2275,14 @@ pub struct ObjectProperty {
 impl_value_meta!(ObjectProperty);
 
 impl ObjectProperty {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.key.shift_source_ranges(offset);
+        self.value.shift_source_ranges(offset);
+    }
+
     pub fn get_lsp_symbols(&self, code: &str) -> Vec<DocumentSymbol> {
         let source_range: SourceRange = self.clone().into();
         let inner_source_range: SourceRange = self.key.clone().into();
@@ -2076,6 +2322,14 @@ pub enum MemberObject {
 }
 
 impl MemberObject {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            MemberObject::MemberExpression(member_expression) => member_expression.shift_source_ranges(offset),
+            MemberObject::Identifier(identifier) => identifier.shift_source_ranges(offset),
+        }
+    }
+
     /// Returns a hover value that includes the given character position.
     pub fn get_hover_value_for_position(&self, pos: usize, code: &str) -> Option<Hover> {
         match self {
@@ -2123,6 +2377,14 @@ pub enum LiteralIdentifier {
 }
 
 impl LiteralIdentifier {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            LiteralIdentifier::Identifier(identifier) => identifier.shift_source_ranges(offset),
+            LiteralIdentifier::Literal(literal) => literal.shift_source_ranges(offset),
+        }
+    }
+
     pub fn start(&self) -> usize {
         match self {
             LiteralIdentifier::Identifier(identifier) => identifier.start,
@@ -2165,6 +2427,14 @@ pub struct MemberExpression {
 impl_value_meta!(MemberExpression);
 
 impl MemberExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.object.shift_source_ranges(offset);
+        self.property.shift_source_ranges(offset);
+    }
+
     /// Get the constraint level for a member expression.
     /// This is always fully constrained.
     pub fn get_constraint_level(&self) -> ConstraintLevel {
@@ -2330,6 +2600,14 @@ pub struct BinaryExpression {
 impl_value_meta!(BinaryExpression);
 
 impl BinaryExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.left.shift_source_ranges(offset);
+        self.right.shift_source_ranges(offset);
+    }
+
     pub fn new(operator: BinaryOperator, left: BinaryPart, right: BinaryPart) -> Self {
         Self {
             start: left.start(),
@@ -2373,6 +2651,8 @@ impl BinaryExpression {
                 self.precedence() > bin_exp.precedence()
                     || self.operator == BinaryOperator::Sub
                     || self.operator == BinaryOperator::Div
+                    // Comparisons don't chain, so `a == (b == c)` needs its brackets.
+                    || (self.operator.is_comparison() && self.precedence() == bin_exp.precedence())
             }
             _ => false,
         };
@@ -2414,8 +2694,34 @@ impl BinaryExpression {
         ctx: &ExecutorContext,
     ) -> Result<MemoryItem, KclError> {
         let left_json_value = self.left.get_result(memory, pipe_info, ctx).await?.get_json_value()?;
+        let to_memory_item = |value: serde_json::Value| {
+            MemoryItem::UserVal(UserVal {
+                value,
+                meta: vec![Metadata {
+                    source_range: self.into(),
+                }],
+            })
+        };
+
+        // Boolean operators short-circuit, so only evaluate the right side if it's needed.
+        if let BinaryOperator::And | BinaryOperator::Or = self.operator {
+            let left = parse_json_value_as_bool(&left_json_value, self.left.clone().into())?;
+            if left == (self.operator == BinaryOperator::Or) {
+                return Ok(to_memory_item(left.into()));
+            }
+            let right_json_value = self.right.get_result(memory, pipe_info, ctx).await?.get_json_value()?;
+            let right = parse_json_value_as_bool(&right_json_value, self.right.clone().into())?;
+            return Ok(to_memory_item(right.into()));
+        }
+
         let right_json_value = self.right.get_result(memory, pipe_info, ctx).await?.get_json_value()?;
 
+        // Equality works on any values, not just numbers.
+        if let BinaryOperator::Eq | BinaryOperator::Neq = self.operator {
+            let equal = json_values_equal(&left_json_value, &right_json_value);
+            return Ok(to_memory_item((equal == (self.operator == BinaryOperator::Eq)).into()));
+        }
+
         // First check if we are doing string concatenation.
         if self.operator == BinaryOperator::Add {
             if let (Some(left), Some(right)) = (
@@ -2442,14 +2748,16 @@ impl BinaryExpression {
             BinaryOperator::Div => (left / right).into(),
             BinaryOperator::Mod => (left % right).into(),
             BinaryOperator::Pow => (left.powf(right)).into(),
+            BinaryOperator::Lt => (left < right).into(),
+            BinaryOperator::Lte => (left <= right).into(),
+            BinaryOperator::Gt => (left > right).into(),
+            BinaryOperator::Gte => (left >= right).into(),
+            BinaryOperator::Eq | BinaryOperator::Neq | BinaryOperator::And | BinaryOperator::Or => {
+                unreachable!("equality and boolean operators are handled above")
+            }
         };
 
-        Ok(MemoryItem::UserVal(UserVal {
-            value,
-            meta: vec![Metadata {
-                source_range: self.into(),
-            }],
-        }))
+        Ok(to_memory_item(value))
     }
 
     /// Rename all identifiers that have the old name to the new given name.
@@ -2475,6 +2783,25 @@ pub fn parse_json_number_as_f64(j: &serde_json::Value, source_range: SourceRange
     }
 }
 
+pub fn parse_json_value_as_bool(j: &serde_json::Value, source_range: SourceRange) -> Result<bool, KclError> {
+    if let serde_json::Value::Bool(b) = j {
+        Ok(*b)
+    } else {
+        Err(KclError::Type(KclErrorDetails {
+            source_ranges: vec![source_range],
+            message: format!("Expected a boolean but found {}", j),
+        }))
+    }
+}
+
+/// Numbers are compared by value, so that e.g. `1 == 1.0`.
+fn json_values_equal(left: &serde_json::Value, right: &serde_json::Value) -> bool {
+    match (left, right) {
+        (serde_json::Value::Number(l), serde_json::Value::Number(r)) => l.as_f64() == r.as_f64(),
+        _ => left == right,
+    }
+}
+
 pub fn parse_json_value_as_string(j: &serde_json::Value) -> Option<String> {
     if let serde_json::Value::String(n) = &j {
         Some(n.clone())
@@ -2513,6 +2840,38 @@ pub enum BinaryOperator {
     #[serde(rename = "^")]
     #[display("^")]
     Pow,
+    /// Are two values equal?
+    #[serde(rename = "==")]
+    #[display("==")]
+    Eq,
+    /// Are two values not equal?
+    #[serde(rename = "!=")]
+    #[display("!=")]
+    Neq,
+    /// Is the left number less than the right?
+    #[serde(rename = "<")]
+    #[display("<")]
+    Lt,
+    /// Is the left number less than or equal to the right?
+    #[serde(rename = "<=")]
+    #[display("<=")]
+    Lte,
+    /// Is the left number greater than the right?
+    #[serde(rename = ">")]
+    #[display(">")]
+    Gt,
+    /// Is the left number greater than or equal to the right?
+    #[serde(rename = ">=")]
+    #[display(">=")]
+    Gte,
+    /// Are both booleans true?
+    #[serde(rename = "&&")]
+    #[display("&&")]
+    And,
+    /// Is either boolean true?
+    #[serde(rename = "||")]
+    #[display("||")]
+    Or,
 }
 
 /// Mathematical associativity.
@@ -2537,17 +2896,38 @@ impl BinaryOperator {
     /// Taken from <https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Operators/Operator_precedence#table>
     pub fn precedence(&self) -> u8 {
         match &self {
+            BinaryOperator::Or => 3,
+            BinaryOperator::And => 4,
+            BinaryOperator::Eq | BinaryOperator::Neq => 8,
+            BinaryOperator::Lt | BinaryOperator::Lte | BinaryOperator::Gt | BinaryOperator::Gte => 9,
             BinaryOperator::Add | BinaryOperator::Sub => 11,
             BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 12,
-            BinaryOperator::Pow => 6,
+            BinaryOperator::Pow => 10,
         }
     }
 
+    /// Does this operator compare its operands, producing a boolean?
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Self::Eq | Self::Neq | Self::Lt | Self::Lte | Self::Gt | Self::Gte)
+    }
+
     /// Follow JS definitions of each operator.
     /// Taken from <https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Operators/Operator_precedence#table>
     pub fn associativity(&self) -> Associativity {
         match self {
-            Self::Add | Self::Sub | Self::Mul | Self::Div | Self::Mod => Associativity::Left,
+            Self::Add
+            | Self::Sub
+            | Self::Mul
+            | Self::Div
+            | Self::Mod
+            | Self::Eq
+            | Self::Neq
+            | Self::Lt
+            | Self::Lte
+            | Self::Gt
+            | Self::Gte
+            | Self::And
+            | Self::Or => Associativity::Left,
             Self::Pow => Associativity::Right,
         }
     }
@@ -2566,6 +2946,13 @@ pub struct UnaryExpression {
 impl_value_meta!(UnaryExpression);
 
 impl UnaryExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.argument.shift_source_ranges(offset);
+    }
+
     pub fn new(operator: UnaryOperator, argument: BinaryPart) -> Self {
         Self {
             start: 0,
@@ -2603,16 +2990,17 @@ impl UnaryExpression {
         pipe_info: &PipeInfo,
         ctx: &ExecutorContext,
     ) -> Result<MemoryItem, KclError> {
-        let num = parse_json_number_as_f64(
-            &self
-                .argument
-                .get_result(memory, pipe_info, ctx)
-                .await?
-                .get_json_value()?,
-            self.into(),
-        )?;
+        let argument = self
+            .argument
+            .get_result(memory, pipe_info, ctx)
+            .await?
+            .get_json_value()?;
+        let value = match self.operator {
+            UnaryOperator::Neg => (-parse_json_number_as_f64(&argument, self.into())?).into(),
+            UnaryOperator::Not => (!parse_json_value_as_bool(&argument, self.into())?).into(),
+        };
         Ok(MemoryItem::UserVal(UserVal {
-            value: (-(num)).into(),
+            value,
             meta: vec![Metadata {
                 source_range: self.into(),
             }],
@@ -2673,6 +3061,16 @@ impl From<PipeExpression> for Value {
 }
 
 impl PipeExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for value in &mut self.body {
+            value.shift_source_ranges(offset);
+        }
+        self.non_code_meta.shift_source_ranges(offset);
+    }
+
     pub fn new(body: Vec<Value>) -> Self {
         Self {
             start: 0,
@@ -2827,120 +3225,1084 @@ async fn execute_pipe_body(
     Ok(final_output)
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema, Bake, FromStr, Display)]
+/// An `if cond { ... } else if cond { ... } else { ... }` expression.
+/// The value of each branch is the value of the last expression in its block.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
 #[databake(path = kcl_lib::ast::types)]
-#[serde(tag = "type")]
-#[display(style = "snake_case")]
-pub enum FnArgPrimitive {
-    /// A string type.
-    String,
-    /// A number type.
-    Number,
-    /// A boolean type.
-    #[display("bool")]
-    #[serde(rename = "bool")]
-    Boolean,
-    /// A sketch group type.
-    SketchGroup,
-    /// A sketch surface type.
-    SketchSurface,
-    /// An extrude group type.
-    ExtrudeGroup,
+#[ts(export)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub struct IfExpression {
+    pub start: usize,
+    pub end: usize,
+    pub cond: Value,
+    pub then_val: Program,
+    pub else_ifs: Vec<ElseIf>,
+    /// The `else` block. If there is none, and no branch was taken, the expression is KCL None.
+    pub final_else: Option<Program>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema, Bake)]
-#[databake(path = kcl_lib::ast::types)]
-#[serde(tag = "type")]
-pub enum FnArgType {
-    /// A primitive type.
-    Primitive(FnArgPrimitive),
-    // An array of a primitive type.
-    Array(FnArgPrimitive),
-    // An object type.
-    Object {
-        properties: Vec<Parameter>,
-    },
-}
+impl_value_meta!(IfExpression);
 
-/// Parameter of a KCL function.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, ts_rs::TS, JsonSchema, Bake)]
-#[databake(path = kcl_lib::ast::types)]
-#[ts(export)]
-#[serde(tag = "type")]
-pub struct Parameter {
-    /// The parameter's label or name.
-    pub identifier: Identifier,
-    /// The type of the parameter.
-    /// This is optional if the user defines a type.
-    #[serde(skip)]
-    pub type_: Option<FnArgType>,
-    /// Is the parameter optional?
-    pub optional: bool,
+impl From<IfExpression> for Value {
+    fn from(if_expression: IfExpression) -> Self {
+        Value::IfExpression(Box::new(if_expression))
+    }
 }
 
+/// An `else if cond { ... }` branch of an [`IfExpression`].
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
 #[databake(path = kcl_lib::ast::types)]
 #[ts(export)]
-#[serde(tag = "type")]
-pub struct FunctionExpression {
+#[serde(rename_all = "camelCase", tag = "type")]
+pub struct ElseIf {
     pub start: usize,
     pub end: usize,
-    pub params: Vec<Parameter>,
-    pub body: Program,
-    #[serde(skip)]
-    pub return_type: Option<FnArgType>,
+    pub cond: Value,
+    pub then_val: Program,
 }
 
-impl_value_meta!(FunctionExpression);
+impl_value_meta!(ElseIf);
 
-pub struct FunctionExpressionParts {
-    pub start: usize,
-    pub end: usize,
-    pub params_required: Vec<Parameter>,
-    pub params_optional: Vec<Parameter>,
-    pub body: Program,
+impl ElseIf {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.cond.shift_source_ranges(offset);
+        self.then_val.shift_source_ranges(offset);
+    }
 }
 
-#[derive(Debug, PartialEq, Clone)]
-pub struct RequiredParamAfterOptionalParam(pub Parameter);
+impl IfExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.cond.shift_source_ranges(offset);
+        self.then_val.shift_source_ranges(offset);
+        for else_if in &mut self.else_ifs {
+            else_if.shift_source_ranges(offset);
+        }
+        if let Some(final_else) = &mut self.final_else {
+            final_else.shift_source_ranges(offset);
+        }
+    }
 
-impl std::fmt::Display for RequiredParamAfterOptionalParam {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "KCL functions must declare any optional parameters after all the required parameters. But your required parameter {} is _after_ an optional parameter. You must move it to before the optional parameters instead.", self.0.identifier.name)
+    /// All the conditions of this expression, in the order they're checked.
+    fn conditions(&self) -> impl Iterator<Item = &Value> {
+        std::iter::once(&self.cond).chain(self.else_ifs.iter().map(|else_if| &else_if.cond))
     }
-}
 
-impl FunctionExpression {
-    /// Function expressions don't really apply.
-    pub fn get_constraint_level(&self) -> ConstraintLevel {
-        ConstraintLevel::Ignore {
-            source_ranges: vec![self.into()],
+    /// All the blocks of this expression, in source order.
+    fn blocks(&self) -> impl Iterator<Item = &Program> {
+        std::iter::once(&self.then_val)
+            .chain(self.else_ifs.iter().map(|else_if| &else_if.then_val))
+            .chain(self.final_else.iter())
+    }
+
+    pub fn replace_value(&mut self, source_range: SourceRange, new_value: Value) {
+        self.cond.replace_value(source_range, new_value.clone());
+        self.then_val.replace_value(source_range, new_value.clone());
+        for else_if in &mut self.else_ifs {
+            else_if.cond.replace_value(source_range, new_value.clone());
+            else_if.then_val.replace_value(source_range, new_value.clone());
+        }
+        if let Some(ref mut final_else) = self.final_else {
+            final_else.replace_value(source_range, new_value);
         }
     }
 
-    pub fn into_parts(self) -> Result<FunctionExpressionParts, RequiredParamAfterOptionalParam> {
-        let Self {
-            start,
-            end,
-            params,
-            body,
-            return_type: _,
-        } = self;
-        let mut params_required = Vec::with_capacity(params.len());
-        let mut params_optional = Vec::with_capacity(params.len());
-        for param in params {
-            if param.optional {
-                params_optional.push(param);
-            } else {
-                if !params_optional.is_empty() {
-                    return Err(RequiredParamAfterOptionalParam(param));
-                }
-                params_required.push(param);
+    /// The constraint level depends on the conditions and the value each branch produces.
+    pub fn get_constraint_level(&self) -> ConstraintLevel {
+        let mut constraint_levels = ConstraintLevels::new();
+        for cond in self.conditions() {
+            constraint_levels.push(cond.get_constraint_level());
+        }
+        for block in self.blocks() {
+            if let Some(BodyItem::ExpressionStatement(expression_statement)) = block.body.last() {
+                constraint_levels.push(expression_statement.expression.get_constraint_level());
             }
         }
-        params_required.shrink_to_fit();
-        params_optional.shrink_to_fit();
-        Ok(FunctionExpressionParts {
+
+        constraint_levels.get_constraint_level(self.into())
+    }
+
+    fn recast(&self, options: &FormatOptions, indentation_level: usize) -> String {
+        // We don't want to end with a new line inside nested blocks.
+        let mut new_options = options.clone();
+        new_options.insert_final_newline = false;
+        let recast_block = |block: &Program| -> String {
+            if block.body.is_empty() {
+                return "{}".to_string();
+            }
+            format!(
+                "{{\n{}{}\n{}}}",
+                options.get_indentation(indentation_level + 1),
+                block.recast(&new_options, indentation_level + 1),
+                options.get_indentation(indentation_level)
+            )
+        };
+
+        let mut output = format!(
+            "if {} {}",
+            self.cond.recast(options, indentation_level, false),
+            recast_block(&self.then_val)
+        );
+        for else_if in &self.else_ifs {
+            let _ = write!(
+                output,
+                " else if {} {}",
+                else_if.cond.recast(options, indentation_level, false),
+                recast_block(&else_if.then_val)
+            );
+        }
+        if let Some(final_else) = &self.final_else {
+            let _ = write!(output, " else {}", recast_block(final_else));
+        }
+        output
+    }
+
+    /// Returns a hover value that includes the given character position.
+    pub fn get_hover_value_for_position(&self, pos: usize, code: &str) -> Option<Hover> {
+        for cond in self.conditions() {
+            let cond_source_range: SourceRange = cond.into();
+            if cond_source_range.contains(pos) {
+                return cond.get_hover_value_for_position(pos, code);
+            }
+        }
+
+        for block in self.blocks() {
+            if let Some(value) = block.get_value_for_position(pos) {
+                return value.get_hover_value_for_position(pos, code);
+            }
+        }
+
+        None
+    }
+
+    #[async_recursion::async_recursion]
+    pub async fn get_result(
+        &self,
+        memory: &mut ProgramMemory,
+        pipe_info: &PipeInfo,
+        ctx: &ExecutorContext,
+    ) -> Result<MemoryItem, KclError> {
+        if evaluate_condition(&self.cond, memory, pipe_info, ctx).await? {
+            return execute_block(&self.then_val, memory, ctx).await;
+        }
+        for else_if in &self.else_ifs {
+            if evaluate_condition(&else_if.cond, memory, pipe_info, ctx).await? {
+                return execute_block(&else_if.then_val, memory, ctx).await;
+            }
+        }
+        if let Some(final_else) = &self.final_else {
+            return execute_block(final_else, memory, ctx).await;
+        }
+
+        Ok(MemoryItem::from(&KclNone {
+            start: self.start,
+            end: self.end,
+        }))
+    }
+
+    /// Rename all identifiers that have the old name to the new given name.
+    fn rename_identifiers(&mut self, old_name: &str, new_name: &str) {
+        self.cond.rename_identifiers(old_name, new_name);
+        self.then_val.rename_identifiers(old_name, new_name);
+        for else_if in &mut self.else_ifs {
+            else_if.cond.rename_identifiers(old_name, new_name);
+            else_if.then_val.rename_identifiers(old_name, new_name);
+        }
+        if let Some(ref mut final_else) = self.final_else {
+            final_else.rename_identifiers(old_name, new_name);
+        }
+    }
+}
+
+/// The most times a single for loop may run.
+/// This stops a mistyped range from hanging the editor while it executes.
+pub const MAX_LOOP_ITERATIONS: usize = 10_000;
+
+/// The most times loop bodies may run in total while executing a program, counting nested loops.
+/// Without this, a loop inside another loop could still run 10,000 × 10,000 times.
+pub const MAX_TOTAL_LOOP_ITERATIONS: usize = 100_000;
+
+/// A loop over an array or a range, e.g. `for i in [0..n] { ... }`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub struct ForStatement {
+    pub start: usize,
+    pub end: usize,
+    /// The name each element is bound to inside the body.
+    pub variable: Identifier,
+    pub iterable: ForIterable,
+    pub body: Program,
+}
+
+impl_value_meta!(ForStatement);
+
+/// What a for loop iterates over.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub enum ForIterable {
+    /// An inclusive range of integers, e.g. `[0..n]`.
+    /// Unlike array ranges, the bounds don't have to be literals.
+    Range {
+        start: usize,
+        end: usize,
+        from: Value,
+        to: Value,
+    },
+    /// Any value which evaluates to an array, e.g. `[1, 2, 3]` or `points`.
+    Array { value: Value },
+}
+
+impl ForIterable {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        match self {
+            ForIterable::Range { start, end, from, to } => {
+                *start = start.saturating_add_signed(offset);
+                *end = end.saturating_add_signed(offset);
+                from.shift_source_ranges(offset);
+                to.shift_source_ranges(offset);
+            }
+            ForIterable::Array { value } => value.shift_source_ranges(offset),
+        }
+    }
+
+    fn recast(&self, options: &FormatOptions, indentation_level: usize) -> String {
+        match self {
+            ForIterable::Range { from, to, .. } => format!(
+                "[{}..{}]",
+                from.recast(options, indentation_level, false),
+                to.recast(options, indentation_level, false)
+            ),
+            ForIterable::Array { value } => value.recast(options, indentation_level, false),
+        }
+    }
+
+    fn values(&self) -> Vec<&Value> {
+        match self {
+            ForIterable::Range { from, to, .. } => vec![from, to],
+            ForIterable::Array { value } => vec![value],
+        }
+    }
+
+    fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            ForIterable::Range { from, to, .. } => vec![from, to],
+            ForIterable::Array { value } => vec![value],
+        }
+    }
+
+    /// Work out every element the loop will iterate over.
+    async fn get_elements(
+        &self,
+        memory: &mut ProgramMemory,
+        pipe_info: &PipeInfo,
+        ctx: &ExecutorContext,
+    ) -> Result<Vec<JValue>, KclError> {
+        let elements = match self {
+            ForIterable::Range { start, end, from, to } => {
+                let from_int = evaluate_range_bound(from, memory, pipe_info, ctx).await?;
+                let to_int = evaluate_range_bound(to, memory, pipe_info, ctx).await?;
+                // Both bounds fit in an i64, so their difference can't overflow an i128.
+                let count = (i128::from(to_int) - i128::from(from_int) + 1).max(0);
+                if count > MAX_LOOP_ITERATIONS as i128 {
+                    return Err(too_many_iterations(count, SourceRange([*start, *end])));
+                }
+                (from_int..=to_int).map(JValue::from).collect()
+            }
+            ForIterable::Array { value } => {
+                let result =
+                    evaluate_value(value, "the array a for loop iterates over", memory, pipe_info, ctx).await?;
+                let JValue::Array(elements) = result.get_json_value()? else {
+                    return Err(KclError::Type(KclErrorDetails {
+                        message: format!(
+                            "A for loop can only iterate over an array or a range, but this was {}",
+                            result.get_json_value()?
+                        ),
+                        source_ranges: vec![value.into()],
+                    }));
+                };
+                if elements.len() > MAX_LOOP_ITERATIONS {
+                    return Err(too_many_iterations(elements.len(), value.into()));
+                }
+                elements
+            }
+        };
+        Ok(elements)
+    }
+}
+
+fn too_many_iterations(count: impl std::fmt::Display, source_range: SourceRange) -> KclError {
+    KclError::Semantic(KclErrorDetails {
+        message: format!(
+            "This for loop would run {} times, but a loop can run at most {} times",
+            count, MAX_LOOP_ITERATIONS
+        ),
+        source_ranges: vec![source_range],
+    })
+}
+
+/// Evaluate one end of a for loop's range, which must be an integer.
+async fn evaluate_range_bound(
+    bound: &Value,
+    memory: &mut ProgramMemory,
+    pipe_info: &PipeInfo,
+    ctx: &ExecutorContext,
+) -> Result<i64, KclError> {
+    let result = evaluate_value(bound, "the bound of a range", memory, pipe_info, ctx).await?;
+    let num = parse_json_number_as_f64(&result.get_json_value()?, bound.into())?;
+    if num.fract() != 0.0 {
+        return Err(KclError::Type(KclErrorDetails {
+            message: format!("The bounds of a range must be integers, but this was {}", num),
+            source_ranges: vec![bound.into()],
+        }));
+    }
+    // `as` would silently saturate anything outside this range.
+    if num < i64::MIN as f64 || num >= i64::MAX as f64 {
+        return Err(KclError::Semantic(KclErrorDetails {
+            message: format!(
+                "The bounds of a range must fit in a 64-bit integer, but this was {}",
+                num
+            ),
+            source_ranges: vec![bound.into()],
+        }));
+    }
+    Ok(num as i64)
+}
+
+impl ForStatement {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.variable.shift_source_ranges(offset);
+        self.iterable.shift_source_ranges(offset);
+        self.body.shift_source_ranges(offset);
+    }
+
+    fn recast(&self, options: &FormatOptions, indentation_level: usize) -> String {
+        // We don't want to end with a new line inside nested blocks.
+        let mut new_options = options.clone();
+        new_options.insert_final_newline = false;
+        let body = if self.body.body.is_empty() {
+            "{}".to_string()
+        } else {
+            format!(
+                "{{\n{}{}\n{}}}",
+                options.get_indentation(indentation_level + 1),
+                self.body.recast(&new_options, indentation_level + 1),
+                options.get_indentation(indentation_level)
+            )
+        };
+        format!(
+            "{}for {} in {} {}",
+            options.get_indentation(indentation_level),
+            self.variable.name,
+            self.iterable.recast(options, indentation_level),
+            body
+        )
+    }
+
+    pub fn get_lsp_folding_range(&self) -> Option<FoldingRange> {
+        let recasted = self.recast(&FormatOptions::default(), 0);
+        // If the loop only has one line, don't fold it.
+        if recasted.lines().count() <= 1 {
+            return None;
+        }
+
+        // This unwrap is safe because we know that the code has at least one line.
+        let first_line = recasted.lines().next().unwrap().to_string();
+
+        Some(FoldingRange {
+            start_line: (self.start + first_line.len()) as u32,
+            start_character: None,
+            end_line: self.end as u32,
+            end_character: None,
+            kind: Some(FoldingRangeKind::Region),
+            collapsed_text: Some(first_line),
+        })
+    }
+
+    /// Returns a value that includes the given character position.
+    pub fn get_value_for_position(&self, pos: usize) -> Option<&Value> {
+        for value in self.iterable.values() {
+            let source_range: SourceRange = value.into();
+            if source_range.contains(pos) {
+                return Some(value);
+            }
+        }
+
+        self.body.get_value_for_position(pos)
+    }
+
+    pub fn replace_value(&mut self, source_range: SourceRange, new_value: Value) {
+        for value in self.iterable.values_mut() {
+            value.replace_value(source_range, new_value.clone());
+        }
+        self.body.replace_value(source_range, new_value);
+    }
+
+    /// Rename all identifiers that have the old name to the new given name.
+    fn rename_identifiers(&mut self, old_name: &str, new_name: &str) {
+        self.variable.rename(old_name, new_name);
+        for value in self.iterable.values_mut() {
+            value.rename_identifiers(old_name, new_name);
+        }
+        self.body.rename_identifiers(old_name, new_name);
+    }
+
+    /// Run the body once for each element, with the element bound to the loop's variable.
+    /// Names declared in the body only exist for a single iteration.
+    pub async fn execute(
+        &self,
+        memory: &mut ProgramMemory,
+        pipe_info: &PipeInfo,
+        ctx: &ExecutorContext,
+    ) -> Result<(), KclError> {
+        let elements = self.iterable.get_elements(memory, pipe_info, ctx).await?;
+        for element in elements {
+            memory.loop_iterations += 1;
+            if memory.loop_iterations > MAX_TOTAL_LOOP_ITERATIONS {
+                return Err(KclError::Semantic(KclErrorDetails {
+                    message: format!(
+                        "Loops in this program have run more than {} times in total, so execution was stopped",
+                        MAX_TOTAL_LOOP_ITERATIONS
+                    ),
+                    source_ranges: vec![self.into()],
+                }));
+            }
+            let entry = memory.enter_scope(memory.current_scope());
+            let result = match memory.add(
+                &self.variable.name,
+                MemoryItem::UserVal(UserVal {
+                    value: element,
+                    meta: vec![Metadata {
+                        source_range: (&self.variable).into(),
+                    }],
+                }),
+                (&self.variable).into(),
+            ) {
+                Ok(()) => ctx.inner_execute(self.body.clone(), memory, BodyType::Block).await,
+                Err(err) => Err(err),
+            };
+            memory.exit_scope(entry);
+            result?;
+        }
+        Ok(())
+    }
+}
+
+/// Brings names exported by another KCL file into this one,
+/// e.g. `import { cube, sphere as ball } from "shapes.kcl"`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub struct ImportStatement {
+    pub start: usize,
+    pub end: usize,
+    pub items: Vec<ImportItem>,
+    /// The file to import from, relative to the project directory.
+    pub path: String,
+}
+
+impl_value_meta!(ImportStatement);
+
+/// A single name in an import statement, e.g. `sphere as ball`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub struct ImportItem {
+    pub start: usize,
+    pub end: usize,
+    /// The name the other file exported.
+    pub name: Identifier,
+    /// The name it's bound to in this file, if that's different.
+    pub alias: Option<Identifier>,
+}
+
+impl_value_meta!(ImportItem);
+
+impl ImportItem {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.name.shift_source_ranges(offset);
+        if let Some(alias) = &mut self.alias {
+            alias.shift_source_ranges(offset);
+        }
+    }
+
+    /// The identifier this item is bound to in the importing file.
+    pub fn identifier(&self) -> &Identifier {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+
+    fn recast(&self) -> String {
+        match &self.alias {
+            Some(alias) => format!("{} as {}", self.name.name, alias.name),
+            None => self.name.name.clone(),
+        }
+    }
+}
+
+impl ImportStatement {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for item in &mut self.items {
+            item.shift_source_ranges(offset);
+        }
+    }
+
+    fn recast(&self, options: &FormatOptions, indentation_level: usize) -> String {
+        format!(
+            "{}import {{ {} }} from \"{}\"",
+            options.get_indentation(indentation_level),
+            self.items
+                .iter()
+                .map(|item| item.recast())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.path
+        )
+    }
+
+    /// Rename the name an item is bound to, if it's at the given position.
+    /// Returns the old name, if an item was renamed.
+    /// The exported name can't change, so renaming an item without an alias gives it one.
+    fn rename_symbol(&mut self, new_name: &str, pos: usize) -> Option<String> {
+        for item in &mut self.items {
+            let source_range: SourceRange = item.identifier().into();
+            if !source_range.contains(pos) {
+                continue;
+            }
+            let old_name = item.identifier().name.clone();
+            match &mut item.alias {
+                Some(alias) => alias.name = new_name.to_string(),
+                None => {
+                    item.alias = Some(Identifier {
+                        start: item.name.start,
+                        end: item.name.end,
+                        name: new_name.to_string(),
+                    })
+                }
+            }
+            return Some(old_name);
+        }
+        None
+    }
+
+    /// Execute the imported file in a scope of its own, then add the items it exported to the current scope.
+    /// Functions keep running in their own file's scope, so they can use names it didn't export.
+    /// A file imported again reuses the values from the first time, rather than running again.
+    pub async fn execute(&self, memory: &mut ProgramMemory, ctx: &ExecutorContext) -> Result<(), KclError> {
+        let source_range = SourceRange::from(self);
+        let path = std::path::Path::new(&self.path);
+        if path.extension().and_then(|ext| ext.to_str()) != Some("kcl") {
+            return Err(KclError::Semantic(KclErrorDetails {
+                message: format!(
+                    "Only KCL files can be imported with `import {{ ... }} from`, but \"{}\" isn't one. Use the `import` function to load other CAD files",
+                    self.path
+                ),
+                source_ranges: vec![source_range],
+            }));
+        }
+
+        let resolved = match &ctx.settings.project_directory {
+            Some(dir) => std::path::Path::new(dir).join(path),
+            None => path.to_path_buf(),
+        };
+        // Drop any `./` and `dir/..` so the same file is always spelled the same way.
+        let mut normalized = std::path::PathBuf::new();
+        for component in resolved.components() {
+            match component {
+                std::path::Component::CurDir => {}
+                std::path::Component::ParentDir
+                    if matches!(
+                        normalized.components().next_back(),
+                        Some(std::path::Component::Normal(_))
+                    ) =>
+                {
+                    normalized.pop();
+                }
+                component => normalized.push(component),
+            }
+        }
+        let resolved = normalized;
+        if ctx.import_stack.contains(&resolved) {
+            let cycle = ctx
+                .import_stack
+                .iter()
+                .skip_while(|p| **p != resolved)
+                .chain(std::iter::once(&resolved))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(KclError::Semantic(KclErrorDetails {
+                message: format!("Import cycle detected: {}", cycle),
+                source_ranges: vec![source_range],
+            }));
+        }
+
+        let module = match memory.module(&resolved) {
+            Some(module) => module.clone(),
+            None => {
+                let module = self.execute_module(&resolved, memory, ctx).await?;
+                memory.add_module(resolved, module.clone());
+                module
+            }
+        };
+
+        let mut values = Vec::with_capacity(self.items.len());
+        for item in &self.items {
+            let name = &item.name.name;
+            match module.declarations.get(name) {
+                None => {
+                    return Err(KclError::UndefinedValue(KclErrorDetails {
+                        message: format!("\"{}\" isn't declared in \"{}\"", name, self.path),
+                        source_ranges: vec![(&item.name).into()],
+                    }))
+                }
+                Some(false) => {
+                    return Err(KclError::Semantic(KclErrorDetails {
+                        message: format!(
+                            "\"{}\" isn't exported from \"{}\". Add `export` before its declaration to import it",
+                            name, self.path
+                        ),
+                        source_ranges: vec![(&item.name).into()],
+                    }))
+                }
+                Some(true) => values.push(memory.get_in(module.scope, name, (&item.name).into())?.clone()),
+            }
+        }
+        for (item, value) in self.items.iter().zip(values) {
+            memory.add(&item.identifier().name, value, item.identifier().into())?;
+        }
+        Ok(())
+    }
+
+    /// Run the file at `path`, which this statement imports, in a scope of its own.
+    /// The scope is kept, since the functions the file exports can still refer to it.
+    async fn execute_module(
+        &self,
+        path: &std::path::Path,
+        memory: &mut ProgramMemory,
+        ctx: &ExecutorContext,
+    ) -> Result<Module, KclError> {
+        let source_range = SourceRange::from(self);
+        let bytes = ctx.fs.read(path, source_range).await?;
+        let code = String::from_utf8(bytes).map_err(|err| {
+            KclError::Semantic(KclErrorDetails {
+                message: format!("\"{}\" isn't valid UTF-8: {}", self.path, err),
+                source_ranges: vec![source_range],
+            })
+        })?;
+        let in_module =
+            |err: KclError| err.in_imported_file(&self.path, &path.display().to_string(), &code, source_range);
+        let tokens = crate::token::lexer(&code).map_err(in_module)?;
+        let program = crate::parser::Parser::new(tokens).ast().map_err(in_module)?;
+
+        let declarations = program
+            .body
+            .iter()
+            .filter_map(|body_item| match body_item {
+                BodyItem::VariableDeclaration(declaration) => Some(declaration),
+                _ => None,
+            })
+            .flat_map(|declaration| {
+                declaration
+                    .declarations
+                    .iter()
+                    .map(|d| (d.id.name.clone(), declaration.visibility == ItemVisibility::Export))
+            })
+            .collect();
+
+        let mut module_ctx = ctx.clone();
+        module_ctx.import_stack.push(path.to_path_buf());
+        let entry = memory.enter_module();
+        let scope = memory.current_scope();
+        let result = module_ctx
+            .inner_execute(program, memory, BodyType::Root)
+            .await
+            .map_err(in_module);
+        memory.exit_module(entry);
+        result?;
+
+        Ok(Module { scope, declarations })
+    }
+}
+
+/// Evaluate a value that's used on its own, e.g. as a condition or the thing a loop iterates over.
+/// `usage` describes where the value is used, for error messages.
+async fn evaluate_value(
+    value: &Value,
+    usage: &str,
+    memory: &mut ProgramMemory,
+    pipe_info: &PipeInfo,
+    ctx: &ExecutorContext,
+) -> Result<MemoryItem, KclError> {
+    let result = match value {
+        Value::Literal(literal) => literal.into(),
+        Value::Identifier(identifier) => memory.get(&identifier.name, identifier.into())?.clone(),
+        Value::BinaryExpression(binary_expression) => binary_expression.get_result(memory, pipe_info, ctx).await?,
+        Value::UnaryExpression(unary_expression) => unary_expression.get_result(memory, pipe_info, ctx).await?,
+        Value::CallExpression(call_expression) => call_expression.execute(memory, pipe_info, ctx).await?,
+        Value::MemberExpression(member_expression) => member_expression.get_result(memory)?,
+        Value::ArrayExpression(array_expression) => array_expression.execute(memory, pipe_info, ctx).await?,
+        Value::PipeExpression(pipe_expression) => pipe_expression.get_result(memory, pipe_info, ctx).await?,
+        Value::IfExpression(if_expression) => if_expression.get_result(memory, pipe_info, ctx).await?,
+        _ => {
+            return Err(KclError::Semantic(KclErrorDetails {
+                message: format!("{:?} cannot be used as {}", value, usage),
+                source_ranges: vec![value.into()],
+            }));
+        }
+    };
+    Ok(result)
+}
+
+/// Evaluate the condition of an if expression, which must be a boolean.
+async fn evaluate_condition(
+    cond: &Value,
+    memory: &mut ProgramMemory,
+    pipe_info: &PipeInfo,
+    ctx: &ExecutorContext,
+) -> Result<bool, KclError> {
+    let result = evaluate_value(cond, "the condition of an if expression", memory, pipe_info, ctx).await?;
+
+    match result.get_json_value()? {
+        serde_json::Value::Bool(b) => Ok(b),
+        other => Err(KclError::Type(KclErrorDetails {
+            message: format!(
+                "The condition of an if expression must be a boolean, but it was {}",
+                other
+            ),
+            source_ranges: vec![cond.into()],
+        })),
+    }
+}
+
+/// Add the arguments of a call to a stdlib function written in KCL to the current scope.
+fn assign_std_args(
+    parts: &FunctionExpressionParts,
+    fn_args: &[MemoryItem],
+    memory: &mut ProgramMemory,
+) -> Result<(), KclError> {
+    for (index, param) in parts.params_required.iter().enumerate() {
+        memory.add(
+            &param.identifier.name,
+            fn_args.get(index).unwrap().clone(),
+            param.identifier.clone().into(),
+        )?;
+    }
+    // Add the optional arguments to the memory.
+    for (index, param) in parts.params_optional.iter().enumerate() {
+        if let Some(arg) = fn_args.get(index + parts.params_required.len()) {
+            memory.add(&param.identifier.name, arg.clone(), param.identifier.clone().into())?;
+        } else {
+            memory.add(
+                &param.identifier.name,
+                MemoryItem::UserVal(UserVal {
+                    value: serde_json::value::Value::Null,
+                    meta: Default::default(),
+                }),
+                param.identifier.clone().into(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Execute one branch of an if expression, returning the value of its last expression.
+/// Names declared inside the branch are not visible once it finishes.
+async fn execute_block(
+    block: &Program,
+    memory: &mut ProgramMemory,
+    ctx: &ExecutorContext,
+) -> Result<MemoryItem, KclError> {
+    let mut block = block.clone();
+    // The trailing expression is the block's value, so treat it like a return.
+    if let Some(BodyItem::ExpressionStatement(tail)) = block.body.last() {
+        let tail = ReturnStatement {
+            start: tail.start,
+            end: tail.end,
+            argument: tail.expression.clone(),
+        };
+        block.body.pop();
+        block.body.push(BodyItem::ReturnStatement(tail));
+    }
+
+    let end = block.end;
+    let entry = memory.enter_scope(memory.current_scope());
+    let result = ctx.inner_execute(block, memory, BodyType::Block).await;
+    let returned = memory.exit_scope(entry);
+    result?;
+    match returned {
+        Some(ProgramReturn::Value(value)) => Ok(value),
+        _ => Ok(MemoryItem::from(&KclNone { start: end, end })),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema, Bake, FromStr, Display)]
+#[databake(path = kcl_lib::ast::types)]
+#[serde(tag = "type")]
+#[display(style = "snake_case")]
+pub enum FnArgPrimitive {
+    /// A string type.
+    String,
+    /// A number type.
+    Number,
+    /// A boolean type.
+    #[display("bool")]
+    #[serde(rename = "bool")]
+    Boolean,
+    /// A sketch group type.
+    SketchGroup,
+    /// A sketch surface type.
+    SketchSurface,
+    /// An extrude group type.
+    ExtrudeGroup,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[serde(tag = "type")]
+pub enum FnArgType {
+    /// A primitive type.
+    Primitive(FnArgPrimitive),
+    // An array of a primitive type.
+    Array(FnArgPrimitive),
+    // An object type.
+    Object {
+        properties: Vec<Parameter>,
+    },
+}
+
+impl std::fmt::Display for FnArgType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FnArgType::Primitive(primitive) => write!(f, "{}", primitive),
+            FnArgType::Array(primitive) => write!(f, "{}[]", primitive),
+            FnArgType::Object { properties } => {
+                let properties = properties
+                    .iter()
+                    .map(|property| {
+                        let optional = if property.optional { "?" } else { "" };
+                        match &property.type_ {
+                            Some(type_) => format!("{}{}: {}", property.identifier.name, optional, type_),
+                            None => format!("{}{}", property.identifier.name, optional),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{{{}}}", properties)
+            }
+        }
+    }
+}
+
+impl FnArgType {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        if let FnArgType::Object { properties } = self {
+            for property in properties {
+                property.shift_source_ranges(offset);
+            }
+        }
+    }
+
+    /// Does the given value have this type?
+    pub fn matches(&self, value: &MemoryItem) -> bool {
+        match (self, value) {
+            (_, MemoryItem::UserVal(user_val)) => self.matches_json(&user_val.value),
+            (FnArgType::Primitive(FnArgPrimitive::SketchGroup), MemoryItem::SketchGroup(_))
+            | (FnArgType::Array(FnArgPrimitive::SketchGroup), MemoryItem::SketchGroups { .. })
+            | (FnArgType::Primitive(FnArgPrimitive::ExtrudeGroup), MemoryItem::ExtrudeGroup(_))
+            | (FnArgType::Array(FnArgPrimitive::ExtrudeGroup), MemoryItem::ExtrudeGroups { .. })
+            | (FnArgType::Primitive(FnArgPrimitive::SketchSurface), MemoryItem::Plane(_) | MemoryItem::Face(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn matches_json(&self, value: &JValue) -> bool {
+        match self {
+            FnArgType::Primitive(primitive) => primitive.matches_json(value),
+            FnArgType::Array(primitive) => match value {
+                JValue::Array(elements) => elements.iter().all(|element| primitive.matches_json(element)),
+                _ => false,
+            },
+            FnArgType::Object { properties } => {
+                let JValue::Object(object) = value else {
+                    return false;
+                };
+                properties.iter().all(
+                    |property| match (object.get(&property.identifier.name), &property.type_) {
+                        (None | Some(JValue::Null), _) => property.optional,
+                        (Some(_), None) => true,
+                        (Some(value), Some(type_)) => type_.matches_json(value),
+                    },
+                )
+            }
+        }
+    }
+}
+
+impl FnArgPrimitive {
+    fn matches_json(&self, value: &JValue) -> bool {
+        // Geometry that ends up inside arrays or objects is serialized with its type as a tag.
+        let tag = value.get("type").and_then(JValue::as_str);
+        match self {
+            FnArgPrimitive::String => value.is_string(),
+            FnArgPrimitive::Number => value.is_number(),
+            FnArgPrimitive::Boolean => value.is_boolean(),
+            FnArgPrimitive::SketchGroup => tag == Some("SketchGroup"),
+            FnArgPrimitive::SketchSurface => matches!(tag, Some("Plane" | "Face")),
+            FnArgPrimitive::ExtrudeGroup => tag == Some("ExtrudeGroup"),
+        }
+    }
+}
+
+/// Describe the type of a value, using the same names as KCL type annotations where they exist.
+pub fn describe_type(value: &MemoryItem) -> String {
+    match value {
+        MemoryItem::UserVal(user_val) => match &user_val.value {
+            JValue::Null => "none".to_string(),
+            JValue::Bool(_) => "bool".to_string(),
+            JValue::Number(_) => "number".to_string(),
+            JValue::String(_) => "string".to_string(),
+            JValue::Array(_) => "array".to_string(),
+            JValue::Object(object) => match object.get("type").and_then(JValue::as_str) {
+                Some("KclNone") => "none".to_string(),
+                Some("SketchGroup") => "sketch_group".to_string(),
+                Some("ExtrudeGroup") => "extrude_group".to_string(),
+                Some("Plane" | "Face") => "sketch_surface".to_string(),
+                _ => "object".to_string(),
+            },
+        },
+        MemoryItem::Plane(_) | MemoryItem::Face(_) => "sketch_surface".to_string(),
+        MemoryItem::SketchGroup(_) => "sketch_group".to_string(),
+        MemoryItem::SketchGroups { .. } => "sketch_group[]".to_string(),
+        MemoryItem::ExtrudeGroup(_) => "extrude_group".to_string(),
+        MemoryItem::ExtrudeGroups { .. } => "extrude_group[]".to_string(),
+        MemoryItem::ImportedGeometry(_) => "imported geometry".to_string(),
+        MemoryItem::Function { .. } => "function".to_string(),
+    }
+}
+
+/// Parameter of a KCL function.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub struct Parameter {
+    /// The parameter's label or name.
+    pub identifier: Identifier,
+    /// The type of the parameter.
+    /// This is optional if the user defines a type.
+    #[serde(skip)]
+    pub type_: Option<FnArgType>,
+    /// Is the parameter optional?
+    pub optional: bool,
+}
+
+impl Parameter {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.identifier.shift_source_ranges(offset);
+        if let Some(type_) = &mut self.type_ {
+            type_.shift_source_ranges(offset);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, ts_rs::TS, JsonSchema, Bake)]
+#[databake(path = kcl_lib::ast::types)]
+#[ts(export)]
+#[serde(tag = "type")]
+pub struct FunctionExpression {
+    pub start: usize,
+    pub end: usize,
+    pub params: Vec<Parameter>,
+    pub body: Program,
+    #[serde(skip)]
+    pub return_type: Option<FnArgType>,
+}
+
+impl_value_meta!(FunctionExpression);
+
+pub struct FunctionExpressionParts {
+    pub start: usize,
+    pub end: usize,
+    pub params_required: Vec<Parameter>,
+    pub params_optional: Vec<Parameter>,
+    pub body: Program,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RequiredParamAfterOptionalParam(pub Parameter);
+
+impl std::fmt::Display for RequiredParamAfterOptionalParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "KCL functions must declare any optional parameters after all the required parameters. But your required parameter {} is _after_ an optional parameter. You must move it to before the optional parameters instead.", self.0.identifier.name)
+    }
+}
+
+impl FunctionExpression {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        for param in &mut self.params {
+            param.shift_source_ranges(offset);
+        }
+        self.body.shift_source_ranges(offset);
+        if let Some(return_type) = &mut self.return_type {
+            return_type.shift_source_ranges(offset);
+        }
+    }
+
+    /// Function expressions don't really apply.
+    pub fn get_constraint_level(&self) -> ConstraintLevel {
+        ConstraintLevel::Ignore {
+            source_ranges: vec![self.into()],
+        }
+    }
+
+    pub fn into_parts(self) -> Result<FunctionExpressionParts, RequiredParamAfterOptionalParam> {
+        let Self {
+            start,
+            end,
+            params,
+            body,
+            return_type: _,
+        } = self;
+        let mut params_required = Vec::with_capacity(params.len());
+        let mut params_optional = Vec::with_capacity(params.len());
+        for param in params {
+            if param.optional {
+                params_optional.push(param);
+            } else {
+                if !params_optional.is_empty() {
+                    return Err(RequiredParamAfterOptionalParam(param));
+                }
+                params_required.push(param);
+            }
+        }
+        params_required.shrink_to_fit();
+        params_optional.shrink_to_fit();
+        Ok(FunctionExpressionParts {
             start,
             end,
             params_required,
@@ -3008,6 +4370,15 @@ pub struct ReturnStatement {
 
 impl_value_meta!(ReturnStatement);
 
+impl ReturnStatement {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+        self.argument.shift_source_ranges(offset);
+    }
+}
+
 /// Describes information about a hover.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -3274,6 +4645,24 @@ ghi("things")
         assert_eq!(folding_ranges[2].collapsed_text, Some("fn ghi = (x) => {".to_string()));
     }
 
+    #[test]
+    fn test_get_lsp_folding_ranges_for_loop() {
+        let code = r#"const n = 3
+for i in [0..n] {
+  const x = i * 2
+}
+for i in [1, 2] {}
+"#;
+        let tokens = crate::token::lexer(code).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast().unwrap();
+        let folding_ranges = program.get_lsp_folding_ranges();
+        assert_eq!(folding_ranges.len(), 1);
+        assert_eq!(folding_ranges[0].start_line, 29);
+        assert_eq!(folding_ranges[0].end_line, 49);
+        assert_eq!(folding_ranges[0].collapsed_text, Some("for i in [0..n] {".to_string()));
+    }
+
     #[test]
     fn test_get_lsp_symbols() {
         let code = r#"const part001 = startSketchOn('XY')
@@ -4307,6 +5696,34 @@ fn ghi = (part001) => {
         );
     }
 
+    #[test]
+    fn test_recast_after_rename_used_in_fn_body() {
+        let some_program_string = r#"const width = 2
+fn double = (x) => {
+  return x * width
+}
+fn shadowed = (width) => {
+  return width
+}"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let mut program = parser.ast().unwrap();
+        program.rename_symbol("newName", 7);
+
+        let recasted = program.recast(&Default::default(), 0);
+        assert_eq!(
+            recasted,
+            r#"const newName = 2
+fn double = (x) => {
+  return x * newName
+}
+fn shadowed = (width) => {
+  return width
+}
+"#
+        );
+    }
+
     #[test]
     fn test_recast_trailing_comma() {
         let some_program_string = r#"startSketchOn('XY')
@@ -4682,6 +6099,107 @@ const thickness = sqrt(distance * p * FOS * 6 / (sigmaAllow * width))"#;
         assert_eq!(recasted.trim(), some_program_string);
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_recast_if_else() {
+        let some_program_string = r#"const x = if a {
+  1
+} else if b {
+  const y = 2
+  y
+} else {}
+fn f = (c) => {
+  if c {
+    g()
+  }
+  return if c {
+    1
+  } else {
+    2
+  }
+}"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast().unwrap();
+
+        let recasted = program.recast(&Default::default(), 0);
+        assert_eq!(recasted.trim(), some_program_string);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_recast_comparison_and_boolean_operators() {
+        let some_program_string = r#"const a = x + 1 > y * 2 && !z || w == 4
+const b = x == (y == z)
+const c = !(x > y)
+const d = (x || y) && z"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast().unwrap();
+
+        let recasted = program.recast(&Default::default(), 0);
+        assert_eq!(recasted.trim(), some_program_string);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_recast_for_loop() {
+        let some_program_string = r#"const n = 4
+for i in [0..n - 1] {
+  const angle = i * 360 / n
+  for point in [[0, 0], [1, 1]] {
+    f(point, angle)
+  }
+}
+for i in points {}
+fn g = () => {
+  for i in [1..2] {
+    h(i)
+  }
+  return 1
+}"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast().unwrap();
+
+        let recasted = program.recast(&Default::default(), 0);
+        assert_eq!(recasted.trim(), some_program_string);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_recast_imports_and_exports() {
+        let some_program_string = r#"import { cube, sphere as ball } from "lib/shapes.kcl"
+export const width = 4
+export fn double = (x) => {
+  return x * 2
+}"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast().unwrap();
+
+        let recasted = program.recast(&Default::default(), 0);
+        assert_eq!(recasted.trim(), some_program_string);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_rename_imported_name() {
+        let some_program_string = r#"import { cube, sphere as ball } from "shapes.kcl"
+const a = cube(1)
+const b = ball(2)"#;
+        let tokens = crate::token::lexer(some_program_string).unwrap();
+        let parser = crate::parser::Parser::new(tokens);
+        let mut program = parser.ast().unwrap();
+
+        // Renaming a name without an alias gives it one, since the exported name can't change.
+        program.rename_symbol("box", 10);
+        // Renaming an alias just changes the alias.
+        program.rename_symbol("orb", 29);
+
+        assert_eq!(
+            program.recast(&Default::default(), 0).trim(),
+            r#"import { cube as box, sphere as orb } from "shapes.kcl"
+const a = box(1)
+const b = orb(2)"#
+        );
+    }
+
     #[test]
     fn recast_literal() {
         use winnow::Parser;
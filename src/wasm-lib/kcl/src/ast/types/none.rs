@@ -43,6 +43,12 @@ impl From<&KclNone> for MemoryItem {
 }
 
 impl KclNone {
+    /// Move every source range by `offset`, e.g. when code was added or removed before this.
+    pub fn shift_source_ranges(&mut self, offset: isize) {
+        self.start = self.start.saturating_add_signed(offset);
+        self.end = self.end.saturating_add_signed(offset);
+    }
+
     pub fn source_range(&self) -> SourceRange {
         SourceRange([self.start, self.end])
     }
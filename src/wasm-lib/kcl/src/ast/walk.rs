@@ -0,0 +1,269 @@
+//! Walks every node of a program, in the order they appear in the source.
+
+use crate::{
+    ast::types::{
+        ArrayExpression, BinaryExpression, BinaryPart, BodyItem, CallExpression, ElseIf, ExpressionStatement,
+        ForIterable, ForStatement, FunctionExpression, Identifier, IfExpression, ImportItem, ImportStatement, KclNone,
+        Literal, LiteralIdentifier, MemberExpression, MemberObject, ObjectExpression, ObjectProperty, Parameter,
+        PipeExpression, PipeSubstitution, Program, ReturnStatement, UnaryExpression, Value, VariableDeclaration,
+        VariableDeclarator,
+    },
+    executor::SourceRange,
+};
+
+/// A node of the AST, borrowed from the program it's in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node<'a> {
+    Program(&'a Program),
+    ExpressionStatement(&'a ExpressionStatement),
+    VariableDeclaration(&'a VariableDeclaration),
+    VariableDeclarator(&'a VariableDeclarator),
+    ReturnStatement(&'a ReturnStatement),
+    ForStatement(&'a ForStatement),
+    ImportStatement(&'a ImportStatement),
+    ImportItem(&'a ImportItem),
+    Literal(&'a Literal),
+    Identifier(&'a Identifier),
+    BinaryExpression(&'a BinaryExpression),
+    FunctionExpression(&'a FunctionExpression),
+    Parameter(&'a Parameter),
+    CallExpression(&'a CallExpression),
+    PipeExpression(&'a PipeExpression),
+    PipeSubstitution(&'a PipeSubstitution),
+    ArrayExpression(&'a ArrayExpression),
+    ObjectExpression(&'a ObjectExpression),
+    ObjectProperty(&'a ObjectProperty),
+    MemberExpression(&'a MemberExpression),
+    UnaryExpression(&'a UnaryExpression),
+    IfExpression(&'a IfExpression),
+    ElseIf(&'a ElseIf),
+    None(&'a KclNone),
+}
+
+impl<'a> Node<'a> {
+    /// The nodes directly inside this one, in source order.
+    pub fn children(&self) -> Vec<Node<'a>> {
+        match *self {
+            Node::Program(program) => program.body.iter().map(Node::from).collect(),
+            Node::ExpressionStatement(statement) => vec![(&statement.expression).into()],
+            Node::VariableDeclaration(declaration) => {
+                declaration.declarations.iter().map(Node::VariableDeclarator).collect()
+            }
+            Node::VariableDeclarator(declarator) => {
+                vec![Node::Identifier(&declarator.id), (&declarator.init).into()]
+            }
+            Node::ReturnStatement(statement) => vec![(&statement.argument).into()],
+            Node::ForStatement(statement) => {
+                let mut children = vec![Node::Identifier(&statement.variable)];
+                match &statement.iterable {
+                    ForIterable::Range { from, to, .. } => {
+                        children.push(from.into());
+                        children.push(to.into());
+                    }
+                    ForIterable::Array { value } => children.push(value.into()),
+                }
+                children.push(Node::Program(&statement.body));
+                children
+            }
+            Node::ImportStatement(statement) => statement.items.iter().map(Node::ImportItem).collect(),
+            Node::ImportItem(item) => std::iter::once(&item.name)
+                .chain(item.alias.as_ref())
+                .map(Node::Identifier)
+                .collect(),
+            Node::Literal(_) | Node::Identifier(_) | Node::PipeSubstitution(_) | Node::None(_) => Vec::new(),
+            Node::BinaryExpression(expression) => vec![(&expression.left).into(), (&expression.right).into()],
+            Node::FunctionExpression(function) => function
+                .params
+                .iter()
+                .map(Node::Parameter)
+                .chain(std::iter::once(Node::Program(&function.body)))
+                .collect(),
+            Node::Parameter(parameter) => vec![Node::Identifier(&parameter.identifier)],
+            Node::CallExpression(call) => std::iter::once(Node::Identifier(&call.callee))
+                .chain(call.arguments.iter().map(Node::from))
+                .collect(),
+            Node::PipeExpression(pipe) => pipe.body.iter().map(Node::from).collect(),
+            Node::ArrayExpression(array) => array.elements.iter().map(Node::from).collect(),
+            Node::ObjectExpression(object) => object.properties.iter().map(Node::ObjectProperty).collect(),
+            Node::ObjectProperty(property) => vec![Node::Identifier(&property.key), (&property.value).into()],
+            Node::MemberExpression(member) => vec![(&member.object).into(), (&member.property).into()],
+            Node::UnaryExpression(expression) => vec![(&expression.argument).into()],
+            Node::IfExpression(expression) => {
+                let mut children = vec![(&expression.cond).into(), Node::Program(&expression.then_val)];
+                children.extend(expression.else_ifs.iter().map(Node::ElseIf));
+                children.extend(expression.final_else.as_ref().map(Node::Program));
+                children
+            }
+            Node::ElseIf(else_if) => vec![(&else_if.cond).into(), Node::Program(&else_if.then_val)],
+        }
+    }
+
+    /// Where the node is in the source.
+    pub fn source_range(&self) -> SourceRange {
+        match *self {
+            Node::Program(program) => SourceRange([program.start, program.end]),
+            Node::ExpressionStatement(statement) => statement.into(),
+            Node::VariableDeclaration(declaration) => declaration.into(),
+            Node::VariableDeclarator(declarator) => declarator.into(),
+            Node::ReturnStatement(statement) => statement.into(),
+            Node::ForStatement(statement) => statement.into(),
+            Node::ImportStatement(statement) => statement.into(),
+            Node::ImportItem(item) => item.into(),
+            Node::Literal(literal) => literal.into(),
+            Node::Identifier(identifier) => identifier.into(),
+            Node::BinaryExpression(expression) => expression.into(),
+            Node::FunctionExpression(function) => function.into(),
+            Node::Parameter(parameter) => (&parameter.identifier).into(),
+            Node::CallExpression(call) => call.into(),
+            Node::PipeExpression(pipe) => pipe.into(),
+            Node::PipeSubstitution(substitution) => substitution.into(),
+            Node::ArrayExpression(array) => array.into(),
+            Node::ObjectExpression(object) => object.into(),
+            Node::ObjectProperty(property) => property.into(),
+            Node::MemberExpression(member) => member.into(),
+            Node::UnaryExpression(expression) => expression.into(),
+            Node::IfExpression(expression) => expression.into(),
+            Node::ElseIf(else_if) => else_if.into(),
+            Node::None(none) => none.into(),
+        }
+    }
+}
+
+impl<'a> From<&'a BodyItem> for Node<'a> {
+    fn from(item: &'a BodyItem) -> Self {
+        match item {
+            BodyItem::ExpressionStatement(statement) => Node::ExpressionStatement(statement),
+            BodyItem::VariableDeclaration(declaration) => Node::VariableDeclaration(declaration),
+            BodyItem::ReturnStatement(statement) => Node::ReturnStatement(statement),
+            BodyItem::ForStatement(statement) => Node::ForStatement(statement),
+            BodyItem::ImportStatement(statement) => Node::ImportStatement(statement),
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for Node<'a> {
+    fn from(value: &'a Value) -> Self {
+        match value {
+            Value::Literal(literal) => Node::Literal(literal),
+            Value::Identifier(identifier) => Node::Identifier(identifier),
+            Value::BinaryExpression(expression) => Node::BinaryExpression(expression),
+            Value::FunctionExpression(function) => Node::FunctionExpression(function),
+            Value::CallExpression(call) => Node::CallExpression(call),
+            Value::PipeExpression(pipe) => Node::PipeExpression(pipe),
+            Value::PipeSubstitution(substitution) => Node::PipeSubstitution(substitution),
+            Value::ArrayExpression(array) => Node::ArrayExpression(array),
+            Value::ObjectExpression(object) => Node::ObjectExpression(object),
+            Value::MemberExpression(member) => Node::MemberExpression(member),
+            Value::UnaryExpression(expression) => Node::UnaryExpression(expression),
+            Value::IfExpression(expression) => Node::IfExpression(expression),
+            Value::None(none) => Node::None(none),
+        }
+    }
+}
+
+impl<'a> From<&'a BinaryPart> for Node<'a> {
+    fn from(part: &'a BinaryPart) -> Self {
+        match part {
+            BinaryPart::Literal(literal) => Node::Literal(literal),
+            BinaryPart::Identifier(identifier) => Node::Identifier(identifier),
+            BinaryPart::BinaryExpression(expression) => Node::BinaryExpression(expression),
+            BinaryPart::CallExpression(call) => Node::CallExpression(call),
+            BinaryPart::UnaryExpression(expression) => Node::UnaryExpression(expression),
+            BinaryPart::MemberExpression(member) => Node::MemberExpression(member),
+        }
+    }
+}
+
+impl<'a> From<&'a MemberObject> for Node<'a> {
+    fn from(object: &'a MemberObject) -> Self {
+        match object {
+            MemberObject::MemberExpression(member) => Node::MemberExpression(member),
+            MemberObject::Identifier(identifier) => Node::Identifier(identifier),
+        }
+    }
+}
+
+impl<'a> From<&'a LiteralIdentifier> for Node<'a> {
+    fn from(property: &'a LiteralIdentifier) -> Self {
+        match property {
+            LiteralIdentifier::Identifier(identifier) => Node::Identifier(identifier),
+            LiteralIdentifier::Literal(literal) => Node::Literal(literal),
+        }
+    }
+}
+
+/// Call `visit` with every node in the program, parents before their children.
+/// `visit` is also given the node's ancestors, outermost first, starting with `program`.
+pub fn walk<'a>(program: &'a Program, visit: &mut impl FnMut(Node<'a>, &[Node<'a>])) {
+    fn walk_node<'a>(node: Node<'a>, ancestors: &mut Vec<Node<'a>>, visit: &mut impl FnMut(Node<'a>, &[Node<'a>])) {
+        visit(node, ancestors);
+        ancestors.push(node);
+        for child in node.children() {
+            walk_node(child, ancestors, visit);
+        }
+        ancestors.pop();
+    }
+
+    walk_node(Node::Program(program), &mut Vec::new(), visit);
+}
+
+/// Whether a node with these ancestors, as given by [`walk`], is outside of any function, loop or if branch.
+pub fn is_top_level(ancestors: &[Node]) -> bool {
+    // The first ancestor is the program itself, any other program is a nested body.
+    !ancestors.iter().skip(1).any(|node| matches!(node, Node::Program(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(code: &str) -> Program {
+        let tokens = crate::token::lexer(code).unwrap();
+        crate::parser::Parser::new(tokens).ast().unwrap()
+    }
+
+    #[test]
+    fn walks_every_identifier_in_source_order() {
+        let program = parse(
+            r#"import { width } from "dims.kcl"
+fn area = (w) => {
+  return w * width
+}
+const total = [area(2), {a: total2.b[c]}]
+for i in [0..width] {
+  if i > 1 { log(i) } else { log(none) }
+}"#,
+        );
+
+        let mut names = Vec::new();
+        walk(&program, &mut |node, _| {
+            if let Node::Identifier(identifier) = node {
+                names.push(identifier.name.clone());
+            }
+        });
+        assert_eq!(
+            names,
+            vec![
+                "width", "area", "w", "w", "width", "total", "area", "a", "total2", "b", "c", "i", "width", "i", "log",
+                "i", "log", "none"
+            ]
+        );
+    }
+
+    #[test]
+    fn gives_each_node_its_ancestors() {
+        let program = parse("const x = f(1 + 2)");
+
+        let mut literal_ancestors = Vec::new();
+        walk(&program, &mut |node, ancestors| {
+            if let Node::Literal(_) = node {
+                literal_ancestors.push(ancestors.len());
+                assert!(matches!(ancestors.first(), Some(Node::Program(_))));
+                assert!(matches!(ancestors.last(), Some(Node::BinaryExpression(_))));
+                assert!(matches!(ancestors[ancestors.len() - 2], Node::CallExpression(_)));
+            }
+        });
+        // Program, declaration, declarator, call, binary expression.
+        assert_eq!(literal_ancestors, vec![5, 5]);
+    }
+}
@@ -0,0 +1,664 @@
+//! The `kcl` command line tool, for checking, formatting and running KCL files without the modeling
+//! app, e.g. in CI or pre-commit hooks.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+
+use crate::{
+    ast::{analysis::Severity, types::Program},
+    executor::{ExecutorContext, ExecutorSettings, SourceRange},
+    fs::{FileSystem, PROJECT_SETTINGS_FILE_NAME},
+    settings::types::{project::ProjectConfiguration, LintSeverity},
+};
+
+/// Work with KCL files from the command line.
+#[derive(Parser, Debug, Clone)]
+#[clap(name = "kcl", version, about)]
+pub struct Opts {
+    #[clap(subcommand)]
+    pub subcmd: SubCommand,
+}
+
+/// A subcommand of the `kcl` tool.
+#[derive(Subcommand, Debug, Clone)]
+pub enum SubCommand {
+    /// Format KCL files in place.
+    Fmt(CmdFmt),
+    /// Check KCL files against the project's lint rules.
+    Lint(CmdLint),
+    /// Print the AST of a KCL file.
+    Parse(CmdParse),
+    /// Print the tokens of a KCL file.
+    Tokens(CmdTokens),
+    /// Find mistakes in KCL files without executing them.
+    Check(CmdCheck),
+    /// Execute a KCL file.
+    Run(CmdRun),
+    /// Run the KCL language server.
+    Lsp(crate::lsp::kcl::Server),
+}
+
+/// Format KCL files in place.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdFmt {
+    /// The files to format. Directories are searched for `.kcl` files.
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Don't change any files, just fail if any of them aren't formatted.
+    #[clap(long)]
+    pub check: bool,
+}
+
+/// Check KCL files against the project's lint rules.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdLint {
+    /// The files to lint. Directories are searched for `.kcl` files.
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// The project settings file to take the lint rules from.
+    /// By default, the closest `project.toml` to each file is used.
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// Fail on warnings as well as errors.
+    #[clap(long)]
+    pub deny_warnings: bool,
+}
+
+/// Print the AST of a KCL file.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdParse {
+    /// The file to parse.
+    pub path: PathBuf,
+
+    /// Print the AST as JSON.
+    #[clap(long)]
+    pub json: bool,
+}
+
+/// Print the tokens of a KCL file.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdTokens {
+    /// The file to tokenize.
+    pub path: PathBuf,
+}
+
+/// Find mistakes in KCL files without executing them.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdCheck {
+    /// The files to check. Directories are searched for `.kcl` files.
+    #[clap(required = true)]
+    pub paths: Vec<PathBuf>,
+}
+
+/// Execute a KCL file.
+#[derive(Parser, Debug, Clone)]
+pub struct CmdRun {
+    /// The file to execute.
+    /// Its imports are relative to the closest directory above it with a `project.toml`, or else
+    /// to the directory it's in.
+    pub path: PathBuf,
+
+    /// Don't connect to the engine, just pretend every command succeeded.
+    /// Otherwise `KITTYCAD_API_TOKEN` must be set.
+    #[clap(long)]
+    pub mock: bool,
+
+    /// How many seconds to wait for the engine to respond to each command.
+    #[clap(long, default_value = "60")]
+    pub engine_timeout: u64,
+}
+
+/// Run the command, writing what it prints to `out`.
+/// Returns false if the command found problems, so the process should exit with a failure.
+pub async fn run(opts: Opts, out: &mut impl Write) -> Result<bool> {
+    match opts.subcmd {
+        SubCommand::Fmt(cmd) => fmt(cmd, out).await,
+        SubCommand::Lint(cmd) => lint(cmd, out).await,
+        SubCommand::Parse(cmd) => parse_cmd(cmd, out).await,
+        SubCommand::Tokens(cmd) => tokens(cmd, out).await,
+        SubCommand::Check(cmd) => check(cmd, out).await,
+        SubCommand::Run(cmd) => run_cmd(cmd, out).await,
+        SubCommand::Lsp(server) => {
+            lsp(server).await?;
+            Ok(true)
+        }
+    }
+}
+
+/// The KCL files at the given paths, searching directories.
+async fn kcl_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let fs = crate::fs::FileManager::new();
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found: Vec<PathBuf> = fs
+                .get_all_files(path, SourceRange::default())
+                .await?
+                .into_iter()
+                .filter(|file| file.extension().is_some_and(|extension| extension == "kcl"))
+                .collect();
+            found.sort();
+            files.extend(found);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+async fn read_code(path: &Path) -> Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("could not read `{}`", path.display()))
+}
+
+/// Where an offset is in the code, as a 1-indexed line and column.
+fn line_column(code: &str, offset: usize) -> (usize, usize) {
+    let before = &code[..offset.min(code.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or_default() + 1;
+    (line, column)
+}
+
+/// Print a problem at a place in a file, the way compilers do.
+fn report(
+    out: &mut impl Write,
+    path: &Path,
+    code: &str,
+    source_range: SourceRange,
+    severity: &str,
+    message: &str,
+) -> Result<()> {
+    let (line, column) = line_column(code, source_range.start());
+    writeln!(out, "{}:{line}:{column}: {severity}: {message}", path.display())?;
+    Ok(())
+}
+
+/// Lex and parse the code, printing the error if it isn't valid.
+fn parse_or_report(out: &mut impl Write, path: &Path, code: &str) -> Result<Option<Program>> {
+    let result = crate::token::lexer(code).and_then(|tokens| crate::parser::Parser::new(tokens).ast());
+    match result {
+        Ok(program) => Ok(Some(program)),
+        Err(err) => {
+            let source_range = err.source_ranges().first().copied().unwrap_or_default();
+            report(
+                out,
+                path,
+                code,
+                source_range,
+                "error",
+                &err.get_message_line_column(code).0,
+            )?;
+            Ok(None)
+        }
+    }
+}
+
+async fn fmt(cmd: CmdFmt, out: &mut impl Write) -> Result<bool> {
+    let mut ok = true;
+    for path in kcl_files(&cmd.paths).await? {
+        let code = read_code(&path).await?;
+        let Some(program) = parse_or_report(out, &path, &code)? else {
+            ok = false;
+            continue;
+        };
+
+        let formatted = program.recast(&Default::default(), 0);
+        if formatted == code {
+            continue;
+        }
+        if cmd.check {
+            writeln!(out, "{} is not formatted", path.display())?;
+            ok = false;
+        } else {
+            tokio::fs::write(&path, formatted)
+                .await
+                .with_context(|| format!("could not write `{}`", path.display()))?;
+            writeln!(out, "formatted {}", path.display())?;
+        }
+    }
+    Ok(ok)
+}
+
+/// The project settings which apply to a file: the closest `project.toml` in its directory or the
+/// ones above it.
+async fn project_settings(path: &Path) -> Result<ProjectConfiguration> {
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .unwrap_or_else(|_| path.to_path_buf());
+    for dir in path.ancestors().skip(1) {
+        let settings_file = dir.join(PROJECT_SETTINGS_FILE_NAME);
+        if settings_file.is_file() {
+            return read_project_settings(&settings_file).await;
+        }
+    }
+    Ok(ProjectConfiguration::default())
+}
+
+async fn read_project_settings(settings_file: &Path) -> Result<ProjectConfiguration> {
+    let toml_str = read_code(settings_file).await?;
+    ProjectConfiguration::backwards_compatible_toml_parse(&toml_str)
+        .with_context(|| format!("could not parse `{}`", settings_file.display()))
+}
+
+async fn lint(cmd: CmdLint, out: &mut impl Write) -> Result<bool> {
+    let config = match &cmd.config {
+        Some(config) => Some(read_project_settings(config).await?),
+        None => None,
+    };
+    let stdlib = crate::std::StdLib::new();
+
+    let mut ok = true;
+    for path in kcl_files(&cmd.paths).await? {
+        let code = read_code(&path).await?;
+        let Some(program) = parse_or_report(out, &path, &code)? else {
+            ok = false;
+            continue;
+        };
+        let settings = match &config {
+            Some(config) => config.clone(),
+            None => project_settings(&path).await?,
+        };
+
+        for discovered in crate::lint::lint(&program, &stdlib, &settings.settings.lint) {
+            let fails = match discovered.severity {
+                LintSeverity::Off | LintSeverity::Hint => false,
+                LintSeverity::Warning => cmd.deny_warnings,
+                LintSeverity::Error => true,
+            };
+            ok &= !fails;
+            report(
+                out,
+                &path,
+                &code,
+                discovered.source_range,
+                &discovered.severity.to_string(),
+                &format!("{} [{}]", discovered.message, discovered.rule),
+            )?;
+        }
+    }
+    Ok(ok)
+}
+
+async fn parse_cmd(cmd: CmdParse, out: &mut impl Write) -> Result<bool> {
+    let code = read_code(&cmd.path).await?;
+    let Some(program) = parse_or_report(out, &cmd.path, &code)? else {
+        return Ok(false);
+    };
+
+    if cmd.json {
+        writeln!(out, "{}", serde_json::to_string_pretty(&program)?)?;
+    } else {
+        writeln!(out, "{:#?}", program)?;
+    }
+    Ok(true)
+}
+
+async fn tokens(cmd: CmdTokens, out: &mut impl Write) -> Result<bool> {
+    let code = read_code(&cmd.path).await?;
+    let tokens = match crate::token::lexer(&code) {
+        Ok(tokens) => tokens,
+        Err(err) => {
+            let source_range = err.source_ranges().first().copied().unwrap_or_default();
+            report(
+                out,
+                &cmd.path,
+                &code,
+                source_range,
+                "error",
+                &err.get_message_line_column(&code).0,
+            )?;
+            return Ok(false);
+        }
+    };
+
+    for token in tokens {
+        writeln!(
+            out,
+            "{}..{} {} {:?}",
+            token.start, token.end, token.token_type, token.value
+        )?;
+    }
+    Ok(true)
+}
+
+async fn check(cmd: CmdCheck, out: &mut impl Write) -> Result<bool> {
+    let stdlib = crate::std::StdLib::new();
+
+    let mut ok = true;
+    for path in kcl_files(&cmd.paths).await? {
+        let code = read_code(&path).await?;
+        let Some(program) = parse_or_report(out, &path, &code)? else {
+            ok = false;
+            continue;
+        };
+
+        let mut problems: Vec<(SourceRange, &str, String)> = crate::ast::analysis::analyze(&program, &stdlib)
+            .into_iter()
+            .map(|finding| {
+                let severity = match finding.severity {
+                    Severity::Warning => "warning",
+                    Severity::Error => "error",
+                };
+                (finding.source_range, severity, finding.message)
+            })
+            .collect();
+        problems.extend(crate::ast::type_check::check_types(&program).into_iter().map(|err| {
+            let source_range = err.source_ranges().first().copied().unwrap_or_default();
+            (source_range, "error", err.get_message_line_column(&code).0)
+        }));
+        problems.sort_by_key(|(source_range, _, _)| source_range.start());
+
+        for (source_range, severity, message) in problems {
+            ok &= severity != "error";
+            report(out, &path, &code, source_range, severity, &message)?;
+        }
+    }
+    Ok(ok)
+}
+
+async fn run_cmd(cmd: CmdRun, out: &mut impl Write) -> Result<bool> {
+    let code = read_code(&cmd.path).await?;
+    let Some(program) = parse_or_report(out, &cmd.path, &code)? else {
+        return Ok(false);
+    };
+
+    let mut settings: ExecutorSettings = project_settings(&cmd.path).await?.into();
+    let path = tokio::fs::canonicalize(&cmd.path)
+        .await
+        .unwrap_or_else(|_| cmd.path.clone());
+    // Imports are relative to the root of the project the file is in.
+    settings.project_directory = crate::fs::project_directory(&crate::fs::FileManager::new(), &path)
+        .await
+        .map(|dir| dir.display().to_string());
+    settings.engine_timeout = std::time::Duration::from_secs(cmd.engine_timeout);
+
+    let ctx = if cmd.mock {
+        ExecutorContext {
+            engine: Arc::new(Box::new(crate::engine::conn_mock::EngineConnection::new().await?)),
+            fs: Arc::new(crate::fs::FileManager::new()),
+            stdlib: Arc::new(crate::std::StdLib::new()),
+            settings,
+            is_mock: true,
+            import_stack: Vec::new(),
+        }
+    } else {
+        let token = std::env::var("KITTYCAD_API_TOKEN").context("KITTYCAD_API_TOKEN must be set to use the engine")?;
+        ExecutorContext::new(&kittycad::Client::new(token), settings).await?
+    };
+
+    match ctx.run(program, None).await {
+        Ok(memory) => {
+            writeln!(out, "{}", serde_json::to_string_pretty(&memory.root)?)?;
+            Ok(true)
+        }
+        Err(err) => {
+            let source_range = err.source_ranges().first().copied().unwrap_or_default();
+            report(
+                out,
+                &cmd.path,
+                &code,
+                source_range,
+                "error",
+                &err.get_message_line_column(&code).0,
+            )?;
+            Ok(false)
+        }
+    }
+}
+
+/// Serve the language server over stdin and stdout, or a TCP socket.
+/// It doesn't execute code, so it doesn't need an engine.
+async fn lsp(server: crate::lsp::kcl::Server) -> Result<()> {
+    let stdlib = crate::std::StdLib::new();
+    let stdlib_completions = crate::lsp::kcl::get_completions_from_stdlib(&stdlib)?;
+    let stdlib_signatures = crate::lsp::kcl::get_signatures_from_stdlib(&stdlib)?;
+    let token_types = crate::token::TokenType::all_semantic_token_types()?;
+    // The client is only used for telemetry, which needs a token.
+    let zoo_client = kittycad::Client::new(std::env::var("KITTYCAD_API_TOKEN").unwrap_or_default());
+
+    let (service, socket) = tower_lsp::LspService::build(|client| crate::lsp::kcl::Backend {
+        client,
+        fs: Arc::new(crate::fs::FileManager::new()),
+        workspace_folders: Default::default(),
+        stdlib_completions,
+        stdlib_signatures,
+        token_types,
+        token_map: Default::default(),
+        ast_map: Default::default(),
+        parsed_map: Default::default(),
+        memory_map: Default::default(),
+        code_map: Default::default(),
+        diagnostics_map: Default::default(),
+        symbols_map: Default::default(),
+        workspace_symbols_map: Default::default(),
+        semantic_tokens_map: Default::default(),
+        zoo_client,
+        can_send_telemetry: false,
+        executor_ctx: Default::default(),
+        can_execute: Default::default(),
+        lint_settings: Default::default(),
+        is_initialized: Default::default(),
+        current_handle: Default::default(),
+    })
+    .custom_method("kcl/updateUnits", crate::lsp::kcl::Backend::update_units)
+    .custom_method("kcl/updateCanExecute", crate::lsp::kcl::Backend::update_can_execute)
+    .finish();
+
+    if server.stdio {
+        tower_lsp::Server::new(tokio::io::stdin(), tokio::io::stdout(), socket)
+            .serve(service)
+            .await;
+    } else {
+        let listener = tokio::net::TcpListener::bind(format!("127.0.0.1:{}", server.socket)).await?;
+        let (stream, _) = listener.accept().await?;
+        let (read, write) = tokio::io::split(stream);
+        tower_lsp::Server::new(read, write, socket).serve(service).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Write the files into a fresh directory, and run the command with the directory as its
+    /// working directory.
+    async fn run_in_project(args: &[&str], files: &[(&str, &str)]) -> (bool, String, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("kcl_cli_{}", uuid::Uuid::new_v4()));
+        for (name, contents) in files {
+            let path = dir.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        }
+
+        let args = std::iter::once("kcl".to_string()).chain(args.iter().map(|arg| {
+            if *arg == "." {
+                dir.display().to_string()
+            } else if arg.ends_with(".kcl") || arg.ends_with(".toml") {
+                dir.join(arg).display().to_string()
+            } else {
+                arg.to_string()
+            }
+        }));
+        let opts = Opts::try_parse_from(args).unwrap();
+        let mut out = Vec::new();
+        let ok = run(opts, &mut out).await.unwrap();
+        let out = String::from_utf8(out)
+            .unwrap()
+            .replace(&format!("{}/", dir.display()), "");
+        (ok, out, dir)
+    }
+
+    #[test]
+    fn line_columns() {
+        let code = "const a = 1\nconst b = 2\n";
+        assert_eq!(line_column(code, 0), (1, 1));
+        assert_eq!(line_column(code, 6), (1, 7));
+        assert_eq!(line_column(code, 12), (2, 1));
+        assert_eq!(line_column(code, 18), (2, 7));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fmt_check_and_write() {
+        let files = [
+            ("a.kcl", "const a = 1\n"),
+            ("dir/b.kcl", "const  b=2"),
+            ("dir/c.txt", "not kcl"),
+        ];
+
+        let (ok, out, dir) = run_in_project(&["fmt", "--check", "."], &files).await;
+        assert!(!ok);
+        assert_eq!(out, "dir/b.kcl is not formatted\n");
+        assert_eq!(std::fs::read_to_string(dir.join("dir/b.kcl")).unwrap(), "const  b=2");
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let (ok, out, dir) = run_in_project(&["fmt", "a.kcl", "dir/b.kcl"], &files).await;
+        assert!(ok);
+        assert_eq!(out, "formatted dir/b.kcl\n");
+        assert_eq!(std::fs::read_to_string(dir.join("dir/b.kcl")).unwrap(), "const b = 2\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fmt_reports_syntax_errors() {
+        let (ok, out, dir) = run_in_project(&["fmt", "a.kcl"], &[("a.kcl", "const a = 1\nconst = 2")]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(!ok);
+        assert_eq!(
+            out,
+            "a.kcl:2:7: error: syntax: Cannot assign a variable to a reserved keyword: =\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn lint_uses_project_settings() {
+        let code = "const my_width = 2\nconst height = my_width * 3\n";
+
+        let (ok, out, dir) = run_in_project(&["lint", "a.kcl"], &[("a.kcl", code)]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(ok);
+        assert_eq!(
+            out,
+            "a.kcl:1:7: warning: `my_width` should be camelCase, e.g. `myWidth` [camel_case]\n"
+        );
+
+        let (ok, _, dir) = run_in_project(&["lint", "--deny-warnings", "a.kcl"], &[("a.kcl", code)]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(!ok);
+
+        let settings = "[settings.lint]\ncamel_case = \"off\"\nmagic_number = \"error\"\n";
+        let (ok, out, dir) = run_in_project(
+            &["lint", "parts/a.kcl"],
+            &[("parts/a.kcl", code), ("project.toml", settings)],
+        )
+        .await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(!ok);
+        assert_eq!(
+            out,
+            "parts/a.kcl:2:27: error: `3` is a magic number, bind it to a variable which says what it means [magic_number]\n"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn check_finds_mistakes() {
+        let code = r#"fn add = (a: number, b: number) => number {
+  const unused = 1
+  return a + b
+}
+const x = add(1, "2")
+const y = add(undefinedName)
+"#;
+        let (ok, out, dir) = run_in_project(&["check", "a.kcl"], &[("a.kcl", code)]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(!ok);
+        assert_eq!(
+            out,
+            r#"a.kcl:2:9: warning: The variable `unused` is never used
+a.kcl:5:18: error: type: The parameter `b` was declared as `number`, but this argument is a `string`
+a.kcl:6:11: error: `add` expects 2 arguments, but it was given 1
+a.kcl:6:15: error: `undefinedName` is not defined
+"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn parse_and_tokens() {
+        let (ok, out, dir) = run_in_project(&["parse", "--json", "a.kcl"], &[("a.kcl", "const a = 1")]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(ok);
+        let program: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(program["body"][0]["type"], "VariableDeclaration");
+
+        let (ok, out, dir) = run_in_project(&["tokens", "a.kcl"], &[("a.kcl", "const a = 1")]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(ok);
+        assert_eq!(
+            out,
+            r#"0..5 keyword "const"
+5..6 whitespace " "
+6..7 word "a"
+7..8 whitespace " "
+8..9 operator "="
+9..10 whitespace " "
+10..11 number "1"
+"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_mock_with_imports() {
+        let (ok, out, dir) = run_in_project(
+            &["run", "--mock", "main.kcl"],
+            &[
+                (
+                    "main.kcl",
+                    "import { width } from \"dims.kcl\"\nconst area = width * width\n",
+                ),
+                ("dims.kcl", "export const width = 3\n"),
+            ],
+        )
+        .await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(ok);
+        let root: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(root["area"]["value"], serde_json::json!(9.0));
+        assert_eq!(root["width"]["value"], serde_json::json!(3));
+
+        // Imports are relative to the project root, where `project.toml` is.
+        let (ok, out, dir) = run_in_project(
+            &["run", "--mock", "parts/main.kcl"],
+            &[
+                (
+                    "parts/main.kcl",
+                    "import { width } from \"lib/dims.kcl\"\nconst area = width * width\n",
+                ),
+                ("lib/dims.kcl", "export const width = 3\n"),
+                ("project.toml", ""),
+            ],
+        )
+        .await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(ok, "{out}");
+
+        let (ok, out, dir) = run_in_project(&["run", "--mock", "main.kcl"], &[("main.kcl", "const a = b\n")]).await;
+        std::fs::remove_dir_all(dir).unwrap();
+        assert!(!ok);
+        assert_eq!(
+            out,
+            "main.kcl:1:11: error: undefined value: memory item key `b` is not defined\n"
+        );
+    }
+}
@@ -5,17 +5,26 @@ use std::sync::{Arc, Mutex};
 
 use anyhow::{anyhow, Result};
 use dashmap::DashMap;
-use futures::{SinkExt, StreamExt};
-use kittycad::types::{OkWebSocketResponseData, WebSocketRequest, WebSocketResponse};
+use futures::{future::BoxFuture, SinkExt, StreamExt};
+use kittycad::types::{ModelingCmd, OkWebSocketResponseData, WebSocketRequest, WebSocketResponse};
 use tokio::sync::{mpsc, oneshot, RwLock};
 use tokio_tungstenite::tungstenite::Message as WsMsg;
 
 use crate::{
-    engine::EngineManager,
+    engine::{ConnectionHealth, ConnectionStatus, EngineManager},
     errors::{KclError, KclErrorDetails},
     executor::DefaultPlanes,
 };
 
+/// How many times to try connecting again after the connection drops, before giving up until the
+/// next command.
+const RECONNECT_ATTEMPTS: u32 = 5;
+/// How long to wait after the first failed attempt to connect again, which doubles after each one.
+const RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+/// How many bytes of commands to remember for restoring the scene after connecting again. Past
+/// this, the scene can't be restored, and is cleared instead.
+const MAX_HISTORY_BYTES: usize = 64 * 1024 * 1024;
+
 #[derive(Debug, PartialEq)]
 enum SocketHealth {
     Active,
@@ -24,18 +33,53 @@ enum SocketHealth {
 
 type WebSocketTcpWrite = futures::stream::SplitSink<tokio_tungstenite::WebSocketStream<reqwest::Upgraded>, WsMsg>;
 #[derive(Debug, Clone)]
-#[allow(dead_code)] // for the TcpReadHandle
 pub struct EngineConnection {
-    engine_req_tx: mpsc::Sender<ToEngineReq>,
-    responses: Arc<DashMap<uuid::Uuid, WebSocketResponse>>,
-    tcp_read_handle: Arc<TcpReadHandle>,
-    socket_health: Arc<Mutex<SocketHealth>>,
+    /// The websocket we're using, which is replaced when the connection drops and we connect again.
+    session: Arc<RwLock<Arc<Session>>>,
+    connector: Connector,
+    /// The commands the engine did since the scene was last cleared, to do again after connecting again.
+    history: Arc<Mutex<History>>,
+    health: Arc<Mutex<ConnectionHealth>>,
     batch: Arc<Mutex<Vec<(WebSocketRequest, crate::executor::SourceRange)>>>,
+    /// How long to wait for the response to a modeling command.
+    response_timeout: std::time::Duration,
 
     /// The default planes for the scene.
     default_planes: Arc<RwLock<Option<DefaultPlanes>>>,
 }
 
+/// Opens a new websocket to the engine, for the first connection and for connecting again after it
+/// drops.
+#[derive(Clone)]
+pub struct Connector(Arc<dyn Fn() -> BoxFuture<'static, Result<reqwest::Upgraded>> + Send + Sync>);
+
+impl Connector {
+    pub fn new(connect: impl Fn() -> BoxFuture<'static, Result<reqwest::Upgraded>> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(connect))
+    }
+
+    async fn connect(&self) -> Result<reqwest::Upgraded> {
+        (self.0)().await
+    }
+}
+
+impl std::fmt::Debug for Connector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Connector")
+    }
+}
+
+/// A websocket to the engine, with the tasks writing requests to it and reading responses from it.
+#[derive(Debug)]
+#[allow(dead_code)] // for the TcpReadHandle
+struct Session {
+    engine_req_tx: mpsc::Sender<ToEngineReq>,
+    /// The requests waiting for a response, which the read task sends them when it arrives.
+    pending_responses: Arc<DashMap<uuid::Uuid, oneshot::Sender<WebSocketResponse>>>,
+    tcp_read_handle: TcpReadHandle,
+    socket_health: Arc<Mutex<SocketHealth>>,
+}
+
 pub struct TcpRead {
     stream: futures::stream::SplitStream<tokio_tungstenite::WebSocketStream<reqwest::Upgraded>>,
 }
@@ -125,7 +169,153 @@ impl EngineConnection {
         Ok(())
     }
 
-    pub async fn new(ws: reqwest::Upgraded) -> Result<EngineConnection> {
+    /// Connect to the engine, giving up on modeling commands which get no response within
+    /// `response_timeout`.
+    pub async fn new(connector: Connector, response_timeout: std::time::Duration) -> Result<EngineConnection> {
+        let session = Session::new(connector.connect().await?).await;
+
+        Ok(EngineConnection {
+            session: Arc::new(RwLock::new(Arc::new(session))),
+            connector,
+            history: Default::default(),
+            health: Default::default(),
+            batch: Arc::new(Mutex::new(Vec::new())),
+            response_timeout,
+            default_planes: Default::default(),
+        })
+    }
+
+    /// Connect to the engine through the Zoo API, connecting the same way again if the connection
+    /// drops.
+    pub async fn connect(
+        client: &kittycad::Client,
+        enable_ssao: bool,
+        response_timeout: std::time::Duration,
+    ) -> Result<EngineConnection> {
+        let client = client.clone();
+        let post_effect = if enable_ssao {
+            Some(kittycad::types::PostEffectType::Ssao)
+        } else {
+            None
+        };
+        let connector = Connector::new(move || {
+            let client = client.clone();
+            let post_effect = post_effect.clone();
+            Box::pin(async move {
+                let ws = client
+                    .modeling()
+                    .commands_ws(None, None, post_effect, None, None, None, Some(false))
+                    .await?;
+                Ok(ws)
+            })
+        });
+
+        Self::new(connector, response_timeout).await
+    }
+
+    /// The session to send commands with, connecting again first if the connection dropped.
+    async fn active_session(&self, source_range: crate::executor::SourceRange) -> Result<Arc<Session>, KclError> {
+        let session = self.session.read().await.clone();
+        if session.is_active() {
+            return Ok(session);
+        }
+
+        let mut current = self.session.write().await;
+        // Another command might have connected again while we waited.
+        if current.is_active() {
+            return Ok(current.clone());
+        }
+        self.health.lock().unwrap().status = ConnectionStatus::Reconnecting;
+
+        let session = match self.reconnect().await {
+            Ok(session) => Arc::new(session),
+            Err(err) => {
+                let mut health = self.health.lock().unwrap();
+                health.status = ConnectionStatus::Disconnected;
+                health.last_error = Some(err.to_string());
+                return Err(KclError::Engine(KclErrorDetails {
+                    message: format!("Modeling command failed: websocket closed early, and reconnecting failed: {err}"),
+                    source_ranges: vec![source_range],
+                }));
+            }
+        };
+        *current = session.clone();
+        let restored = self.replay_history(&session, source_range).await;
+        drop(current);
+
+        {
+            let mut health = self.health.lock().unwrap();
+            health.status = ConnectionStatus::Connected;
+            health.reconnects += 1;
+            health.last_error = restored.as_ref().err().map(|err| err.message().to_string());
+        }
+        if let Err(err) = restored {
+            // Start again from an empty scene with new default planes, since whatever was made
+            // before is gone. Clearing it also forgets the commands which made it.
+            self.clear_scene(source_range).await?;
+            return Err(KclError::Engine(KclErrorDetails {
+                message: format!(
+                    "The connection to the engine dropped and the scene could not be restored, so it was cleared; run the program again: {}",
+                    err.message()
+                ),
+                source_ranges: vec![source_range],
+            }));
+        }
+
+        Ok(session)
+    }
+
+    /// Open a new websocket to the engine, waiting longer after each failed attempt.
+    async fn reconnect(&self) -> Result<Session> {
+        let mut backoff = RECONNECT_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match self.connector.connect().await {
+                Ok(ws) => return Ok(Session::new(ws).await),
+                Err(err) if attempt >= RECONNECT_ATTEMPTS => return Err(err),
+                Err(err) => println!("reconnecting to the engine failed: {:?}", err),
+            }
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+
+    /// Do the commands which made the scene again, with the same ids, so everything refers to the
+    /// same things as before.
+    async fn replay_history(
+        &self,
+        session: &Session,
+        source_range: crate::executor::SourceRange,
+    ) -> Result<(), KclError> {
+        let history = {
+            let history = self.history.lock().unwrap();
+            if history.overflowed {
+                return Err(KclError::Engine(KclErrorDetails {
+                    message: format!(
+                        "The scene was made with more than {} MiB of commands, which is too many to send again",
+                        MAX_HISTORY_BYTES / 1024 / 1024
+                    ),
+                    source_ranges: vec![source_range],
+                }));
+            }
+            history.commands.clone()
+        };
+        for (id, req) in history {
+            let resp = session.send(id, req, source_range, self.response_timeout).await?;
+            if resp.resp.is_none() {
+                return Err(KclError::Engine(KclErrorDetails {
+                    message: format!("Replaying modeling command failed: {:?}", resp.errors),
+                    source_ranges: vec![source_range],
+                }));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Session {
+    async fn new(ws: reqwest::Upgraded) -> Session {
         let ws_stream = tokio_tungstenite::WebSocketStream::from_raw_socket(
             ws,
             tokio_tungstenite::tungstenite::protocol::Role::Client,
@@ -135,12 +325,12 @@ impl EngineConnection {
 
         let (tcp_write, tcp_read) = ws_stream.split();
         let (engine_req_tx, engine_req_rx) = mpsc::channel(10);
-        tokio::task::spawn(Self::start_write_actor(tcp_write, engine_req_rx));
+        tokio::task::spawn(EngineConnection::start_write_actor(tcp_write, engine_req_rx));
 
         let mut tcp_read = TcpRead { stream: tcp_read };
 
-        let responses: Arc<DashMap<uuid::Uuid, WebSocketResponse>> = Arc::new(DashMap::new());
-        let responses_clone = responses.clone();
+        let pending_responses: Arc<DashMap<uuid::Uuid, oneshot::Sender<WebSocketResponse>>> = Arc::new(DashMap::new());
+        let pending_responses_clone = pending_responses.clone();
         let socket_health = Arc::new(Mutex::new(SocketHealth::Active));
 
         let socket_health_tcp_read = socket_health.clone();
@@ -152,29 +342,113 @@ impl EngineConnection {
                         for e in ws_resp.errors.iter().flatten() {
                             println!("got error message: {e}");
                         }
-                        if let Some(id) = ws_resp.request_id {
-                            responses_clone.insert(id, ws_resp.clone());
+                        // Nobody is waiting for responses to requests which timed out.
+                        if let Some((_, response_tx)) =
+                            ws_resp.request_id.and_then(|id| pending_responses_clone.remove(&id))
+                        {
+                            let _ = response_tx.send(ws_resp);
                         }
                     }
                     Err(e) => {
                         println!("got ws error: {:?}", e);
                         *socket_health_tcp_read.lock().unwrap() = SocketHealth::Inactive;
+                        // No more responses are coming, so stop everything waiting for one.
+                        pending_responses_clone.clear();
                         return Err(e);
                     }
                 }
             }
         });
 
-        Ok(EngineConnection {
+        Session {
             engine_req_tx,
-            tcp_read_handle: Arc::new(TcpReadHandle {
+            pending_responses,
+            tcp_read_handle: TcpReadHandle {
                 handle: Arc::new(tcp_read_handle),
-            }),
-            responses,
+            },
             socket_health,
-            batch: Arc::new(Mutex::new(Vec::new())),
-            default_planes: Default::default(),
-        })
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        *self.socket_health.lock().unwrap() == SocketHealth::Active
+    }
+
+    /// Send a request to the engine and wait for the response to it.
+    async fn send(
+        &self,
+        id: uuid::Uuid,
+        req: WebSocketRequest,
+        source_range: crate::executor::SourceRange,
+        timeout: std::time::Duration,
+    ) -> Result<WebSocketResponse, KclError> {
+        // Wait for the response from before the request is sent, so it can't be missed.
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending_responses.insert(id, response_tx);
+        // The socket might have closed after we last heard from it.
+        if !self.is_active() {
+            self.pending_responses.remove(&id);
+            return Err(KclError::Engine(KclErrorDetails {
+                message: "Modeling command failed: websocket closed early".to_string(),
+                source_ranges: vec![source_range],
+            }));
+        }
+
+        if let Err(err) = self.send_to_write_actor(req, source_range).await {
+            self.pending_responses.remove(&id);
+            return Err(err);
+        }
+
+        // Wait for the response.
+        match tokio::time::timeout(timeout, response_rx).await {
+            Ok(Ok(resp)) => Ok(resp),
+            // The read task stopped waiting for responses.
+            Ok(Err(_)) => Err(KclError::Engine(KclErrorDetails {
+                message: "Modeling command failed: websocket closed early".to_string(),
+                source_ranges: vec![source_range],
+            })),
+            Err(_) => {
+                self.pending_responses.remove(&id);
+                Err(KclError::Timeout(KclErrorDetails {
+                    message: format!("Modeling command `{}` got no response within {:?}", id, timeout),
+                    source_ranges: vec![source_range],
+                }))
+            }
+        }
+    }
+
+    /// Send the request to the engine, via the write actor, and wait for it to be sent.
+    async fn send_to_write_actor(
+        &self,
+        req: WebSocketRequest,
+        source_range: crate::executor::SourceRange,
+    ) -> Result<(), KclError> {
+        let (tx, rx) = oneshot::channel();
+
+        self.engine_req_tx
+            .send(ToEngineReq { req, request_sent: tx })
+            .await
+            .map_err(|e| {
+                KclError::Engine(KclErrorDetails {
+                    message: format!("Failed to send modeling command: {}", e),
+                    source_ranges: vec![source_range],
+                })
+            })?;
+
+        // Wait for the request to be sent.
+        rx.await
+            .map_err(|e| {
+                KclError::Engine(KclErrorDetails {
+                    message: format!("could not send request to the engine actor: {e}"),
+                    source_ranges: vec![source_range],
+                })
+            })?
+            .map_err(|e| {
+                KclError::Engine(KclErrorDetails {
+                    message: format!("could not send request to the engine: {e}"),
+                    source_ranges: vec![source_range],
+                })
+            })
     }
 }
 
@@ -184,6 +458,19 @@ impl EngineManager for EngineConnection {
         self.batch.clone()
     }
 
+    fn connection_health(&self) -> ConnectionHealth {
+        let mut health = self.health.lock().unwrap().clone();
+        // The socket might have closed since the last command, which notices when it's sent.
+        if health.status == ConnectionStatus::Connected {
+            if let Ok(session) = self.session.try_read() {
+                if !session.is_active() {
+                    health.status = ConnectionStatus::Disconnected;
+                }
+            }
+        }
+        health
+    }
+
     async fn default_planes(&self, source_range: crate::executor::SourceRange) -> Result<DefaultPlanes, KclError> {
         {
             let opt = self.default_planes.read().await.as_ref().cloned();
@@ -213,64 +500,268 @@ impl EngineManager for EngineConnection {
         cmd: kittycad::types::WebSocketRequest,
         _id_to_source_range: std::collections::HashMap<uuid::Uuid, crate::executor::SourceRange>,
     ) -> Result<OkWebSocketResponseData, KclError> {
-        let (tx, rx) = oneshot::channel();
+        // If the connection drops while we wait, the command is sent again once we've connected again
+        // and restored the scene, since the engine we connect to again hasn't done it.
+        let mut retried = false;
+        let resp = loop {
+            let session = self.active_session(source_range).await?;
+            match session.send(id, cmd.clone(), source_range, self.response_timeout).await {
+                Ok(resp) => break resp,
+                Err(_) if !retried && !session.is_active() => retried = true,
+                Err(err) => return Err(err),
+            }
+        };
 
-        // Send the request to the engine, via the actor.
-        self.engine_req_tx
-            .send(ToEngineReq {
-                req: cmd.clone(),
-                request_sent: tx,
-            })
-            .await
-            .map_err(|e| {
-                KclError::Engine(KclErrorDetails {
-                    message: format!("Failed to send modeling command: {}", e),
-                    source_ranges: vec![source_range],
-                })
-            })?;
+        if let Some(data) = &resp.resp {
+            self.history.lock().unwrap().record(id, cmd);
+            Ok(data.clone())
+        } else {
+            Err(KclError::Engine(KclErrorDetails {
+                message: format!("Modeling command failed: {:?}", resp.errors),
+                source_ranges: vec![source_range],
+            }))
+        }
+    }
+}
 
-        // Wait for the request to be sent.
-        rx.await
-            .map_err(|e| {
-                KclError::Engine(KclErrorDetails {
-                    message: format!("could not send request to the engine actor: {e}"),
-                    source_ranges: vec![source_range],
-                })
-            })?
-            .map_err(|e| {
-                KclError::Engine(KclErrorDetails {
-                    message: format!("could not send request to the engine: {e}"),
-                    source_ranges: vec![source_range],
-                })
-            })?;
+/// The commands which made the scene, to do again after connecting again.
+#[derive(Debug, Default)]
+struct History {
+    /// The commands, with their ids. Nothing from before the scene was last cleared is needed to
+    /// restore it, other than the scene's settings, which clearing the scene doesn't undo.
+    commands: Vec<(uuid::Uuid, WebSocketRequest)>,
+    /// Roughly how many bytes the commands take up.
+    size: usize,
+    /// Whether commands were forgotten because there were too many of them, so the scene can't be
+    /// restored until it's cleared.
+    overflowed: bool,
+}
 
-        // Wait for the response.
-        let current_time = std::time::Instant::now();
-        while current_time.elapsed().as_secs() < 60 {
-            if let Ok(guard) = self.socket_health.lock() {
-                if *guard == SocketHealth::Inactive {
-                    return Err(KclError::Engine(KclErrorDetails {
-                        message: "Modeling command failed: websocket closed early".to_string(),
-                        source_ranges: vec![source_range],
-                    }));
-                }
+impl History {
+    /// Remember a command the engine did.
+    fn record(&mut self, id: uuid::Uuid, req: WebSocketRequest) {
+        if clears_scene(&req) {
+            self.keep_scene_settings();
+            self.overflowed = false;
+        } else if self.overflowed {
+            return;
+        }
+
+        self.size += request_size(&req);
+        self.commands.push((id, req));
+        if self.size > MAX_HISTORY_BYTES {
+            // Forget the commands rather than keep using more memory.
+            self.keep_scene_settings();
+            self.overflowed = true;
+        }
+    }
+
+    fn keep_scene_settings(&mut self) {
+        self.commands = self
+            .commands
+            .drain(..)
+            .filter_map(|(id, req)| scene_settings(req).map(|req| (id, req)))
+            .collect();
+        self.size = self.commands.iter().map(|(_, req)| request_size(req)).sum();
+    }
+}
+
+/// Roughly how many bytes a request takes up, counting the files it imports.
+fn request_size(req: &WebSocketRequest) -> usize {
+    let files_size = |cmd: &ModelingCmd| match cmd {
+        ModelingCmd::ImportFiles { files, .. } => files.iter().map(|file| file.path.len() + file.data.len()).sum(),
+        _ => 0,
+    };
+    let cmds_size = match req {
+        WebSocketRequest::ModelingCmdReq { cmd, .. } => files_size(cmd),
+        WebSocketRequest::ModelingCmdBatchReq { requests, .. } => requests.iter().map(|req| files_size(&req.cmd)).sum(),
+        _ => 0,
+    };
+    std::mem::size_of::<WebSocketRequest>() + cmds_size
+}
+
+fn clears_scene(req: &WebSocketRequest) -> bool {
+    match req {
+        WebSocketRequest::ModelingCmdReq { cmd, .. } => matches!(cmd, ModelingCmd::SceneClearAll { .. }),
+        WebSocketRequest::ModelingCmdBatchReq { requests, .. } => requests
+            .iter()
+            .any(|req| matches!(req.cmd, ModelingCmd::SceneClearAll { .. })),
+        _ => false,
+    }
+}
+
+/// The commands in the request which set up the scene rather than add to it, if there are any.
+fn scene_settings(req: WebSocketRequest) -> Option<WebSocketRequest> {
+    let is_setting = |cmd: &ModelingCmd| {
+        matches!(
+            cmd,
+            ModelingCmd::EdgeLinesVisible { .. } | ModelingCmd::SetSceneUnits { .. }
+        )
+    };
+    match req {
+        WebSocketRequest::ModelingCmdReq { ref cmd, .. } if is_setting(cmd) => Some(req),
+        WebSocketRequest::ModelingCmdBatchReq {
+            requests,
+            batch_id,
+            responses,
+        } => {
+            let requests: Vec<_> = requests.into_iter().filter(|req| is_setting(&req.cmd)).collect();
+            if requests.is_empty() {
+                None
+            } else {
+                Some(WebSocketRequest::ModelingCmdBatchReq {
+                    requests,
+                    batch_id,
+                    responses,
+                })
             }
-            // We pop off the responses to cleanup our mappings.
-            if let Some((_, resp)) = self.responses.remove(&id) {
-                return if let Some(data) = &resp.resp {
-                    Ok(data.clone())
-                } else {
-                    Err(KclError::Engine(KclErrorDetails {
-                        message: format!("Modeling command failed: {:?}", resp.errors),
-                        source_ranges: vec![source_range],
-                    }))
-                };
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::executor::SourceRange;
+
+    fn req(cmd: ModelingCmd) -> (uuid::Uuid, WebSocketRequest) {
+        let id = uuid::Uuid::new_v4();
+        (id, WebSocketRequest::ModelingCmdReq { cmd, cmd_id: id })
+    }
+
+    #[test]
+    fn record_keeps_scene_settings_after_clearing() {
+        let mut history = History::default();
+        let edges = req(ModelingCmd::EdgeLinesVisible { hidden: true });
+        let plane = req(ModelingCmd::ObjectVisible {
+            object_id: uuid::Uuid::new_v4(),
+            hidden: false,
+        });
+        let clear = req(ModelingCmd::SceneClearAll {});
+        history.record(edges.0, edges.1);
+        history.record(plane.0, plane.1);
+        assert_eq!(history.commands.len(), 2);
+
+        history.record(clear.0, clear.1);
+        let ids: Vec<_> = history.commands.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![edges.0, clear.0]);
+    }
+
+    #[test]
+    fn record_forgets_everything_but_settings_when_the_history_is_too_big() {
+        let import = |size| {
+            req(ModelingCmd::ImportFiles {
+                files: vec![kittycad::types::ImportFile {
+                    data: vec![0; size],
+                    path: "cube.step".to_string(),
+                }],
+                format: kittycad::types::InputFormat::Step {},
+            })
+        };
+        let mut history = History::default();
+        let edges = req(ModelingCmd::EdgeLinesVisible { hidden: true });
+        history.record(edges.0, edges.1);
+        let small = import(1024);
+        history.record(small.0, small.1);
+        assert!(!history.overflowed);
+        assert_eq!(history.commands.len(), 2);
+
+        let big = import(MAX_HISTORY_BYTES);
+        history.record(big.0, big.1);
+        assert!(history.overflowed);
+        let ids: Vec<_> = history.commands.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![edges.0]);
+        assert!(history.size < 1024);
+
+        // Nothing else is remembered until the scene is cleared.
+        let after = import(1);
+        history.record(after.0, after.1);
+        assert_eq!(history.commands.len(), 1);
+        let clear = req(ModelingCmd::SceneClearAll {});
+        history.record(clear.0, clear.1);
+        assert!(!history.overflowed);
+        let ids: Vec<_> = history.commands.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![edges.0, clear.0]);
+    }
+
+    /// A session which sends every request, but where the engine never responds.
+    /// `on_sent` is called with the requests waiting for a response after each one is sent.
+    fn fake_session(
+        on_sent: impl Fn(&DashMap<uuid::Uuid, oneshot::Sender<WebSocketResponse>>) + Send + 'static,
+    ) -> Session {
+        let (engine_req_tx, mut engine_req_rx) = mpsc::channel::<ToEngineReq>(10);
+        let pending_responses: Arc<DashMap<uuid::Uuid, oneshot::Sender<WebSocketResponse>>> = Arc::new(DashMap::new());
+        let pending_responses_clone = pending_responses.clone();
+        tokio::spawn(async move {
+            while let Some(ToEngineReq { request_sent, .. }) = engine_req_rx.recv().await {
+                let _ = request_sent.send(Ok(()));
+                on_sent(&pending_responses_clone);
             }
+        });
+
+        Session {
+            engine_req_tx,
+            pending_responses,
+            tcp_read_handle: TcpReadHandle {
+                handle: Arc::new(tokio::spawn(std::future::pending())),
+            },
+            socket_health: Arc::new(Mutex::new(SocketHealth::Active)),
         }
+    }
 
-        Err(KclError::Engine(KclErrorDetails {
-            message: format!("Modeling command timed out `{}`", id),
-            source_ranges: vec![source_range],
-        }))
+    #[tokio::test]
+    async fn send_times_out_without_a_response() {
+        let session = fake_session(|_| {});
+        let (id, req) = req(ModelingCmd::SceneClearAll {});
+        let result = session
+            .send(id, req, SourceRange([1, 2]), std::time::Duration::from_millis(10))
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            KclError::Timeout(KclErrorDetails {
+                message: format!("Modeling command `{}` got no response within 10ms", id),
+                source_ranges: vec![SourceRange([1, 2])],
+            })
+        );
+        // Nothing is left waiting for the response.
+        assert!(session.pending_responses.is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_fails_when_the_socket_closes_while_waiting() {
+        // This is what the read task does when the websocket errors.
+        let session = fake_session(|pending_responses| pending_responses.clear());
+        let (id, req) = req(ModelingCmd::SceneClearAll {});
+        let result = session
+            .send(id, req, SourceRange([1, 2]), std::time::Duration::from_secs(60))
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            KclError::Engine(KclErrorDetails {
+                message: "Modeling command failed: websocket closed early".to_string(),
+                source_ranges: vec![SourceRange([1, 2])],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn send_fails_when_the_socket_already_closed() {
+        let session = fake_session(|_| panic!("nothing should be sent"));
+        *session.socket_health.lock().unwrap() = SocketHealth::Inactive;
+        let (id, req) = req(ModelingCmd::SceneClearAll {});
+        let result = session
+            .send(id, req, SourceRange([1, 2]), std::time::Duration::from_secs(60))
+            .await;
+        assert_eq!(
+            result.unwrap_err(),
+            KclError::Engine(KclErrorDetails {
+                message: "Modeling command failed: websocket closed early".to_string(),
+                source_ranges: vec![SourceRange([1, 2])],
+            })
+        );
+        assert!(session.pending_responses.is_empty());
     }
 }
@@ -1,22 +1,38 @@
-//! Functions for setting up our WebSocket and WebRTC connections for communications with the
-//! engine.
+//! A stand-in for the engine, for executing KCL without a connection to the Zoo API.
+//! It keeps track of the geometry the modeling commands make, so it can give plausible answers to
+//! the commands which ask about it.
 
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use anyhow::Result;
-use kittycad::types::{OkWebSocketResponseData, WebSocketRequest};
+use kittycad::types::{
+    CurveType, EntityType, ExtrusionFaceCapType, ExtrusionFaceInfo, ModelingCmd, OkModelingCmdResponse,
+    OkWebSocketResponseData, PathCommand, PathSegment, PathSegmentInfo, Point3D, UnitAngle, WebSocketRequest,
+};
+use tokio::sync::RwLock;
+use uuid::Uuid;
 
-use crate::{errors::KclError, executor::DefaultPlanes};
+use crate::{
+    errors::{KclError, KclErrorDetails},
+    executor::DefaultPlanes,
+};
 
 #[derive(Debug, Clone)]
 pub struct EngineConnection {
     batch: Arc<Mutex<Vec<(WebSocketRequest, crate::executor::SourceRange)>>>,
+    scene: Arc<Mutex<Scene>>,
+    default_planes: Arc<RwLock<Option<DefaultPlanes>>>,
 }
 
 impl EngineConnection {
     pub async fn new() -> Result<EngineConnection> {
         Ok(EngineConnection {
             batch: Arc::new(Mutex::new(Vec::new())),
+            scene: Default::default(),
+            default_planes: Default::default(),
         })
     }
 }
@@ -27,23 +43,851 @@ impl crate::engine::EngineManager for EngineConnection {
         self.batch.clone()
     }
 
-    async fn default_planes(&self, _source_range: crate::executor::SourceRange) -> Result<DefaultPlanes, KclError> {
-        Ok(DefaultPlanes::default())
+    async fn default_planes(&self, source_range: crate::executor::SourceRange) -> Result<DefaultPlanes, KclError> {
+        {
+            let opt = self.default_planes.read().await.as_ref().cloned();
+            if let Some(planes) = opt {
+                return Ok(planes);
+            }
+        } // drop the read lock
+
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes.clone());
+
+        Ok(new_planes)
     }
 
-    async fn clear_scene_post_hook(&self, _source_range: crate::executor::SourceRange) -> Result<(), KclError> {
+    async fn clear_scene_post_hook(&self, source_range: crate::executor::SourceRange) -> Result<(), KclError> {
+        // Remake the default planes, since they were removed with everything else.
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes);
+
         Ok(())
     }
 
     async fn inner_send_modeling_cmd(
         &self,
         _id: uuid::Uuid,
-        _source_range: crate::executor::SourceRange,
-        _cmd: kittycad::types::WebSocketRequest,
-        _id_to_source_range: std::collections::HashMap<uuid::Uuid, crate::executor::SourceRange>,
+        source_range: crate::executor::SourceRange,
+        cmd: kittycad::types::WebSocketRequest,
+        id_to_source_range: std::collections::HashMap<uuid::Uuid, crate::executor::SourceRange>,
     ) -> Result<OkWebSocketResponseData, KclError> {
-        Ok(OkWebSocketResponseData::Modeling {
-            modeling_response: kittycad::types::OkModelingCmdResponse::Empty {},
+        let requests = match cmd {
+            WebSocketRequest::ModelingCmdReq { cmd, cmd_id } => vec![(cmd, cmd_id)],
+            WebSocketRequest::ModelingCmdBatchReq { requests, .. } => {
+                requests.into_iter().map(|req| (req.cmd, req.cmd_id)).collect()
+            }
+            _ => vec![],
+        };
+
+        // Like the engine, answer a batch with the response to its last command.
+        let mut scene = self.scene.lock().unwrap();
+        let mut modeling_response = OkModelingCmdResponse::Empty {};
+        for (cmd, cmd_id) in requests {
+            modeling_response = scene.apply(cmd_id, cmd).map_err(|message| {
+                KclError::Engine(KclErrorDetails {
+                    message: format!("Modeling command failed: {}", message),
+                    source_ranges: vec![id_to_source_range.get(&cmd_id).copied().unwrap_or(source_range)],
+                })
+            })?;
+        }
+
+        Ok(OkWebSocketResponseData::Modeling { modeling_response })
+    }
+}
+
+/// Everything the modeling commands have made so far.
+#[derive(Debug, Default)]
+struct Scene {
+    planes: Vec<Uuid>,
+    paths: HashMap<Uuid, SketchPath>,
+    /// The path each curve is a segment of.
+    curves: HashMap<Uuid, Uuid>,
+    solids: HashMap<Uuid, Solid>,
+    /// The solid each face is on.
+    faces: HashMap<Uuid, Uuid>,
+    /// Other objects, e.g. imported ones, which we know nothing about except that they exist.
+    objects: Vec<Uuid>,
+    /// The solid we're sketching on a face of, if we're in sketch mode on a face.
+    sketching_on: Option<Uuid>,
+}
+
+/// A path, made by `StartPath` and extended segment by segment.
+#[derive(Debug, Clone, Default)]
+struct SketchPath {
+    pen: Option<Point3D>,
+    start: Option<Point3D>,
+    segments: Vec<Segment>,
+    holes: Vec<Uuid>,
+    /// The solid this path was sketched on a face of, which its extrusion becomes part of.
+    on_solid: Option<Uuid>,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    /// The id of the command which made the segment, which is also the id of its curve.
+    id: Uuid,
+    command: PathCommand,
+    curve_type: CurveType,
+    relative: bool,
+    /// The first control point is where the segment starts, and the last is where it ends.
+    control_points: Vec<Point3D>,
+}
+
+/// A solid, made by extruding or revolving a path. Sketching on one of its faces and extruding adds
+/// to it.
+#[derive(Debug, Clone, Default)]
+struct Solid {
+    extrusions: Vec<Extrusion>,
+}
+
+/// The faces and edges one extrusion (or revolution) of a path made.
+#[derive(Debug, Clone)]
+struct Extrusion {
+    sides: Vec<Side>,
+    bottom: Option<Uuid>,
+    top: Option<Uuid>,
+}
+
+/// The face a segment of the path was swept into, and its edges.
+#[derive(Debug, Clone)]
+struct Side {
+    /// The curve of the segment, which is also the id of the edge along the bottom of the face.
+    curve_id: Uuid,
+    face_id: Uuid,
+    top_edge: Uuid,
+    /// The edge where the face meets the previous side.
+    start_edge: Uuid,
+    /// The edge where the face meets the next side.
+    end_edge: Uuid,
+}
+
+impl Extrusion {
+    fn new(curve_ids: &[Uuid], closed: bool, capped: bool) -> Self {
+        // There is an edge up from every vertex of the path, and a closed path ends where it starts.
+        let num_vertices = if closed { curve_ids.len() } else { curve_ids.len() + 1 };
+        let vertical_edges: Vec<Uuid> = (0..num_vertices).map(|_| Uuid::new_v4()).collect();
+        let sides = curve_ids
+            .iter()
+            .enumerate()
+            .map(|(i, curve_id)| Side {
+                curve_id: *curve_id,
+                face_id: Uuid::new_v4(),
+                top_edge: Uuid::new_v4(),
+                start_edge: vertical_edges[i],
+                end_edge: vertical_edges[(i + 1) % num_vertices],
+            })
+            .collect();
+
+        Extrusion {
+            sides,
+            bottom: capped.then(Uuid::new_v4),
+            top: capped.then(Uuid::new_v4),
+        }
+    }
+
+    fn face_ids(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.sides
+            .iter()
+            .map(|side| side.face_id)
+            .chain(self.bottom)
+            .chain(self.top)
+    }
+
+    /// The side whose bottom or top edge this is.
+    fn side_with_edge(&self, edge_id: Uuid) -> Option<(usize, &Side)> {
+        self.sides
+            .iter()
+            .enumerate()
+            .find(|(_, side)| side.curve_id == edge_id || side.top_edge == edge_id)
+    }
+}
+
+impl Solid {
+    fn side_with_edge(&self, edge_id: Uuid) -> Option<(&Extrusion, usize, &Side)> {
+        self.extrusions.iter().find_map(|extrusion| {
+            extrusion
+                .side_with_edge(edge_id)
+                .map(|(index, side)| (extrusion, index, side))
         })
     }
+
+    fn has_edge(&self, edge_id: Uuid) -> bool {
+        self.extrusions
+            .iter()
+            .flat_map(|extrusion| &extrusion.sides)
+            .any(|side| [side.curve_id, side.top_edge, side.start_edge, side.end_edge].contains(&edge_id))
+    }
+}
+
+impl Scene {
+    /// Carry out a modeling command, or answer it if it's a question.
+    /// Commands about things we don't track succeed without doing anything.
+    fn apply(&mut self, id: Uuid, cmd: ModelingCmd) -> Result<OkModelingCmdResponse, String> {
+        match cmd {
+            ModelingCmd::SceneClearAll {} => *self = Scene::default(),
+            ModelingCmd::MakePlane { .. } => self.planes.push(id),
+            ModelingCmd::EnableSketchMode { entity_id, .. } => {
+                self.sketching_on = self.faces.get(&entity_id).copied();
+            }
+            ModelingCmd::SketchModeDisable {} => self.sketching_on = None,
+            ModelingCmd::StartPath {} => {
+                self.paths.insert(
+                    id,
+                    SketchPath {
+                        on_solid: self.sketching_on,
+                        ..Default::default()
+                    },
+                );
+            }
+            ModelingCmd::MovePathPen { path, to } => {
+                let path = self.path_mut(path)?;
+                if path.start.is_none() {
+                    path.start = Some(to);
+                }
+                path.pen = Some(to);
+            }
+            ModelingCmd::ExtendPath { path: path_id, segment } => {
+                let path = self.path_mut(path_id)?;
+                let segment = segment_from_pen(id, path, segment);
+                path.pen = segment.control_points.last().copied();
+                path.segments.push(segment);
+                self.curves.insert(id, path_id);
+            }
+            ModelingCmd::ClosePath { path_id } => {
+                let path = self.path_mut(path_id)?;
+                let (Some(pen), Some(start)) = (path.pen, path.start) else {
+                    return Err(format!("The path `{}` has no segments to close", path_id));
+                };
+                path.segments.push(Segment {
+                    id,
+                    command: PathCommand::LineTo,
+                    curve_type: CurveType::Line,
+                    relative: false,
+                    control_points: vec![pen, start],
+                });
+                path.pen = Some(start);
+                self.curves.insert(id, path_id);
+            }
+            ModelingCmd::Solid2DAddHole { object_id, hole_id } => {
+                self.path(hole_id)?;
+                self.path_mut(object_id)?.holes.push(hole_id);
+            }
+            ModelingCmd::Extrude { target, cap, .. } => self.sweep(target, cap)?,
+            ModelingCmd::Revolve { target, angle, .. } | ModelingCmd::RevolveAboutEdge { target, angle, .. } => {
+                // Only a partial revolution leaves the ends of the solid open to cap.
+                self.sweep(target, radians(&angle).abs() < std::f64::consts::TAU)?
+            }
+            ModelingCmd::Solid3DFilletEdge { edge_id, object_id, .. } => self.check_has_edge(object_id, edge_id)?,
+            ModelingCmd::ImportFiles { .. } => {
+                let object_id = Uuid::new_v4();
+                self.objects.push(object_id);
+                return Ok(OkModelingCmdResponse::ImportFiles {
+                    data: kittycad::types::ImportFiles { object_id },
+                });
+            }
+            ModelingCmd::EntityLinearPattern {
+                entity_id,
+                num_repetitions,
+                ..
+            } => {
+                return Ok(OkModelingCmdResponse::EntityLinearPattern {
+                    data: kittycad::types::EntityLinearPattern {
+                        entity_ids: self.copy_entity(entity_id, num_repetitions)?,
+                    },
+                });
+            }
+            ModelingCmd::EntityCircularPattern {
+                entity_id,
+                num_repetitions,
+                ..
+            } => {
+                return Ok(OkModelingCmdResponse::EntityCircularPattern {
+                    data: kittycad::types::EntityCircularPattern {
+                        entity_ids: self.copy_entity(entity_id, num_repetitions)?,
+                    },
+                });
+            }
+            ModelingCmd::PathGetInfo { path_id } => {
+                let path = self.path(path_id)?;
+                // Moving the pen to the start doesn't make a segment, so it has no command id.
+                let move_to = path.start.as_ref().map(|_| PathSegmentInfo {
+                    command: PathCommand::MoveTo,
+                    command_id: None,
+                    relative: false,
+                });
+                let segments = move_to
+                    .into_iter()
+                    .chain(path.segments.iter().map(|segment| PathSegmentInfo {
+                        command: segment.command.clone(),
+                        command_id: Some(segment.id),
+                        relative: segment.relative,
+                    }))
+                    .collect();
+                return Ok(OkModelingCmdResponse::PathGetInfo {
+                    data: kittycad::types::PathGetInfo { segments },
+                });
+            }
+            ModelingCmd::CurveGetControlPoints { curve_id } => {
+                return Ok(OkModelingCmdResponse::CurveGetControlPoints {
+                    data: kittycad::types::CurveGetControlPoints {
+                        control_points: self.curve(curve_id)?.control_points.clone(),
+                    },
+                });
+            }
+            ModelingCmd::CurveGetEndPoints { curve_id } => {
+                let control_points = &self.curve(curve_id)?.control_points;
+                return Ok(OkModelingCmdResponse::CurveGetEndPoints {
+                    data: kittycad::types::CurveGetEndPoints {
+                        start: control_points[0],
+                        end: control_points[control_points.len() - 1],
+                    },
+                });
+            }
+            ModelingCmd::CurveGetType { curve_id } => {
+                return Ok(OkModelingCmdResponse::CurveGetType {
+                    data: kittycad::types::CurveGetType {
+                        curve_type: self.curve(curve_id)?.curve_type.clone(),
+                    },
+                });
+            }
+            ModelingCmd::GetEntityType { entity_id } => {
+                return Ok(OkModelingCmdResponse::GetEntityType {
+                    data: kittycad::types::GetEntityType {
+                        entity_type: self.entity_type(entity_id)?,
+                    },
+                });
+            }
+            ModelingCmd::Solid3DGetExtrusionFaceInfo { object_id, edge_id } => {
+                let (extrusion, _, _) = self.side_with_edge(object_id, edge_id)?;
+                let sides = extrusion.sides.iter().map(|side| ExtrusionFaceInfo {
+                    cap: ExtrusionFaceCapType::None,
+                    curve_id: Some(side.curve_id),
+                    face_id: Some(side.face_id),
+                });
+                let caps = [
+                    (ExtrusionFaceCapType::Bottom, extrusion.bottom),
+                    (ExtrusionFaceCapType::Top, extrusion.top),
+                ]
+                .into_iter()
+                .filter_map(|(cap, face_id)| {
+                    face_id.map(|face_id| ExtrusionFaceInfo {
+                        cap,
+                        curve_id: None,
+                        face_id: Some(face_id),
+                    })
+                });
+                return Ok(OkModelingCmdResponse::Solid3DGetExtrusionFaceInfo {
+                    data: kittycad::types::Solid3DGetExtrusionFaceInfo {
+                        faces: sides.chain(caps).collect(),
+                    },
+                });
+            }
+            ModelingCmd::Solid3DGetOppositeEdge {
+                edge_id,
+                object_id,
+                face_id,
+            } => {
+                let (_, _, side) = self.side_with_edge(object_id, edge_id)?;
+                if side.face_id != face_id {
+                    return Err(format!("The edge `{}` is not on the face `{}`", edge_id, face_id));
+                }
+                let edge = if side.curve_id == edge_id {
+                    side.top_edge
+                } else {
+                    side.curve_id
+                };
+                return Ok(OkModelingCmdResponse::Solid3DGetOppositeEdge {
+                    data: kittycad::types::Solid3DGetOppositeEdge { edge },
+                });
+            }
+            ModelingCmd::Solid3DGetNextAdjacentEdge {
+                edge_id,
+                object_id,
+                face_id,
+            } => {
+                let edge = self.adjacent_edge(object_id, edge_id, face_id, true)?;
+                return Ok(OkModelingCmdResponse::Solid3DGetNextAdjacentEdge {
+                    data: kittycad::types::Solid3DGetNextAdjacentEdge { edge },
+                });
+            }
+            ModelingCmd::Solid3DGetPrevAdjacentEdge {
+                edge_id,
+                object_id,
+                face_id,
+            } => {
+                let edge = self.adjacent_edge(object_id, edge_id, face_id, false)?;
+                return Ok(OkModelingCmdResponse::Solid3DGetPrevAdjacentEdge {
+                    data: kittycad::types::Solid3DGetPrevAdjacentEdge { edge },
+                });
+            }
+            ModelingCmd::Solid3DGetAllOppositeEdges { edge_id, object_id, .. } => {
+                let (_, _, side) = self.side_with_edge(object_id, edge_id)?;
+                let edge = if side.curve_id == edge_id {
+                    side.top_edge
+                } else {
+                    side.curve_id
+                };
+                return Ok(OkModelingCmdResponse::Solid3DGetAllOppositeEdges {
+                    data: kittycad::types::Solid3DGetAllOppositeEdges { edges: vec![edge] },
+                });
+            }
+            ModelingCmd::Solid3DGetAllEdgeFaces { edge_id, object_id } => {
+                let (extrusion, _, side) = self.side_with_edge(object_id, edge_id)?;
+                let cap = if side.curve_id == edge_id {
+                    extrusion.bottom
+                } else {
+                    extrusion.top
+                };
+                return Ok(OkModelingCmdResponse::Solid3DGetAllEdgeFaces {
+                    data: kittycad::types::Solid3DGetAllEdgeFaces {
+                        faces: std::iter::once(side.face_id).chain(cap).collect(),
+                    },
+                });
+            }
+            _ => {}
+        }
+
+        Ok(OkModelingCmdResponse::Empty {})
+    }
+
+    fn path(&self, path_id: Uuid) -> Result<&SketchPath, String> {
+        self.paths
+            .get(&path_id)
+            .ok_or_else(|| format!("There is no path with the id `{}`", path_id))
+    }
+
+    fn path_mut(&mut self, path_id: Uuid) -> Result<&mut SketchPath, String> {
+        self.paths
+            .get_mut(&path_id)
+            .ok_or_else(|| format!("There is no path with the id `{}`", path_id))
+    }
+
+    fn curve(&self, curve_id: Uuid) -> Result<&Segment, String> {
+        self.curves
+            .get(&curve_id)
+            .and_then(|path_id| self.paths.get(path_id))
+            .and_then(|path| path.segments.iter().find(|segment| segment.id == curve_id))
+            .ok_or_else(|| format!("There is no curve with the id `{}`", curve_id))
+    }
+
+    fn solid(&self, object_id: Uuid) -> Result<&Solid, String> {
+        self.solids
+            .get(&object_id)
+            .ok_or_else(|| format!("There is no solid with the id `{}`", object_id))
+    }
+
+    fn check_has_edge(&self, object_id: Uuid, edge_id: Uuid) -> Result<(), String> {
+        if self.solid(object_id)?.has_edge(edge_id) {
+            Ok(())
+        } else {
+            Err(format!(
+                "The edge `{}` is not part of the solid `{}`",
+                edge_id, object_id
+            ))
+        }
+    }
+
+    fn side_with_edge(&self, object_id: Uuid, edge_id: Uuid) -> Result<(&Extrusion, usize, &Side), String> {
+        self.solid(object_id)?
+            .side_with_edge(edge_id)
+            .ok_or_else(|| format!("The edge `{}` is not part of the solid `{}`", edge_id, object_id))
+    }
+
+    /// The edge after or before the given one, going around the given face.
+    fn adjacent_edge(&self, object_id: Uuid, edge_id: Uuid, face_id: Uuid, next: bool) -> Result<Option<Uuid>, String> {
+        let (extrusion, index, side) = self.side_with_edge(object_id, edge_id)?;
+        if side.face_id == face_id {
+            // Around a side face, the edges next to its top and bottom are the ones up its ends.
+            return Ok(Some(if next { side.end_edge } else { side.start_edge }));
+        }
+
+        let is_bottom = side.curve_id == edge_id;
+        let cap = if is_bottom { extrusion.bottom } else { extrusion.top };
+        if cap != Some(face_id) {
+            return Err(format!("The edge `{}` is not on the face `{}`", edge_id, face_id));
+        }
+        // Around a cap, the edges are the ends of the sides, in the order of the path.
+        let num_sides = extrusion.sides.len();
+        let adjacent = if next {
+            &extrusion.sides[(index + 1) % num_sides]
+        } else {
+            &extrusion.sides[(index + num_sides - 1) % num_sides]
+        };
+        Ok(Some(if is_bottom {
+            adjacent.curve_id
+        } else {
+            adjacent.top_edge
+        }))
+    }
+
+    /// Sweep a path, and its holes, into a solid, or into the solid whose face it was sketched on.
+    fn sweep(&mut self, target: Uuid, capped: bool) -> Result<(), String> {
+        let path = self.path(target)?;
+        if path.segments.is_empty() {
+            return Err(format!("The path `{}` has no segments to sweep", target));
+        }
+
+        let mut extrusions = Vec::new();
+        for path_id in std::iter::once(&target).chain(&path.holes) {
+            let path = self.path(*path_id)?;
+            let curve_ids: Vec<Uuid> = path.segments.iter().map(|segment| segment.id).collect();
+            let closed = path.start.is_some() && path.start == path.pen;
+            // Only the outside of the profile gets caps, they cover the holes too.
+            extrusions.push(Extrusion::new(&curve_ids, closed, capped && extrusions.is_empty()));
+        }
+
+        let solid_id = path.on_solid.unwrap_or(target);
+        for extrusion in &extrusions {
+            for face_id in extrusion.face_ids() {
+                self.faces.insert(face_id, solid_id);
+            }
+        }
+        self.solids.entry(solid_id).or_default().extrusions.extend(extrusions);
+        Ok(())
+    }
+
+    /// Copy a path or solid for a pattern. The copies share the original's faces and edges, so
+    /// asking about them gives the same answers as asking about the original.
+    fn copy_entity(&mut self, entity_id: Uuid, num_copies: u32) -> Result<Vec<Uuid>, String> {
+        let copy_ids: Vec<Uuid> = (0..num_copies).map(|_| Uuid::new_v4()).collect();
+        if let Some(solid) = self.solids.get(&entity_id).cloned() {
+            for id in &copy_ids {
+                self.solids.insert(*id, solid.clone());
+            }
+        } else if let Some(path) = self.paths.get(&entity_id).cloned() {
+            for id in &copy_ids {
+                self.paths.insert(*id, path.clone());
+            }
+        } else {
+            return Err(format!("There is no path or solid with the id `{}` to copy", entity_id));
+        }
+        Ok(copy_ids)
+    }
+
+    fn entity_type(&self, entity_id: Uuid) -> Result<EntityType, String> {
+        let is_edge = || self.solids.values().any(|solid| solid.has_edge(entity_id));
+        let entity_type = if self.planes.contains(&entity_id) {
+            EntityType::Plane
+        } else if self.solids.contains_key(&entity_id) {
+            EntityType::Solid3D
+        } else if self.paths.contains_key(&entity_id) {
+            EntityType::Path
+        } else if self.curves.contains_key(&entity_id) {
+            EntityType::Curve
+        } else if self.faces.contains_key(&entity_id) {
+            EntityType::Face
+        } else if is_edge() {
+            EntityType::Edge
+        } else if self.objects.contains(&entity_id) {
+            EntityType::Object
+        } else {
+            return Err(format!("There is no entity with the id `{}`", entity_id));
+        };
+        Ok(entity_type)
+    }
+}
+
+/// The segment a path segment command adds, starting from where the pen is.
+fn segment_from_pen(id: Uuid, path: &SketchPath, segment: PathSegment) -> Segment {
+    let pen = path.pen.unwrap_or(Point3D { x: 0.0, y: 0.0, z: 0.0 });
+    let offset = |relative: bool, point: Point3D| {
+        if relative {
+            add(&pen, &point)
+        } else {
+            point
+        }
+    };
+
+    let (command, curve_type, relative, control_points) = match segment {
+        PathSegment::Line { end, relative } => (
+            PathCommand::LineTo,
+            CurveType::Line,
+            relative,
+            vec![pen, offset(relative, end)],
+        ),
+        PathSegment::Bezier {
+            control_1,
+            control_2,
+            end,
+            relative,
+        } => (
+            PathCommand::BezCurveTo,
+            CurveType::Nurbs,
+            relative,
+            vec![
+                pen,
+                offset(relative, control_1),
+                offset(relative, control_2),
+                offset(relative, end),
+            ],
+        ),
+        PathSegment::Arc {
+            center,
+            radius,
+            end,
+            relative,
+            ..
+        } => {
+            let center = offset(
+                relative,
+                Point3D {
+                    x: center.x,
+                    y: center.y,
+                    z: pen.z,
+                },
+            );
+            let end = radians(&end);
+            let end = Point3D {
+                x: center.x + radius * end.cos(),
+                y: center.y + radius * end.sin(),
+                z: pen.z,
+            };
+            (PathCommand::AddArc, CurveType::Arc, relative, vec![pen, end])
+        }
+        PathSegment::TangentialArc { radius, offset } => {
+            // Turn about a center to the side of the direction the path was going in, to the left
+            // for a positive (counter-clockwise) offset.
+            let [dx, dy] = tangent(path);
+            let offset = radians(&offset);
+            let side = if offset >= 0.0 { 1.0 } else { -1.0 };
+            let center = [pen.x - side * dy * radius, pen.y + side * dx * radius];
+            let (sin, cos) = offset.sin_cos();
+            let (x, y) = (pen.x - center[0], pen.y - center[1]);
+            let end = Point3D {
+                x: center[0] + x * cos - y * sin,
+                y: center[1] + x * sin + y * cos,
+                z: pen.z,
+            };
+            (PathCommand::AddArc, CurveType::Arc, false, vec![pen, end])
+        }
+        PathSegment::TangentialArcTo { to, .. } => {
+            (PathCommand::AddArc, CurveType::Arc, true, vec![pen, offset(true, to)])
+        }
+    };
+
+    Segment {
+        id,
+        command,
+        curve_type,
+        relative,
+        control_points,
+    }
+}
+
+/// The direction, as a unit vector, the path was going in at its end.
+fn tangent(path: &SketchPath) -> [f64; 2] {
+    let Some([.., from, to]) = path.segments.last().map(|segment| segment.control_points.as_slice()) else {
+        return [1.0, 0.0];
+    };
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        [1.0, 0.0]
+    } else {
+        [dx / length, dy / length]
+    }
+}
+
+fn add(a: &Point3D, b: &Point3D) -> Point3D {
+    Point3D {
+        x: a.x + b.x,
+        y: a.y + b.y,
+        z: a.z + b.z,
+    }
+}
+
+fn radians(angle: &kittycad::types::Angle) -> f64 {
+    match angle.unit {
+        UnitAngle::Degrees => angle.value.to_radians(),
+        UnitAngle::Radians => angle.value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        engine::EngineManager,
+        executor::{ExecutorContext, MemoryItem, PlaneType, ProgramMemory, SourceRange},
+    };
+
+    async fn execute(code: &str) -> Result<(ExecutorContext, ProgramMemory), KclError> {
+        let tokens = crate::token::lexer(code)?;
+        let program = crate::parser::Parser::new(tokens).ast()?;
+        let ctx = ExecutorContext {
+            engine: Arc::new(Box::new(EngineConnection::new().await.unwrap())),
+            fs: Arc::new(crate::fs::FileManager::new()),
+            stdlib: Arc::new(crate::std::StdLib::new()),
+            settings: Default::default(),
+            is_mock: false,
+            import_stack: Vec::new(),
+        };
+        let memory = ctx.run(program, None).await?;
+        Ok((ctx, memory))
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_default_planes_are_distinct() {
+        let engine = EngineConnection::new().await.unwrap();
+        let planes = engine.default_planes(SourceRange::default()).await.unwrap();
+        let mut ids = vec![
+            planes.xy,
+            planes.xz,
+            planes.yz,
+            planes.neg_xy,
+            planes.neg_xz,
+            planes.neg_yz,
+        ];
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 6);
+        assert!(!ids.contains(&Uuid::nil()));
+
+        // Asking again gives the same planes.
+        assert_eq!(engine.default_planes(SourceRange::default()).await.unwrap(), planes);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_extrude_has_faces() {
+        let code = r#"const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "left")
+  |> line([10, 0], %)
+  |> line([0, -10], %)
+  |> close(%)
+  |> extrude(10, %)
+"#;
+        let (_, memory) = execute(code).await.unwrap();
+        let MemoryItem::ExtrudeGroup(extrude_group) = memory.get("part001", SourceRange::default()).unwrap() else {
+            panic!("expected an extrude group");
+        };
+        assert!(extrude_group.start_cap_id.is_some());
+        assert!(extrude_group.end_cap_id.is_some());
+        // A face for each of the four segments.
+        assert_eq!(extrude_group.value.len(), 4);
+        assert_eq!(extrude_group.value[0].get_name(), "left");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fillet_with_edge_queries() {
+        let code = r#"const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "thing")
+  |> line([10, 0], %, "thing1")
+  |> line([0, -10], %, "thing2")
+  |> close(%, "thing3")
+  |> extrude(10, %)
+  |> fillet({
+       radius: 2,
+       tags: [
+         "thing",
+         getOppositeEdge("thing", %),
+         getNextAdjacentEdge("thing1", %),
+         getPreviousAdjacentEdge("thing3", %)
+       ]
+     }, %)
+"#;
+        execute(code).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_opposite_edge_is_consistent() {
+        let code = r#"const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "thing")
+  |> line([10, 0], %)
+  |> close(%)
+  |> extrude(10, %)
+const opposite = getOppositeEdge("thing", part001)
+const again = getOppositeEdge("thing", part001)
+"#;
+        let (_, memory) = execute(code).await.unwrap();
+        let opposite = memory
+            .get("opposite", SourceRange::default())
+            .unwrap()
+            .get_json_value()
+            .unwrap();
+        let again = memory
+            .get("again", SourceRange::default())
+            .unwrap()
+            .get_json_value()
+            .unwrap();
+        assert_eq!(opposite, again);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_extrude_sketch_on_face() {
+        let code = r#"const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "here")
+  |> line([10, 0], %)
+  |> line([0, -10], %)
+  |> close(%)
+  |> extrude(10, %)
+
+const part002 = startSketchOn(part001, "here")
+  |> startProfileAt([2, 2], %)
+  |> line([0, 5], %)
+  |> line([5, 0], %)
+  |> close(%)
+  |> extrude(5, %)
+"#;
+        let (_, memory) = execute(code).await.unwrap();
+        let MemoryItem::ExtrudeGroup(extrude_group) = memory.get("part002", SourceRange::default()).unwrap() else {
+            panic!("expected an extrude group");
+        };
+        assert_eq!(extrude_group.value.len(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_modify_ast_for_sketch() {
+        let code = r#"const part001 = startSketchOn("XY")
+  |> startProfileAt([8.41, 5.78], %)
+  |> line([7.37, -11.0], %)
+  |> line([-8.69, -3.75], %)
+  |> line([-5.0, 4.25], %)
+"#;
+        let (ctx, memory) = execute(code).await.unwrap();
+        let MemoryItem::SketchGroup(sketch_group) = memory.get("part001", SourceRange::default()).unwrap() else {
+            panic!("expected a sketch group");
+        };
+
+        let mut program = crate::parser::Parser::new(crate::token::lexer(code).unwrap())
+            .ast()
+            .unwrap();
+        let new_code = crate::ast::modify::modify_ast_for_sketch(
+            &ctx.engine,
+            &mut program,
+            "part001",
+            PlaneType::XY,
+            sketch_group.id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(new_code, code);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_query_unknown_path_fails() {
+        let engine = EngineConnection::new().await.unwrap();
+        let path_id = Uuid::new_v4();
+        let err = engine
+            .send_modeling_cmd(
+                Uuid::new_v4(),
+                SourceRange([3, 4]),
+                ModelingCmd::PathGetInfo { path_id },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            KclError::Engine(KclErrorDetails {
+                message: format!("Modeling command failed: There is no path with the id `{}`", path_id),
+                source_ranges: vec![SourceRange([3, 4])],
+            })
+        );
+    }
 }
@@ -0,0 +1,124 @@
+//! Records a session with the engine, so it can be replayed without a connection to the Zoo API.
+//! It wraps another engine, and writes every request sent to it and its response to a file, one
+//! JSON line for each.
+
+use std::{
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use kittycad::types::{OkWebSocketResponseData, WebSocketRequest};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::{
+    engine::{ConnectionHealth, EngineManager},
+    errors::{KclError, KclErrorDetails},
+    executor::{DefaultPlanes, SourceRange},
+};
+
+/// A request sent to the engine, and how it responded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Exchange {
+    pub request: WebSocketRequest,
+    pub source_range: SourceRange,
+    pub response: Result<OkWebSocketResponseData, KclError>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineConnection {
+    engine: Arc<Box<dyn EngineManager>>,
+    file: Arc<Mutex<std::fs::File>>,
+    batch: Arc<Mutex<Vec<(WebSocketRequest, SourceRange)>>>,
+    default_planes: Arc<RwLock<Option<DefaultPlanes>>>,
+}
+
+impl EngineConnection {
+    /// Send commands to `engine`, recording them and its responses to the file at `path`, which is
+    /// replaced if it exists.
+    pub fn new(engine: Arc<Box<dyn EngineManager>>, path: &Path) -> Result<EngineConnection> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+
+        Ok(EngineConnection {
+            engine,
+            file: Arc::new(Mutex::new(file)),
+            batch: Arc::new(Mutex::new(Vec::new())),
+            default_planes: Default::default(),
+        })
+    }
+
+    fn record(&self, exchange: &Exchange) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        serde_json::to_writer(&mut *file, exchange)?;
+        file.write_all(b"\n")?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl EngineManager for EngineConnection {
+    fn batch(&self) -> Arc<Mutex<Vec<(WebSocketRequest, SourceRange)>>> {
+        self.batch.clone()
+    }
+
+    fn connection_health(&self) -> ConnectionHealth {
+        self.engine.connection_health()
+    }
+
+    async fn default_planes(&self, source_range: SourceRange) -> Result<DefaultPlanes, KclError> {
+        {
+            let opt = self.default_planes.read().await.as_ref().cloned();
+            if let Some(planes) = opt {
+                return Ok(planes);
+            }
+        } // drop the read lock
+
+        // Make the planes through this connection rather than the wrapped one, so they're recorded.
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes.clone());
+
+        Ok(new_planes)
+    }
+
+    async fn clear_scene_post_hook(&self, source_range: SourceRange) -> Result<(), KclError> {
+        // Remake the default planes, since they were removed with everything else.
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes);
+
+        Ok(())
+    }
+
+    async fn inner_send_modeling_cmd(
+        &self,
+        id: uuid::Uuid,
+        source_range: SourceRange,
+        cmd: WebSocketRequest,
+        id_to_source_range: std::collections::HashMap<uuid::Uuid, SourceRange>,
+    ) -> Result<OkWebSocketResponseData, KclError> {
+        let response = self
+            .engine
+            .inner_send_modeling_cmd(id, source_range, cmd.clone(), id_to_source_range)
+            .await;
+
+        let exchange = Exchange {
+            request: cmd,
+            source_range,
+            response,
+        };
+        self.record(&exchange).map_err(|e| {
+            KclError::Internal(KclErrorDetails {
+                message: format!("Failed to record modeling command: {}", e),
+                source_ranges: vec![source_range],
+            })
+        })?;
+
+        exchange.response
+    }
+}
@@ -0,0 +1,298 @@
+//! Replays a session recorded with [`crate::engine::conn_record`], without a connection to the Zoo
+//! API. Each request must match the next recorded one, which gets the recorded response.
+//!
+//! The ids in the requests are new every time, so the recorded ids are matched to the ones sent in
+//! their place, and the ids in the recorded responses are changed to match. Only the commands are
+//! matched, not where in the program they were sent from, so editing a program's formatting doesn't
+//! invalidate its recording. The commands in a batch can be in any order.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use kittycad::types::{OkWebSocketResponseData, WebSocketRequest};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    engine::{conn_record::Exchange, EngineManager},
+    errors::{KclError, KclErrorDetails},
+    executor::{DefaultPlanes, SourceRange},
+};
+
+#[derive(Debug, Clone)]
+pub struct EngineConnection {
+    exchanges: Arc<Vec<Exchange>>,
+    replay: Arc<Mutex<Replay>>,
+    batch: Arc<Mutex<Vec<(WebSocketRequest, SourceRange)>>>,
+    default_planes: Arc<RwLock<Option<DefaultPlanes>>>,
+}
+
+/// How far through the recording we are.
+#[derive(Debug, Default)]
+struct Replay {
+    /// The index of the next exchange.
+    next: usize,
+    /// The ids sent in place of each recorded id.
+    ids: HashMap<Uuid, Uuid>,
+}
+
+impl EngineConnection {
+    /// Replay the session recorded to the file at `path`.
+    pub fn new(path: &Path) -> Result<EngineConnection> {
+        let recording =
+            std::fs::read_to_string(path).with_context(|| format!("reading recording {}", path.display()))?;
+        let exchanges = recording
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).with_context(|| format!("parsing line {} of {}", index + 1, path.display()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self::from_exchanges(exchanges))
+    }
+
+    pub fn from_exchanges(exchanges: Vec<Exchange>) -> EngineConnection {
+        EngineConnection {
+            exchanges: Arc::new(exchanges),
+            replay: Default::default(),
+            batch: Arc::new(Mutex::new(Vec::new())),
+            default_planes: Default::default(),
+        }
+    }
+
+    /// Check every recorded request was sent, so a program which now stops early doesn't pass.
+    pub fn finish(&self) -> Result<(), KclError> {
+        let next = self.replay.lock().unwrap().next;
+        match self.exchanges.get(next) {
+            Some(exchange) => Err(KclError::Engine(KclErrorDetails {
+                message: format!(
+                    "{} of {} recorded modeling commands were sent; the next is {}",
+                    next,
+                    self.exchanges.len(),
+                    serde_json::to_string(&exchange.request).unwrap_or_default()
+                ),
+                source_ranges: vec![exchange.source_range],
+            })),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EngineManager for EngineConnection {
+    fn batch(&self) -> Arc<Mutex<Vec<(WebSocketRequest, SourceRange)>>> {
+        self.batch.clone()
+    }
+
+    async fn default_planes(&self, source_range: SourceRange) -> Result<DefaultPlanes, KclError> {
+        {
+            let opt = self.default_planes.read().await.as_ref().cloned();
+            if let Some(planes) = opt {
+                return Ok(planes);
+            }
+        } // drop the read lock
+
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes.clone());
+
+        Ok(new_planes)
+    }
+
+    async fn clear_scene_post_hook(&self, source_range: SourceRange) -> Result<(), KclError> {
+        // Remake the default planes, since they were removed with everything else.
+        let new_planes = self.new_default_planes(source_range).await?;
+        *self.default_planes.write().await = Some(new_planes);
+
+        Ok(())
+    }
+
+    async fn inner_send_modeling_cmd(
+        &self,
+        _id: uuid::Uuid,
+        source_range: SourceRange,
+        cmd: WebSocketRequest,
+        _id_to_source_range: std::collections::HashMap<uuid::Uuid, SourceRange>,
+    ) -> Result<OkWebSocketResponseData, KclError> {
+        let mismatch = |message: String| {
+            KclError::Engine(KclErrorDetails {
+                message,
+                source_ranges: vec![source_range],
+            })
+        };
+        let sent = serde_json::to_value(&cmd).map_err(|e| mismatch(format!("Failed to serialize request: {}", e)))?;
+
+        let mut replay = self.replay.lock().unwrap();
+        let index = replay.next;
+        let Some(exchange) = self.exchanges.get(index) else {
+            return Err(mismatch(format!(
+                "Modeling command {} was sent, but only {} were recorded: {}",
+                index + 1,
+                self.exchanges.len(),
+                sent
+            )));
+        };
+        let recorded = serde_json::to_value(&exchange.request)
+            .map_err(|e| mismatch(format!("Failed to serialize recorded request: {}", e)))?;
+        if !matches(&recorded, &sent, &mut replay.ids) {
+            return Err(mismatch(format!(
+                "Modeling command {} doesn't match the recording.\nRecorded at {:?}: {}\nSent at {:?}: {}",
+                index + 1,
+                exchange.source_range,
+                recorded,
+                source_range,
+                sent
+            )));
+        }
+        replay.next += 1;
+
+        match &exchange.response {
+            Ok(response) => {
+                let mut response = serde_json::to_value(response)
+                    .map_err(|e| mismatch(format!("Failed to serialize recorded response: {}", e)))?;
+                replace_ids(&mut response, &replay.ids);
+                serde_json::from_value(response)
+                    .map_err(|e| mismatch(format!("Failed to deserialize recorded response: {}", e)))
+            }
+            Err(err) => Err(err.clone()),
+        }
+    }
+}
+
+/// If the sent request is the same as the recorded one, apart from its ids, which are matched to
+/// the recorded ones they replace.
+fn matches(recorded: &Value, sent: &Value, ids: &mut HashMap<Uuid, Uuid>) -> bool {
+    if let (Some(recorded), Some(sent)) = (batch_requests(recorded), batch_requests(sent)) {
+        return recorded.len() == sent.len() && matches_in_any_order(recorded, sent, ids);
+    }
+    match (recorded, sent) {
+        (Value::String(recorded), Value::String(sent)) => match (Uuid::parse_str(recorded), Uuid::parse_str(sent)) {
+            (Ok(recorded), Ok(sent)) => *ids.entry(recorded).or_insert(sent) == sent,
+            _ => recorded == sent,
+        },
+        (Value::Array(recorded), Value::Array(sent)) => {
+            recorded.len() == sent.len() && recorded.iter().zip(sent).all(|(r, s)| matches(r, s, ids))
+        }
+        (Value::Object(recorded), Value::Object(sent)) => {
+            recorded.len() == sent.len()
+                && recorded
+                    .iter()
+                    .all(|(key, r)| sent.get(key).is_some_and(|s| matches(r, s, ids)))
+        }
+        _ => recorded == sent,
+    }
+}
+
+/// The requests in a batch request, whose order can change from run to run, e.g. the default
+/// planes are made in whatever order a hash map has them in.
+fn batch_requests(request: &Value) -> Option<&Vec<Value>> {
+    if request.get("type")?.as_str()? != "modeling_cmd_batch_req" {
+        return None;
+    }
+    request.get("requests")?.as_array()
+}
+
+/// If every recorded request matches a different sent one, matching ids as they're matched.
+fn matches_in_any_order(recorded: &[Value], sent: &[Value], ids: &mut HashMap<Uuid, Uuid>) -> bool {
+    let mut unmatched: Vec<&Value> = sent.iter().collect();
+    recorded.iter().all(|recorded| {
+        let found = unmatched.iter().position(|sent| {
+            // Only keep the ids matched if the whole request matches.
+            let mut attempt = ids.clone();
+            let found = matches(recorded, sent, &mut attempt);
+            if found {
+                *ids = attempt;
+            }
+            found
+        });
+        found.map(|index| unmatched.remove(index)).is_some()
+    })
+}
+
+/// Change the recorded ids in a recorded response to the ones sent in their place. Ids the engine
+/// made stay as they were, since they're sent back as they were recorded too.
+fn replace_ids(value: &mut Value, ids: &HashMap<Uuid, Uuid>) {
+    match value {
+        Value::String(s) => {
+            if let Some(id) = Uuid::parse_str(s).ok().and_then(|id| ids.get(&id)) {
+                *s = id.to_string();
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| replace_ids(value, ids)),
+        Value::Object(values) => values.values_mut().for_each(|value| replace_ids(value, ids)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::executor::{ExecutorContext, MemoryItem};
+
+    async fn execute(code: &str, engine: Arc<Box<dyn EngineManager>>) -> Result<MemoryItem, KclError> {
+        let tokens = crate::token::lexer(code)?;
+        let program = crate::parser::Parser::new(tokens).ast()?;
+        let ctx = ExecutorContext::new_with_engine(engine, Default::default())
+            .await
+            .unwrap();
+        let memory = ctx.run(program, None).await?;
+        Ok(memory.get("part", SourceRange::default())?.clone())
+    }
+
+    const CODE: &str = r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, "side")
+  |> line([10, 0], %)
+  |> close(%)
+  |> extrude(5, %)
+  |> fillet({ radius: 1, tags: [getOppositeEdge("side", %)] }, %)
+"#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_replay_recorded_session() {
+        let path = std::env::temp_dir().join(format!("kcl_recording_{}.jsonl", Uuid::new_v4()));
+        let engine = crate::engine::conn_mock::EngineConnection::new().await.unwrap();
+        let recorder = crate::engine::conn_record::EngineConnection::new(Arc::new(Box::new(engine)), &path).unwrap();
+        let recorded = execute(CODE, Arc::new(Box::new(recorder))).await.unwrap();
+
+        // The replay makes the same geometry, with the ids it sent.
+        let replayer = EngineConnection::new(&path).unwrap();
+        let replayed = execute(CODE, Arc::new(Box::new(replayer.clone()))).await.unwrap();
+        replayer.finish().unwrap();
+        let (MemoryItem::ExtrudeGroup(recorded), MemoryItem::ExtrudeGroup(replayed)) = (recorded, replayed) else {
+            panic!("Expected extrude groups");
+        };
+        assert_ne!(recorded.id, replayed.id);
+        assert_eq!(recorded.value.len(), replayed.value.len());
+
+        // Reformatting the program moves where the commands are sent from, but they're the same.
+        let replayer = EngineConnection::new(&path).unwrap();
+        execute(&format!("\n\n{}", CODE), Arc::new(Box::new(replayer.clone())))
+            .await
+            .unwrap();
+        replayer.finish().unwrap();
+
+        // Changing the program changes the commands it sends.
+        let replayer = EngineConnection::new(&path).unwrap();
+        let err = execute(&CODE.replace("extrude(5", "extrude(6"), Arc::new(Box::new(replayer)))
+            .await
+            .unwrap_err();
+        assert!(
+            err.message().contains("doesn't match the recording"),
+            "{}",
+            err.message()
+        );
+
+        // Nothing was sent, so every recorded command is left over.
+        assert!(EngineConnection::new(&path).unwrap().finish().is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -4,6 +4,10 @@
 #[cfg(feature = "engine")]
 pub mod conn;
 pub mod conn_mock;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod conn_record;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod conn_replay;
 #[cfg(target_arch = "wasm32")]
 #[cfg(feature = "engine")]
 pub mod conn_wasm;
@@ -22,11 +26,39 @@ use crate::{
     executor::{DefaultPlanes, Point3d},
 };
 
+/// How the connection to the engine is doing, for long-running services to report.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionHealth {
+    pub status: ConnectionStatus,
+    /// How many times the connection dropped and we connected again.
+    pub reconnects: u64,
+    /// Why restoring the scene or connecting again last failed, if it did.
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    /// Connected, as far as we know.
+    #[default]
+    Connected,
+    /// The connection dropped and we're connecting again.
+    Reconnecting,
+    /// The connection dropped and connecting again failed. The next command tries again.
+    Disconnected,
+}
+
 #[async_trait::async_trait]
 pub trait EngineManager: std::fmt::Debug + Send + Sync + 'static {
     /// Get the batch of commands to be sent to the engine.
     fn batch(&self) -> Arc<Mutex<Vec<(kittycad::types::WebSocketRequest, crate::executor::SourceRange)>>>;
 
+    /// How the connection to the engine is doing.
+    fn connection_health(&self) -> ConnectionHealth {
+        ConnectionHealth::default()
+    }
+
     /// Get the default planes.
     async fn default_planes(
         &self,
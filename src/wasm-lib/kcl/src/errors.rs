@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Range};
 
 use crate::executor::SourceRange;
 
@@ -28,8 +28,12 @@ pub enum KclError {
     InvalidExpression(KclErrorDetails),
     #[error("engine: {0:?}")]
     Engine(KclErrorDetails),
+    #[error("timeout: {0:?}")]
+    Timeout(KclErrorDetails),
     #[error("internal error, please report to KittyCAD team: {0:?}")]
     Internal(KclErrorDetails),
+    #[error("imported file: {0:?}")]
+    ImportedFile(Box<ImportedFileError>),
 }
 
 #[derive(Debug, Serialize, Deserialize, ts_rs::TS, Clone, PartialEq, Eq)]
@@ -41,6 +45,27 @@ pub struct KclErrorDetails {
     pub message: String,
 }
 
+/// An error in a file the program imported.
+/// Source ranges can only point into the file being executed, so the error is reported at the
+/// import statement, and `path` and `range` say where it happened in the imported file.
+#[derive(Debug, Serialize, Deserialize, ts_rs::TS, Clone, PartialEq, Eq)]
+#[ts(export)]
+pub struct ImportedFileError {
+    /// The import statement that loaded the file.
+    #[serde(rename = "sourceRanges")]
+    pub source_ranges: Vec<SourceRange>,
+    /// The error's message, followed by the file, line and column it happened at.
+    #[serde(rename = "msg")]
+    pub message: String,
+    /// The path of the imported file.
+    pub path: String,
+    /// Where the error is in the imported file.
+    #[ts(type = "{ start: { line: number, character: number }, end: { line: number, character: number } }")]
+    pub range: Range,
+    /// The error itself, with source ranges in the imported file.
+    pub error: KclError,
+}
+
 impl KclError {
     /// Get the error message, line and column from the error and input code.
     pub fn get_message_line_column(&self, input: &str) -> (String, Option<usize>, Option<usize>) {
@@ -69,7 +94,9 @@ impl KclError {
             KclError::UndefinedValue(_) => "undefined value",
             KclError::InvalidExpression(_) => "invalid expression",
             KclError::Engine(_) => "engine",
+            KclError::Timeout(_) => "timeout",
             KclError::Internal(_) => "internal",
+            KclError::ImportedFile(e) => e.error.error_type(),
         }
     }
 
@@ -85,7 +112,9 @@ impl KclError {
             KclError::UndefinedValue(e) => e.source_ranges.clone(),
             KclError::InvalidExpression(e) => e.source_ranges.clone(),
             KclError::Engine(e) => e.source_ranges.clone(),
+            KclError::Timeout(e) => e.source_ranges.clone(),
             KclError::Internal(e) => e.source_ranges.clone(),
+            KclError::ImportedFile(e) => e.source_ranges.clone(),
         }
     }
 
@@ -102,7 +131,9 @@ impl KclError {
             KclError::UndefinedValue(e) => &e.message,
             KclError::InvalidExpression(e) => &e.message,
             KclError::Engine(e) => &e.message,
+            KclError::Timeout(e) => &e.message,
             KclError::Internal(e) => &e.message,
+            KclError::ImportedFile(e) => &e.message,
         }
     }
 
@@ -118,12 +149,26 @@ impl KclError {
             code_description: None,
             source: Some("kcl".to_string()),
             message,
-            related_information: None,
+            related_information: self.related_information(),
             tags: None,
             data: None,
         }
     }
 
+    /// Where an error from an imported file happened in that file.
+    fn related_information(&self) -> Option<Vec<DiagnosticRelatedInformation>> {
+        let KclError::ImportedFile(e) = self else {
+            return None;
+        };
+        Some(vec![DiagnosticRelatedInformation {
+            location: Location {
+                uri: crate::lsp::util::path_to_uri(std::path::Path::new(&e.path))?,
+                range: e.range,
+            },
+            message: format!("{}: {}", e.error.error_type(), e.error.message()),
+        }])
+    }
+
     pub fn override_source_ranges(&self, source_ranges: Vec<SourceRange>) -> Self {
         let mut new = self.clone();
         match &mut new {
@@ -137,11 +182,36 @@ impl KclError {
             KclError::UndefinedValue(e) => e.source_ranges = source_ranges,
             KclError::InvalidExpression(e) => e.source_ranges = source_ranges,
             KclError::Engine(e) => e.source_ranges = source_ranges,
+            KclError::Timeout(e) => e.source_ranges = source_ranges,
             KclError::Internal(e) => e.source_ranges = source_ranges,
+            KclError::ImportedFile(e) => e.source_ranges = source_ranges,
         }
 
         new
     }
+
+    /// Point an error from the imported file at `path` at the import statement that loaded it.
+    /// `name` is the file as the import statement spelled it, for the message.
+    pub fn in_imported_file(self, name: &str, path: &str, code: &str, import_range: SourceRange) -> Self {
+        let range = self
+            .source_ranges()
+            .first()
+            .map(|r| r.to_lsp_range(code))
+            .unwrap_or_default();
+        KclError::ImportedFile(Box::new(ImportedFileError {
+            source_ranges: vec![import_range],
+            message: format!(
+                "{} (in {}:{}:{})",
+                self.message(),
+                name,
+                range.start.line + 1,
+                range.start.character + 1
+            ),
+            path: path.to_string(),
+            range,
+            error: self,
+        }))
+    }
 }
 
 /// This is different than to_string() in that it will serialize the Error
@@ -12,7 +12,7 @@ use serde_json::Value as JValue;
 use tower_lsp::lsp_types::{Position as LspPosition, Range as LspRange};
 
 use crate::{
-    ast::types::{BodyItem, FunctionExpression, KclNone, Value},
+    ast::types::{describe_type, BodyItem, FunctionExpression, ItemVisibility, KclNone, Value},
     engine::EngineManager,
     errors::{KclError, KclErrorDetails},
     fs::FileManager,
@@ -23,72 +23,239 @@ use crate::{
 #[ts(export)]
 #[serde(rename_all = "camelCase")]
 pub struct ProgramMemory {
+    /// The names declared at the top level of the program.
     pub root: HashMap<String, MemoryItem>,
     #[serde(rename = "return")]
     pub return_: Option<ProgramReturn>,
+    /// Scopes nested inside the root, e.g. the bodies of function calls, loops and imported files.
+    #[serde(skip)]
+    #[ts(skip)]
+    scopes: Vec<Scope>,
+    /// The scope that names are currently declared in and looked up from.
+    #[serde(skip)]
+    #[ts(skip)]
+    current_scope: ScopeRef,
+    /// The files imported so far, by path, so each one only runs once however often it's imported.
+    #[serde(skip)]
+    #[ts(skip)]
+    modules: HashMap<std::path::PathBuf, Module>,
+    /// How many times loop bodies have run so far in this execution, across every loop.
+    #[serde(skip)]
+    #[ts(skip)]
+    pub(crate) loop_iterations: usize,
+}
+
+/// Refers to a scope in a [`ProgramMemory`], either its root or one of the scopes nested inside it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScopeRef(usize);
+
+impl ScopeRef {
+    /// The top level of the program.
+    pub const ROOT: Self = Self(0);
+}
+
+/// Names declared in a nested scope.
+#[derive(Debug, Clone, PartialEq)]
+struct Scope {
+    values: HashMap<String, MemoryItem>,
+    /// Where names that aren't declared in this scope are looked up.
+    /// Imported files have no parent, so they can't see the names of the file importing them.
+    parent: Option<ScopeRef>,
+}
+
+/// A file that has been imported.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Module {
+    /// The scope the file ran in, which holds its values.
+    pub(crate) scope: ScopeRef,
+    /// The names declared at the top level of the file, and whether each one is exported.
+    pub(crate) declarations: HashMap<String, bool>,
+}
+
+/// Where execution was before it entered a nested scope, so it can go back there afterwards.
+#[derive(Debug)]
+#[must_use]
+pub(crate) struct ScopeEntry {
+    scope: ScopeRef,
+    previous_scope: ScopeRef,
+    previous_return: Option<ProgramReturn>,
+}
+
+/// Names every program can use without declaring them.
+pub(crate) fn prelude() -> HashMap<String, MemoryItem> {
+    HashMap::from([
+        (
+            "ZERO".to_string(),
+            MemoryItem::UserVal(UserVal {
+                value: serde_json::Value::Number(serde_json::value::Number::from(0)),
+                meta: Default::default(),
+            }),
+        ),
+        (
+            "QUARTER_TURN".to_string(),
+            MemoryItem::UserVal(UserVal {
+                value: serde_json::Value::Number(serde_json::value::Number::from(90)),
+                meta: Default::default(),
+            }),
+        ),
+        (
+            "HALF_TURN".to_string(),
+            MemoryItem::UserVal(UserVal {
+                value: serde_json::Value::Number(serde_json::value::Number::from(180)),
+                meta: Default::default(),
+            }),
+        ),
+        (
+            "THREE_QUARTER_TURN".to_string(),
+            MemoryItem::UserVal(UserVal {
+                value: serde_json::Value::Number(serde_json::value::Number::from(270)),
+                meta: Default::default(),
+            }),
+        ),
+    ])
 }
 
 impl ProgramMemory {
     pub fn new() -> Self {
         Self {
-            root: HashMap::from([
-                (
-                    "ZERO".to_string(),
-                    MemoryItem::UserVal(UserVal {
-                        value: serde_json::Value::Number(serde_json::value::Number::from(0)),
-                        meta: Default::default(),
-                    }),
-                ),
-                (
-                    "QUARTER_TURN".to_string(),
-                    MemoryItem::UserVal(UserVal {
-                        value: serde_json::Value::Number(serde_json::value::Number::from(90)),
-                        meta: Default::default(),
-                    }),
-                ),
-                (
-                    "HALF_TURN".to_string(),
-                    MemoryItem::UserVal(UserVal {
-                        value: serde_json::Value::Number(serde_json::value::Number::from(180)),
-                        meta: Default::default(),
-                    }),
-                ),
-                (
-                    "THREE_QUARTER_TURN".to_string(),
-                    MemoryItem::UserVal(UserVal {
-                        value: serde_json::Value::Number(serde_json::value::Number::from(270)),
-                        meta: Default::default(),
-                    }),
-                ),
-            ]),
+            root: prelude(),
             return_: None,
+            scopes: Vec::new(),
+            current_scope: ScopeRef::ROOT,
+            modules: HashMap::new(),
+            loop_iterations: 0,
         }
     }
 
-    /// Add to the program memory.
+    /// Add to the program memory, in the current scope.
+    /// Names from outer scopes can be shadowed, but a scope can't declare the same name twice.
     pub fn add(&mut self, key: &str, value: MemoryItem, source_range: SourceRange) -> Result<(), KclError> {
-        if self.root.contains_key(key) {
+        let values = self.values_mut(self.current_scope);
+        if values.contains_key(key) {
             return Err(KclError::ValueAlreadyDefined(KclErrorDetails {
                 message: format!("Cannot redefine {}", key),
                 source_ranges: vec![source_range],
             }));
         }
 
-        self.root.insert(key.to_string(), value);
+        values.insert(key.to_string(), value);
 
         Ok(())
     }
 
-    /// Get a value from the program memory.
+    /// Get a value from the program memory, looking in the current scope and then the scopes around it.
     /// Return Err if not found.
     pub fn get(&self, key: &str, source_range: SourceRange) -> Result<&MemoryItem, KclError> {
-        self.root.get(key).ok_or_else(|| {
-            KclError::UndefinedValue(KclErrorDetails {
-                message: format!("memory item key `{}` is not defined", key),
-                source_ranges: vec![source_range],
-            })
+        self.get_in(self.current_scope, key, source_range)
+    }
+
+    /// Get a value from the program memory, looking in `scope` and then the scopes around it.
+    pub(crate) fn get_in(
+        &self,
+        scope: ScopeRef,
+        key: &str,
+        source_range: SourceRange,
+    ) -> Result<&MemoryItem, KclError> {
+        let mut scope = Some(scope);
+        while let Some(current) = scope {
+            if let Some(value) = self.values(current).get(key) {
+                return Ok(value);
+            }
+            scope = self.parent(current);
+        }
+
+        Err(KclError::UndefinedValue(KclErrorDetails {
+            message: format!("memory item key `{}` is not defined", key),
+            source_ranges: vec![source_range],
+        }))
+    }
+
+    /// The scope that names are currently declared in.
+    pub fn current_scope(&self) -> ScopeRef {
+        self.current_scope
+    }
+
+    /// Start declaring names in a new scope nested inside `parent`.
+    /// The return value of the enclosing body is put aside until [`Self::exit_scope`].
+    pub(crate) fn enter_scope(&mut self, parent: ScopeRef) -> ScopeEntry {
+        self.push_scope(Scope {
+            values: HashMap::new(),
+            parent: Some(parent),
+        })
+    }
+
+    /// Go back to the scope that was current before `entry`, returning the value the nested scope returned.
+    /// The nested scope is thrown away, unless it returned a function that might still need its names.
+    pub(crate) fn exit_scope(&mut self, entry: ScopeEntry) -> Option<ProgramReturn> {
+        let scope = entry.scope;
+        let returned = self.pop_scope(entry);
+        if !matches!(returned, Some(ProgramReturn::Value(MemoryItem::Function { .. }))) {
+            // Nothing can refer to this scope, or the scopes created after it, any more.
+            self.scopes.truncate(scope.0 - 1);
+        }
+        returned
+    }
+
+    /// Start executing an imported file, in a scope which can't see the names of this one.
+    pub(crate) fn enter_module(&mut self) -> ScopeEntry {
+        self.push_scope(Scope {
+            values: prelude(),
+            parent: None,
         })
     }
+
+    /// Finish executing an imported file.
+    /// Its scope is kept, because the functions it exported can still refer to it.
+    pub(crate) fn exit_module(&mut self, entry: ScopeEntry) {
+        self.pop_scope(entry);
+    }
+
+    /// The file at `path`, if it has already been imported.
+    pub(crate) fn module(&self, path: &std::path::Path) -> Option<&Module> {
+        self.modules.get(path)
+    }
+
+    /// Remember that the file at `path` has been imported.
+    pub(crate) fn add_module(&mut self, path: std::path::PathBuf, module: Module) {
+        self.modules.insert(path, module);
+    }
+
+    fn push_scope(&mut self, scope: Scope) -> ScopeEntry {
+        self.scopes.push(scope);
+        let entry = ScopeEntry {
+            scope: ScopeRef(self.scopes.len()),
+            previous_scope: self.current_scope,
+            previous_return: self.return_.take(),
+        };
+        self.current_scope = entry.scope;
+        entry
+    }
+
+    fn pop_scope(&mut self, entry: ScopeEntry) -> Option<ProgramReturn> {
+        self.current_scope = entry.previous_scope;
+        std::mem::replace(&mut self.return_, entry.previous_return)
+    }
+
+    fn values(&self, scope: ScopeRef) -> &HashMap<String, MemoryItem> {
+        match scope {
+            ScopeRef::ROOT => &self.root,
+            ScopeRef(index) => &self.scopes[index - 1].values,
+        }
+    }
+
+    fn values_mut(&mut self, scope: ScopeRef) -> &mut HashMap<String, MemoryItem> {
+        match scope {
+            ScopeRef::ROOT => &mut self.root,
+            ScopeRef(index) => &mut self.scopes[index - 1].values,
+        }
+    }
+
+    fn parent(&self, scope: ScopeRef) -> Option<ScopeRef> {
+        match scope {
+            ScopeRef::ROOT => None,
+            ScopeRef(index) => self.scopes[index - 1].parent,
+        }
+    }
 }
 
 impl Default for ProgramMemory {
@@ -148,6 +315,9 @@ pub enum MemoryItem {
         #[serde(skip)]
         func: Option<MemoryFunction>,
         expression: Box<FunctionExpression>,
+        /// The scope the function was declared in, which its body can use the names of.
+        #[serde(skip)]
+        scope: ScopeRef,
         #[serde(rename = "__meta")]
         meta: Vec<Metadata>,
     },
@@ -298,23 +468,28 @@ pub struct UserVal {
     pub meta: Vec<Metadata>,
 }
 
-pub type MemoryFunction =
-    fn(
-        s: Vec<MemoryItem>,
-        memory: ProgramMemory,
-        expression: Box<FunctionExpression>,
-        metadata: Vec<Metadata>,
-        ctx: ExecutorContext,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<ProgramReturn>, KclError>> + Send>>;
+pub type MemoryFunction = for<'a> fn(
+    s: Vec<MemoryItem>,
+    memory: &'a mut ProgramMemory,
+    scope: ScopeRef,
+    expression: Box<FunctionExpression>,
+    metadata: Vec<Metadata>,
+    ctx: ExecutorContext,
+) -> std::pin::Pin<
+    Box<dyn std::future::Future<Output = Result<Option<ProgramReturn>, KclError>> + Send + 'a>,
+>;
 
 fn force_memory_function<
-    F: Fn(
+    F: for<'a> Fn(
         Vec<MemoryItem>,
-        ProgramMemory,
+        &'a mut ProgramMemory,
+        ScopeRef,
         Box<FunctionExpression>,
         Vec<Metadata>,
         ExecutorContext,
-    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Option<ProgramReturn>, KclError>> + Send>>,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Option<ProgramReturn>, KclError>> + Send + 'a>,
+    >,
 >(
     f: F,
 ) -> F {
@@ -391,15 +566,29 @@ impl MemoryItem {
             .map(Some)
     }
 
+    /// Is this the value of an optional parameter that wasn't given an argument?
+    pub fn is_none(&self) -> bool {
+        let MemoryItem::UserVal(user_val) = self else {
+            return false;
+        };
+        user_val.value.get("type").and_then(JValue::as_str) == Some("KclNone")
+    }
+
     /// If this memory item is a function, call it with the given arguments, return its val as Ok.
     /// If it's not a function, return Err.
     pub async fn call_fn(
         &self,
         args: Vec<MemoryItem>,
-        memory: ProgramMemory,
+        memory: &mut ProgramMemory,
         ctx: ExecutorContext,
     ) -> Result<Option<ProgramReturn>, KclError> {
-        let MemoryItem::Function { func, expression, meta } = &self else {
+        let MemoryItem::Function {
+            func,
+            expression,
+            scope,
+            meta,
+        } = &self
+        else {
             return Err(KclError::Semantic(KclErrorDetails {
                 message: "not a in memory function".to_string(),
                 source_ranges: vec![],
@@ -411,7 +600,7 @@ impl MemoryItem {
                 source_ranges: vec![],
             }));
         };
-        func(args, memory, expression.clone(), meta.clone(), ctx).await
+        func(args, memory, *scope, expression.clone(), meta.clone(), ctx).await
     }
 }
 
@@ -991,6 +1180,9 @@ pub struct ExecutorContext {
     /// Mock mode is only for the modeling app when they just want to mock engine calls and not
     /// actually make them.
     pub is_mock: bool,
+    /// The KCL files currently being imported, outermost first.
+    /// Used to detect import cycles.
+    pub import_stack: Vec<std::path::PathBuf>,
 }
 
 /// The executor settings.
@@ -1002,14 +1194,24 @@ pub struct ExecutorSettings {
     pub highlight_edges: bool,
     /// Whether or not Screen Space Ambient Occlusion (SSAO) is enabled.
     pub enable_ssao: bool,
+    /// The directory of the current project.
+    /// Paths in KCL import statements are relative to this.
+    pub project_directory: Option<String>,
+    /// How long to wait for the engine to respond to a modeling command before giving up on it.
+    pub engine_timeout: std::time::Duration,
 }
 
+/// How long to wait for the engine to respond to a modeling command, unless the settings say otherwise.
+pub const DEFAULT_ENGINE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
 impl Default for ExecutorSettings {
     fn default() -> Self {
         Self {
             units: Default::default(),
             highlight_edges: true,
             enable_ssao: false,
+            project_directory: None,
+            engine_timeout: DEFAULT_ENGINE_TIMEOUT,
         }
     }
 }
@@ -1020,6 +1222,8 @@ impl From<crate::settings::types::Configuration> for ExecutorSettings {
             units: config.settings.modeling.base_unit,
             highlight_edges: config.settings.modeling.highlight_edges.into(),
             enable_ssao: config.settings.modeling.enable_ssao.into(),
+            project_directory: None,
+            engine_timeout: DEFAULT_ENGINE_TIMEOUT,
         }
     }
 }
@@ -1030,6 +1234,8 @@ impl From<crate::settings::types::project::ProjectConfiguration> for ExecutorSet
             units: config.settings.modeling.base_unit,
             highlight_edges: config.settings.modeling.highlight_edges.into(),
             enable_ssao: config.settings.modeling.enable_ssao.into(),
+            project_directory: None,
+            engine_timeout: DEFAULT_ENGINE_TIMEOUT,
         }
     }
 }
@@ -1040,6 +1246,8 @@ impl From<crate::settings::types::ModelingSettings> for ExecutorSettings {
             units: modeling.base_unit,
             highlight_edges: modeling.highlight_edges.into(),
             enable_ssao: modeling.enable_ssao.into(),
+            project_directory: None,
+            engine_timeout: DEFAULT_ENGINE_TIMEOUT,
         }
     }
 }
@@ -1048,26 +1256,17 @@ impl ExecutorContext {
     /// Create a new default executor context.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn new(client: &kittycad::Client, settings: ExecutorSettings) -> Result<Self> {
-        let ws = client
-            .modeling()
-            .commands_ws(
-                None,
-                None,
-                if settings.enable_ssao {
-                    Some(kittycad::types::PostEffectType::Ssao)
-                } else {
-                    None
-                },
-                None,
-                None,
-                None,
-                Some(false),
-            )
-            .await?;
+        let engine: Arc<Box<dyn EngineManager>> = Arc::new(Box::new(
+            crate::engine::conn::EngineConnection::connect(client, settings.enable_ssao, settings.engine_timeout)
+                .await?,
+        ));
 
-        let engine: Arc<Box<dyn EngineManager>> =
-            Arc::new(Box::new(crate::engine::conn::EngineConnection::new(ws).await?));
+        Self::new_with_engine(engine, settings).await
+    }
 
+    /// Create a new executor context which sends modeling commands to `engine`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn new_with_engine(engine: Arc<Box<dyn EngineManager>>, settings: ExecutorSettings) -> Result<Self> {
         // Set the edge visibility.
         engine
             .send_modeling_cmd(
@@ -1085,6 +1284,7 @@ impl ExecutorContext {
             stdlib: Arc::new(StdLib::new()),
             settings,
             is_mock: false,
+            import_stack: Vec::new(),
         })
     }
 
@@ -1106,13 +1306,11 @@ impl ExecutorContext {
                 },
             )
             .await?;
-        let mut memory = if let Some(memory) = memory {
-            memory.clone()
-        } else {
-            Default::default()
-        };
+        let mut memory = memory.unwrap_or_default();
+        memory.loop_iterations = 0;
         self.inner_execute(program, &mut memory, crate::executor::BodyType::Root)
-            .await
+            .await?;
+        Ok(memory)
     }
 
     /// Execute an AST's program.
@@ -1121,8 +1319,8 @@ impl ExecutorContext {
         &self,
         program: crate::ast::types::Program,
         memory: &mut ProgramMemory,
-        _body_type: BodyType,
-    ) -> Result<ProgramMemory, KclError> {
+        body_type: BodyType,
+    ) -> Result<(), KclError> {
         let pipe_info = PipeInfo::default();
 
         // Iterate over the body of the program.
@@ -1131,6 +1329,8 @@ impl ExecutorContext {
                 BodyItem::ExpressionStatement(expression_statement) => {
                     if let Value::PipeExpression(pipe_expr) = &expression_statement.expression {
                         pipe_expr.get_result(memory, &pipe_info, self).await?;
+                    } else if let Value::IfExpression(if_expr) = &expression_statement.expression {
+                        if_expr.get_result(memory, &pipe_info, self).await?;
                     } else if let Value::CallExpression(call_expr) = &expression_statement.expression {
                         let fn_name = call_expr.callee.name.to_string();
                         let mut args: Vec<MemoryItem> = Vec::new();
@@ -1161,6 +1361,10 @@ impl ExecutorContext {
                                     let result = array_expression.execute(memory, &pipe_info, self).await?;
                                     args.push(result);
                                 }
+                                Value::IfExpression(if_expression) => {
+                                    let result = if_expression.get_result(memory, &pipe_info, self).await?;
+                                    args.push(result);
+                                }
                                 // We do nothing for the rest.
                                 _ => (),
                             }
@@ -1172,15 +1376,16 @@ impl ExecutorContext {
                                 memory.return_ = Some(ProgramReturn::Value(result));
                             }
                             FunctionKind::Std(func) => {
-                                let mut newmem = memory.clone();
+                                let entry = memory.enter_scope(ScopeRef::ROOT);
                                 let result = self
-                                    .inner_execute(func.program().to_owned(), &mut newmem, BodyType::Block)
-                                    .await?;
-                                memory.return_ = result.return_;
+                                    .inner_execute(func.program().to_owned(), memory, BodyType::Block)
+                                    .await;
+                                memory.return_ = memory.exit_scope(entry);
+                                result?;
                             }
                             FunctionKind::UserDefined => {
-                                if let Some(func) = memory.clone().root.get(&fn_name) {
-                                    let result = func.call_fn(args.clone(), memory.clone(), self.clone()).await?;
+                                if let Ok(func) = memory.get(&fn_name, call_expr.into()).cloned() {
+                                    let result = func.call_fn(args.clone(), memory, self.clone()).await?;
 
                                     memory.return_ = result;
                                 } else {
@@ -1194,6 +1399,12 @@ impl ExecutorContext {
                     }
                 }
                 BodyItem::VariableDeclaration(variable_declaration) => {
+                    if variable_declaration.visibility == ItemVisibility::Export && body_type != BodyType::Root {
+                        return Err(KclError::Semantic(KclErrorDetails {
+                            message: "Only declarations at the top level of a file can be exported".to_string(),
+                            source_ranges: vec![variable_declaration.into()],
+                        }));
+                    }
                     for declaration in &variable_declaration.declarations {
                         let var_name = declaration.id.name.to_string();
                         let source_range: SourceRange = declaration.init.clone().into();
@@ -1217,23 +1428,13 @@ impl ExecutorContext {
                             Value::FunctionExpression(function_expression) => {
                                 let mem_func = force_memory_function(
                                     |args: Vec<MemoryItem>,
-                                     memory: ProgramMemory,
+                                     memory: &mut ProgramMemory,
+                                     scope: ScopeRef,
                                      function_expression: Box<FunctionExpression>,
                                      _metadata: Vec<Metadata>,
                                      ctx: ExecutorContext| {
                                         Box::pin(async move {
-                                            let mut fn_memory =
-                                                assign_args_to_params(&function_expression, args, memory.clone())?;
-
-                                            let result = ctx
-                                                .inner_execute(
-                                                    function_expression.body.clone(),
-                                                    &mut fn_memory,
-                                                    BodyType::Block,
-                                                )
-                                                .await?;
-
-                                            Ok(result.return_)
+                                            call_user_function(&function_expression, args, memory, scope, &ctx).await
                                         })
                                     },
                                 );
@@ -1243,6 +1444,7 @@ impl ExecutorContext {
                                         expression: function_expression.clone(),
                                         meta: vec![metadata],
                                         func: Some(mem_func),
+                                        scope: memory.current_scope(),
                                     },
                                     source_range,
                                 )?;
@@ -1280,6 +1482,10 @@ impl ExecutorContext {
                                 let result = unary_expression.get_result(memory, &pipe_info, self).await?;
                                 memory.add(&var_name, result, source_range)?;
                             }
+                            Value::IfExpression(if_expression) => {
+                                let result = if_expression.get_result(memory, &pipe_info, self).await?;
+                                memory.add(&var_name, result, source_range)?;
+                            }
                         }
                     }
                 }
@@ -1319,19 +1525,35 @@ impl ExecutorContext {
                         let result = pipe_expr.get_result(memory, &pipe_info, self).await?;
                         memory.return_ = Some(ProgramReturn::Value(result));
                     }
+                    Value::IfExpression(if_expr) => {
+                        let result = if_expr.get_result(memory, &pipe_info, self).await?;
+                        memory.return_ = Some(ProgramReturn::Value(result));
+                    }
                     Value::PipeSubstitution(_) => {}
                     Value::FunctionExpression(_) => {}
                     Value::None(none) => {
                         memory.return_ = Some(ProgramReturn::Value(MemoryItem::from(none)));
                     }
                 },
+                BodyItem::ForStatement(for_statement) => {
+                    for_statement.execute(memory, &pipe_info, self).await?;
+                }
+                BodyItem::ImportStatement(import_statement) => {
+                    if body_type != BodyType::Root {
+                        return Err(KclError::Semantic(KclErrorDetails {
+                            message: "Imports are only allowed at the top level of a file".to_string(),
+                            source_ranges: vec![import_statement.into()],
+                        }));
+                    }
+                    import_statement.execute(memory, self).await?;
+                }
             }
         }
 
         // Flush the batch queue.
         self.engine.flush_batch(SourceRange([program.end, program.end])).await?;
 
-        Ok(memory.clone())
+        Ok(())
     }
 
     /// Update the units for the executor.
@@ -1340,14 +1562,39 @@ impl ExecutorContext {
     }
 }
 
+/// Call a user-defined function.
+/// Its arguments are declared in a new scope nested inside `scope`, the one the function was declared in,
+/// so its body sees the names around its declaration rather than those around the call.
+async fn call_user_function(
+    function_expression: &FunctionExpression,
+    args: Vec<MemoryItem>,
+    memory: &mut ProgramMemory,
+    scope: ScopeRef,
+    ctx: &ExecutorContext,
+) -> Result<Option<ProgramReturn>, KclError> {
+    let entry = memory.enter_scope(scope);
+    let result = match assign_args_to_params(function_expression, args, memory) {
+        Ok(()) => {
+            ctx.inner_execute(function_expression.body.clone(), memory, BodyType::Block)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    let returned = memory.exit_scope(entry);
+    result?;
+
+    check_return_type(function_expression, &returned)?;
+    Ok(returned)
+}
+
 /// For each argument given,
-/// assign it to a parameter of the function, in the given block of function memory.
+/// assign it to a parameter of the function, in the current scope of the function memory.
 /// Returns Err if too few/too many arguments were given for the function.
 fn assign_args_to_params(
     function_expression: &FunctionExpression,
     args: Vec<MemoryItem>,
-    mut fn_memory: ProgramMemory,
-) -> Result<ProgramMemory, KclError> {
+    fn_memory: &mut ProgramMemory,
+) -> Result<(), KclError> {
     let num_args = function_expression.number_of_args();
     let (min_params, max_params) = num_args.into_inner();
     let n = args.len();
@@ -1369,7 +1616,22 @@ fn assign_args_to_params(
     // Add the arguments to the memory.
     for (index, param) in function_expression.params.iter().enumerate() {
         if let Some(arg) = args.get(index) {
-            // Argument was provided.
+            // Argument was provided, so make sure it has the declared type.
+            if let Some(type_) = &param.type_ {
+                if !(type_.matches(arg) || (param.optional && arg.is_none())) {
+                    let mut source_ranges: Vec<SourceRange> = arg.clone().into();
+                    source_ranges.push((&param.identifier).into());
+                    return Err(KclError::Type(KclErrorDetails {
+                        message: format!(
+                            "The parameter `{}` was declared as `{}`, but this argument is a `{}`",
+                            param.identifier.name,
+                            type_,
+                            describe_type(arg)
+                        ),
+                        source_ranges,
+                    }));
+                }
+            }
             fn_memory.add(&param.identifier.name, arg.clone(), (&param.identifier).into())?;
         } else {
             // Argument was not provided.
@@ -1392,7 +1654,33 @@ fn assign_args_to_params(
             }
         }
     }
-    Ok(fn_memory)
+    Ok(())
+}
+
+/// Make sure a user-defined function returned a value of the type it declared, if it declared one.
+fn check_return_type(
+    function_expression: &FunctionExpression,
+    returned: &Option<ProgramReturn>,
+) -> Result<(), KclError> {
+    let (Some(return_type), Some(ProgramReturn::Value(value))) = (&function_expression.return_type, returned) else {
+        return Ok(());
+    };
+    if return_type.matches(value) {
+        return Ok(());
+    }
+
+    let mut source_ranges: Vec<SourceRange> = value.clone().into();
+    if source_ranges.is_empty() {
+        source_ranges.push(function_expression.into());
+    }
+    Err(KclError::Type(KclErrorDetails {
+        message: format!(
+            "This function was declared to return `{}`, but it returned a `{}`",
+            return_type,
+            describe_type(value)
+        ),
+        source_ranges,
+    }))
 }
 
 #[cfg(test)]
@@ -1414,12 +1702,41 @@ mod tests {
             stdlib: Arc::new(crate::std::StdLib::new()),
             settings: Default::default(),
             is_mock: false,
+            import_stack: Vec::new(),
         };
         let memory = ctx.run(program, None).await?;
 
         Ok(memory)
     }
 
+    /// Write the given files into a fresh project directory, then execute `code` as part of that project.
+    async fn parse_execute_in_project(code: &str, files: &[(&str, &str)]) -> Result<ProgramMemory> {
+        let dir = std::env::temp_dir().join(format!("kcl_project_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)?;
+        for (name, contents) in files {
+            std::fs::write(dir.join(name), contents)?;
+        }
+
+        let tokens = crate::token::lexer(code)?;
+        let parser = crate::parser::Parser::new(tokens);
+        let program = parser.ast()?;
+        let ctx = ExecutorContext {
+            engine: Arc::new(Box::new(crate::engine::conn_mock::EngineConnection::new().await?)),
+            fs: Arc::new(crate::fs::FileManager::new()),
+            stdlib: Arc::new(crate::std::StdLib::new()),
+            settings: ExecutorSettings {
+                project_directory: Some(dir.display().to_string()),
+                ..Default::default()
+            },
+            is_mock: false,
+            import_stack: Vec::new(),
+        };
+        let memory = ctx.run(program, None).await;
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(memory?)
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_execute_assign_two_variables() {
         let ast = r#"const myVar = 5
@@ -1920,6 +2237,536 @@ const bracket = startSketchOn('XY')
         parse_execute(ast).await.unwrap();
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_if_else() {
+        let ast = r#"const a = if true {
+  3
+} else if true {
+  4
+} else {
+  5
+}
+const b = if false {
+  3
+} else if true {
+  const inner = 4
+  inner + 1
+} else {
+  5
+}
+const c = if false {
+  3
+} else {
+  5
+}"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert_eq!(
+            serde_json::json!(3),
+            memory.root.get("a").unwrap().get_json_value().unwrap()
+        );
+        assert_eq!(
+            serde_json::json!(5.0),
+            memory.root.get("b").unwrap().get_json_value().unwrap()
+        );
+        assert_eq!(
+            serde_json::json!(5),
+            memory.root.get("c").unwrap().get_json_value().unwrap()
+        );
+        // Names declared inside a branch don't leak out of it.
+        assert!(!memory.root.contains_key("inner"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_if_without_else_is_none() {
+        let ast = r#"const flag = false
+const a = if flag {
+  3
+}"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert_eq!(None, memory.root.get("a").unwrap().get_json_opt::<f64>().unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_if_in_function() {
+        let ast = r#"fn pick = (useFirst) => {
+  return if useFirst {
+    1
+  } else {
+    2
+  }
+}
+const a = pick(true)
+const b = pick(false)"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert_eq!(
+            serde_json::json!(1),
+            memory.root.get("a").unwrap().get_json_value().unwrap()
+        );
+        assert_eq!(
+            serde_json::json!(2),
+            memory.root.get("b").unwrap().get_json_value().unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_if_condition_must_be_bool() {
+        let ast = r#"const a = if 1 {
+  3
+} else {
+  4
+}"#;
+        let result = parse_execute(ast).await;
+        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"type: KclErrorDetails { source_ranges: [SourceRange([13, 14])], message: "The condition of an if expression must be a boolean, but it was 1" }"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_comparison_and_boolean_operators() {
+        let ast = r#"const x = 3
+const lt = x < 4
+const lte = x <= 2
+const gt = x > 2.5
+const gte = x >= 3
+const eq = x == 3.0
+const neq = "a" != "b"
+const both = lt && gte
+const either = lte || !gt
+const precedence = 1 + 2 > 2 * 1 && x ^ 2 == 9"#;
+        let memory = parse_execute(ast).await.unwrap();
+        for (name, expected) in [
+            ("lt", true),
+            ("lte", false),
+            ("gt", true),
+            ("gte", true),
+            ("eq", true),
+            ("neq", true),
+            ("both", true),
+            ("either", false),
+            ("precedence", true),
+        ] {
+            assert_eq!(
+                serde_json::json!(expected),
+                memory.root.get(name).unwrap().get_json_value().unwrap(),
+                "wrong value for {name}"
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_boolean_operators_short_circuit() {
+        // The right-hand side is never evaluated, so the undefined name isn't an error.
+        let ast = r#"const a = false && notDefined
+const b = true || notDefined"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert_eq!(
+            serde_json::json!(false),
+            memory.root.get("a").unwrap().get_json_value().unwrap()
+        );
+        assert_eq!(
+            serde_json::json!(true),
+            memory.root.get("b").unwrap().get_json_value().unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_boolean_operators_need_booleans() {
+        let result = parse_execute("const a = 1 && true").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"type: KclErrorDetails { source_ranges: [SourceRange([10, 11])], message: "Expected a boolean but found 1" }"#
+        );
+
+        let result = parse_execute("const a = !3").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"type: KclErrorDetails { source_ranges: [SourceRange([10, 12])], message: "Expected a boolean but found 3" }"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_for_loop() {
+        // The body fails on the iteration where `i` is 2, which proves how far the loop got.
+        let ast = |range: &str| {
+            format!(
+                r#"const n = 1
+for i in {range} {{
+  const x = if i == 2 {{
+    notDefined
+  }} else {{
+    i
+  }}
+}}"#
+            )
+        };
+        parse_execute(&ast("[0..n]")).await.unwrap();
+        parse_execute(&ast("[0..1]")).await.unwrap();
+        parse_execute(&ast("[3, 4, 0]")).await.unwrap();
+        // Ranges are inclusive.
+        assert!(parse_execute(&ast("[0..n + 1]")).await.is_err());
+        assert!(parse_execute(&ast("[3, 2]")).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_for_loop_scope() {
+        let ast = r#"for i in [0..2] {
+  const x = i
+}"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert!(!memory.root.contains_key("i"));
+        assert!(!memory.root.contains_key("x"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_for_loop_errors() {
+        let result = parse_execute("for i in [0..100000] {}").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"semantic: KclErrorDetails { source_ranges: [SourceRange([9, 20])], message: "This for loop would run 100001 times, but a loop can run at most 10000 times" }"#
+        );
+
+        let result = parse_execute("for i in [0..1.5] {}").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"type: KclErrorDetails { source_ranges: [SourceRange([13, 16])], message: "The bounds of a range must be integers, but this was 1.5" }"#
+        );
+
+        let result = parse_execute("for i in 5 {}").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"type: KclErrorDetails { source_ranges: [SourceRange([9, 10])], message: "A for loop can only iterate over an array or a range, but this was 5" }"#
+        );
+
+        let result = parse_execute("for i in [-9000000000000000000..9000000000000000000] {}").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"semantic: KclErrorDetails { source_ranges: [SourceRange([9, 52])], message: "This for loop would run 18000000000000000001 times, but a loop can run at most 10000 times" }"#
+        );
+
+        let result = parse_execute("for i in [0..1000000000000000000000] {}").await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"semantic: KclErrorDetails { source_ranges: [SourceRange([13, 35])], message: "The bounds of a range must fit in a 64-bit integer, but this was 1000000000000000000000" }"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_nested_for_loops_share_an_iteration_budget() {
+        let ast = r#"for i in [1..1000] {
+  for j in [1..1000] {}
+}"#;
+        let result = parse_execute(ast).await;
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            r#"semantic: KclErrorDetails { source_ranges: [SourceRange([23, 44])], message: "Loops in this program have run more than 100000 times in total, so execution was stopped" }"#
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_runs_have_their_own_iteration_budget() {
+        // Each run loops 60006 times, so they'd go over budget if they counted together.
+        let ast = r#"for i in [1..6] {
+  for j in [1..10000] {}
+}"#;
+        let program = crate::parser::Parser::new(crate::token::lexer(ast).unwrap())
+            .ast()
+            .unwrap();
+        let ctx = ExecutorContext {
+            engine: Arc::new(Box::new(
+                crate::engine::conn_mock::EngineConnection::new().await.unwrap(),
+            )),
+            fs: Arc::new(crate::fs::FileManager::new()),
+            stdlib: Arc::new(crate::std::StdLib::new()),
+            settings: Default::default(),
+            is_mock: false,
+            import_stack: Vec::new(),
+        };
+        let runs: Vec<_> = (0..2)
+            .map(|_| {
+                let (ctx, program) = (ctx.clone(), program.clone());
+                tokio::spawn(async move { ctx.run(program, None).await })
+            })
+            .collect();
+        for run in runs {
+            run.await.unwrap().unwrap();
+        }
+    }
+
+    const SHAPES_KCL: &str = r#"fn addOne = (x) => { return x + 1 }
+export fn bigger = (n) => { return addOne(n) * 2 }
+export const width = 4
+const secret = 3"#;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import() {
+        let ast = r#"import { bigger, width as w } from "shapes.kcl"
+const size = bigger(w)"#;
+        let memory = parse_execute_in_project(ast, &[("shapes.kcl", SHAPES_KCL)])
+            .await
+            .unwrap();
+        // `bigger` can call `addOne`, even though it wasn't exported.
+        assert_eq!(
+            memory
+                .get("size", SourceRange::default())
+                .unwrap()
+                .get_json_opt::<f64>()
+                .unwrap(),
+            Some(10.0)
+        );
+        assert_eq!(
+            memory
+                .get("w", SourceRange::default())
+                .unwrap()
+                .get_json_value()
+                .unwrap(),
+            4
+        );
+        for name in ["width", "addOne", "secret"] {
+            assert!(!memory.root.contains_key(name), "{name} should not be in memory");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import_errors() {
+        let files = [
+            ("shapes.kcl", SHAPES_KCL),
+            (
+                "broken.kcl",
+                "export const x = 1
+const y = missing",
+            ),
+            (
+                "a.kcl",
+                r#"import { b } from "./b.kcl"
+export const a = 1"#,
+            ),
+            (
+                "b.kcl",
+                r#"import { a } from "a.kcl"
+export const b = 1"#,
+            ),
+        ];
+        for (code, expected) in [
+            (
+                r#"import { secret } from "shapes.kcl""#,
+                r#"semantic: KclErrorDetails { source_ranges: [SourceRange([9, 15])], message: "\"secret\" isn't exported from \"shapes.kcl\". Add `export` before its declaration to import it" }"#,
+            ),
+            (
+                r#"import { nothing } from "shapes.kcl""#,
+                r#"undefined value: KclErrorDetails { source_ranges: [SourceRange([9, 16])], message: "\"nothing\" isn't declared in \"shapes.kcl\"" }"#,
+            ),
+            (
+                r#"import { width } from "cube.obj""#,
+                r#"semantic: KclErrorDetails { source_ranges: [SourceRange([0, 32])], message: "Only KCL files can be imported with `import { ... } from`, but \"cube.obj\" isn't one. Use the `import` function to load other CAD files" }"#,
+            ),
+            (
+                "fn f = () => {\n  import { width } from \"shapes.kcl\"\n  return width\n}\nconst v = f()",
+                r#"semantic: KclErrorDetails { source_ranges: [SourceRange([17, 51])], message: "Imports are only allowed at the top level of a file" }"#,
+            ),
+        ] {
+            let result = parse_execute_in_project(&format!("{code}\nconst z = 0"), &files).await;
+            assert_eq!(result.unwrap_err().to_string(), expected, "wrong error for {code}");
+        }
+
+        // Errors in the imported file say where they are in it.
+        let result = parse_execute_in_project(r#"import { x } from "broken.kcl""#, &files).await;
+        let err = result.unwrap_err();
+        let Some(KclError::ImportedFile(err)) = err.downcast_ref::<KclError>() else {
+            panic!("expected an error in the imported file, got {err}");
+        };
+        assert_eq!(err.source_ranges, vec![SourceRange([0, 30])]);
+        assert_eq!(
+            err.message,
+            "memory item key `missing` is not defined (in broken.kcl:2:11)"
+        );
+        assert!(err.path.ends_with("/broken.kcl"), "{}", err.path);
+        assert_eq!(
+            err.range,
+            tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 1, character: 10 },
+                end: tower_lsp::lsp_types::Position { line: 1, character: 17 },
+            }
+        );
+        assert_eq!(err.error.source_ranges(), vec![SourceRange([29, 36])]);
+        // The language server links the diagnostic to the place in the imported file.
+        let diagnostic = KclError::ImportedFile(err.clone()).to_lsp_diagnostic(r#"import { x } from "broken.kcl""#);
+        let related = diagnostic.related_information.unwrap();
+        assert_eq!(
+            related[0].location.uri.to_file_path().unwrap().display().to_string(),
+            err.path
+        );
+        assert_eq!(related[0].location.range, err.range);
+
+        let result = parse_execute_in_project(r#"import { a } from "a.kcl""#, &files).await;
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("Import cycle detected: "), "{err}");
+        assert!(err.contains("a.kcl -> ") && err.contains("b.kcl -> "), "{err}");
+
+        let result = parse_execute_in_project(r#"import { width } from "missing.kcl""#, &files).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_import_runs_each_file_once() {
+        let files = [
+            (
+                "part.kcl",
+                r#"export const sketch = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %)"#,
+            ),
+            (
+                "user.kcl",
+                "import { sketch } from \"./part.kcl\"\nexport const again = sketch",
+            ),
+        ];
+        let ast = r#"import { sketch } from "part.kcl"
+import { again } from "user.kcl"
+import { sketch as third } from "sub/../part.kcl""#;
+        let memory = parse_execute_in_project(ast, &files).await.unwrap();
+        // Running the file again would have made a new sketch, with a new ID.
+        let sketch = memory.get("sketch", SourceRange::default()).unwrap();
+        assert_eq!(sketch, memory.get("again", SourceRange::default()).unwrap());
+        assert_eq!(sketch, memory.get("third", SourceRange::default()).unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lexical_scope() {
+        let ast = r#"const x = 10
+fn addX = (n) => { return n + x }
+fn twice = (x) => { return addX(x) + addX(x) }
+fn shadow = () => {
+  const x = 1
+  return x
+}
+fn usesLater = () => { return later }
+const later = 5
+fn fact = (n) => { return if n <= 1 { 1 } else { n * fact(n - 1) } }
+fn makeAdder = (n) => {
+  fn add = (m) => { return m + n }
+  return add
+}
+const addTwo = makeAdder(2)
+const a = twice(1)
+const b = shadow()
+const c = usesLater()
+const d = fact(4)
+const g = addTwo(3)"#;
+        let memory = parse_execute(ast).await;
+        let Ok(memory) = memory else {
+            panic!("{memory:?}");
+        };
+        for (name, expected) in [
+            ("x", serde_json::json!(10)),
+            ("a", serde_json::json!(22.0)),
+            ("b", serde_json::json!(1)),
+            ("c", serde_json::json!(5)),
+            ("d", serde_json::json!(24.0)),
+            ("g", serde_json::json!(5.0)),
+        ] {
+            assert_eq!(
+                memory.root.get(name).unwrap().get_json_value().unwrap(),
+                expected,
+                "wrong value for {name}"
+            );
+        }
+        // Names declared inside a function don't leak out of it.
+        assert!(!memory.root.contains_key("add"));
+        assert!(!memory.root.contains_key("m"));
+        // Only the scope captured by `addTwo` outlives its call.
+        assert_eq!(memory.scopes.len(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_lexical_scope_errors() {
+        for (code, expected) in [
+            (
+                // Functions see the names around their declaration, not around their call.
+                "fn inner = () => { return secret }
+fn outer = () => {
+  const secret = 1
+  return inner()
+}
+const a = outer()",
+                r#"undefined value: KclErrorDetails { source_ranges: [SourceRange([26, 32])], message: "memory item key `secret` is not defined" }"#,
+            ),
+            (
+                "fn f = () => {
+  const y = 1
+  const y = 2
+  return y
+}
+const a = f()",
+                r#"value already defined: KclErrorDetails { source_ranges: [SourceRange([41, 42])], message: "Cannot redefine y" }"#,
+            ),
+        ] {
+            let result = parse_execute(code).await;
+            assert_eq!(result.unwrap_err().to_string(), expected, "wrong error for {code}");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_declared_types() {
+        let ast = r#"fn area = (w: number, h?: number, sizes?: number[]) => number { return w * 2 }
+fn grow = (sg: sketch_group) => sketch_group { return sg }
+fn pick = (opts: {w: number}) => { return opts.w }
+const a = area(3)
+const b = area(4, 5, [1, 2])
+const c = pick({ w: 1, extra: true })
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 0], %)
+  |> grow(%)"#;
+        let memory = parse_execute(ast).await.unwrap();
+        assert_eq!(
+            serde_json::json!(6.0),
+            memory
+                .get("a", SourceRange::default())
+                .unwrap()
+                .get_json_value()
+                .unwrap()
+        );
+        assert_eq!(
+            serde_json::json!(1),
+            memory
+                .get("c", SourceRange::default())
+                .unwrap()
+                .get_json_value()
+                .unwrap()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_declared_types_errors() {
+        for (code, expected) in [
+            (
+                r#"fn area = (w: number) => { return w }
+const a = area("wide")"#,
+                r#"type: KclErrorDetails { source_ranges: [SourceRange([53, 59]), SourceRange([11, 12])], message: "The parameter `w` was declared as `number`, but this argument is a `string`" }"#,
+            ),
+            (
+                r#"fn total = (sizes: number[]) => { return sizes[0] }
+const a = total([1, "2"])"#,
+                r#"type: KclErrorDetails { source_ranges: [SourceRange([68, 76]), SourceRange([12, 17])], message: "The parameter `sizes` was declared as `number[]`, but this argument is a `array`" }"#,
+            ),
+            (
+                r#"fn pick = (opts: {w: number}) => { return opts.w }
+const a = pick({ h: 1 })"#,
+                r#"type: KclErrorDetails { source_ranges: [SourceRange([66, 74]), SourceRange([11, 15])], message: "The parameter `opts` was declared as `{w: number}`, but this argument is a `object`" }"#,
+            ),
+            (
+                r#"fn name = () => string { return 1 + 2 }
+const a = name()"#,
+                r#"type: KclErrorDetails { source_ranges: [SourceRange([32, 37])], message: "This function was declared to return `string`, but it returned a `number`" }"#,
+            ),
+        ] {
+            let result = parse_execute(code).await;
+            assert_eq!(result.unwrap_err().to_string(), expected, "wrong error for {code}");
+        }
+    }
+
     #[test]
     fn test_assign_args_to_params() {
         // Set up a little framework for this test.
@@ -2034,7 +2881,8 @@ const bracket = startSketchOn('XY')
                 },
                 return_type: None,
             };
-            let actual = assign_args_to_params(func_expr, args, ProgramMemory::new());
+            let mut memory = ProgramMemory::new();
+            let actual = assign_args_to_params(func_expr, args, &mut memory).map(|()| memory);
             assert_eq!(
                 actual, expected,
                 "failed test '{test_name}':\ngot {actual:?}\nbut expected\n{expected:?}"
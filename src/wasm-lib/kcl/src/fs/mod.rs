@@ -14,6 +14,21 @@ use anyhow::Result;
 #[cfg(not(test))]
 pub use wasm::FileManager;
 
+/// The file at the root of a project, which holds its settings.
+pub const PROJECT_SETTINGS_FILE_NAME: &str = "project.toml";
+
+/// The directory that imports in the KCL file at `path` are relative to: the root of its project,
+/// which is the closest directory above it with a `project.toml`, or else the file's own directory.
+pub async fn project_directory(fs: &impl FileSystem, path: &std::path::Path) -> Option<std::path::PathBuf> {
+    for dir in path.ancestors().skip(1) {
+        let settings_file = dir.join(PROJECT_SETTINGS_FILE_NAME);
+        if fs.exists(&settings_file, Default::default()).await.unwrap_or(false) {
+            return Some(dir.to_path_buf());
+        }
+    }
+    path.parent().map(|dir| dir.to_path_buf())
+}
+
 #[async_trait::async_trait]
 pub trait FileSystem: Clone {
     /// Read a file from the local file system.
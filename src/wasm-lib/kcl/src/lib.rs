@@ -5,12 +5,15 @@
 #![recursion_limit = "1024"]
 
 pub mod ast;
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+pub mod cli;
 pub mod coredump;
 pub mod docs;
 pub mod engine;
 pub mod errors;
 pub mod executor;
 pub mod fs;
+pub mod lint;
 pub mod lsp;
 pub mod parser;
 pub mod settings;
@@ -0,0 +1,252 @@
+//! Checks that a program follows the conventions KCL code is expected to follow.
+//!
+//! Each [`Rule`] can be turned off, or made more or less serious, in the project's
+//! [`LintSettings`]. Rules can suggest a change which fixes what they found.
+
+mod rules;
+
+use parse_display::{Display, FromStr};
+use serde::{Deserialize, Serialize};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, TextEdit};
+
+use crate::{
+    ast::types::Program,
+    executor::SourceRange,
+    settings::types::{LintSettings, LintSeverity},
+    std::StdLib,
+};
+
+/// The `source` of the LSP diagnostics for lints, so they can be told apart from errors.
+pub const DIAGNOSTIC_SOURCE: &str = "kcl-lint";
+
+/// A convention checked by the linter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, FromStr)]
+#[display(style = "snake_case")]
+pub enum Rule {
+    /// Numbers used in an expression instead of being bound to a named variable.
+    MagicNumber,
+    /// Variable, function and parameter names which aren't camelCase.
+    CamelCase,
+    /// Sketches started with `startSketchAt` instead of `startSketchOn`.
+    StartSketchAt,
+    /// Pipe expressions nested inside other pipe expressions.
+    NestedPipe,
+    /// Tags on segments which are never used.
+    UnusedTag,
+}
+
+impl Rule {
+    /// Every rule, in the order they're checked.
+    pub const ALL: [Rule; 5] = [
+        Rule::MagicNumber,
+        Rule::CamelCase,
+        Rule::StartSketchAt,
+        Rule::NestedPipe,
+        Rule::UnusedTag,
+    ];
+
+    /// How seriously the project treats what this rule finds.
+    pub fn severity(&self, settings: &LintSettings) -> LintSeverity {
+        match self {
+            Rule::MagicNumber => settings.magic_number,
+            Rule::CamelCase => settings.camel_case,
+            Rule::StartSketchAt => settings.start_sketch_at,
+            Rule::NestedPipe => settings.nested_pipe,
+            Rule::UnusedTag => settings.unused_tag,
+        }
+    }
+
+    fn check(&self, program: &Program, stdlib: &StdLib) -> Vec<Violation> {
+        match self {
+            Rule::MagicNumber => rules::magic_number(program),
+            Rule::CamelCase => rules::camel_case(program),
+            Rule::StartSketchAt => rules::start_sketch_at(program),
+            Rule::NestedPipe => rules::nested_pipe(program),
+            Rule::UnusedTag => rules::unused_tag(program, stdlib),
+        }
+    }
+}
+
+/// A change which fixes what a rule found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    /// What the change does, e.g. "Remove the unused tag".
+    pub title: String,
+    /// The parts of the program to replace, and what to replace each of them with.
+    pub edits: Vec<(SourceRange, String)>,
+}
+
+impl Suggestion {
+    /// The suggestion as the `data` of an LSP diagnostic, which code actions turn back into a workspace edit.
+    pub fn to_lsp_data(&self, code: &str) -> serde_json::Value {
+        let suggestion = LspSuggestion {
+            title: self.title.clone(),
+            edits: self
+                .edits
+                .iter()
+                .map(|(range, new_text)| TextEdit {
+                    range: range.to_lsp_range(code),
+                    new_text: new_text.clone(),
+                })
+                .collect(),
+        };
+        // Serializing a struct of strings and positions can't fail.
+        serde_json::to_value(suggestion).unwrap_or_default()
+    }
+}
+
+/// Something in a program which breaks one of the rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovered {
+    pub rule: Rule,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub source_range: SourceRange,
+    pub suggestion: Option<Suggestion>,
+}
+
+/// The suggestion attached to a lint's or an analysis finding's LSP diagnostic, as its `data`.
+/// Code actions turn it back into a workspace edit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LspSuggestion {
+    pub title: String,
+    pub edits: Vec<TextEdit>,
+}
+
+impl Discovered {
+    pub fn to_lsp_diagnostic(&self, code: &str) -> Diagnostic {
+        let data = self.suggestion.as_ref().map(|suggestion| suggestion.to_lsp_data(code));
+
+        Diagnostic {
+            range: self.source_range.to_lsp_range(code),
+            severity: Some(match self.severity {
+                LintSeverity::Off | LintSeverity::Hint => DiagnosticSeverity::HINT,
+                LintSeverity::Warning => DiagnosticSeverity::WARNING,
+                LintSeverity::Error => DiagnosticSeverity::ERROR,
+            }),
+            code: Some(NumberOrString::String(self.rule.to_string())),
+            code_description: None,
+            source: Some(DIAGNOSTIC_SOURCE.to_string()),
+            message: self.message.clone(),
+            related_information: None,
+            tags: None,
+            data,
+        }
+    }
+}
+
+/// What a rule found, before the project's settings are applied to it.
+#[derive(Debug, Clone, PartialEq)]
+struct Violation {
+    message: String,
+    source_range: SourceRange,
+    suggestion: Option<Suggestion>,
+}
+
+impl Violation {
+    fn new(message: String, source_range: SourceRange) -> Self {
+        Self {
+            message,
+            source_range,
+            suggestion: None,
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: Option<Suggestion>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+}
+
+/// Check the program against every rule the settings don't turn off.
+/// What's found is sorted by where it is in the program.
+pub fn lint(program: &Program, stdlib: &StdLib, settings: &LintSettings) -> Vec<Discovered> {
+    let mut discovered: Vec<Discovered> = Rule::ALL
+        .iter()
+        .filter(|rule| rule.severity(settings) != LintSeverity::Off)
+        .flat_map(|rule| {
+            rule.check(program, stdlib).into_iter().map(|violation| Discovered {
+                rule: *rule,
+                severity: rule.severity(settings),
+                message: violation.message,
+                source_range: violation.source_range,
+                suggestion: violation.suggestion,
+            })
+        })
+        .collect();
+    discovered.sort_by_key(|discovered| discovered.source_range.start());
+    discovered
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn parse(code: &str) -> Program {
+        let tokens = crate::token::lexer(code).unwrap();
+        crate::parser::Parser::new(tokens).ast().unwrap()
+    }
+
+    #[test]
+    fn settings_choose_rules_and_severities() {
+        let program = parse("const my_part = startSketchAt([0, 5])");
+        let stdlib = StdLib::new();
+
+        let discovered = lint(&program, &stdlib, &LintSettings::default());
+        assert_eq!(
+            discovered
+                .iter()
+                .map(|discovered| (discovered.rule, discovered.severity))
+                .collect::<Vec<_>>(),
+            vec![
+                (Rule::CamelCase, LintSeverity::Warning),
+                (Rule::StartSketchAt, LintSeverity::Warning)
+            ]
+        );
+
+        let settings = LintSettings {
+            magic_number: LintSeverity::Hint,
+            camel_case: LintSeverity::Off,
+            start_sketch_at: LintSeverity::Error,
+            ..Default::default()
+        };
+        let discovered = lint(&program, &stdlib, &settings);
+        assert_eq!(
+            discovered
+                .iter()
+                .map(|discovered| (discovered.rule, discovered.severity))
+                .collect::<Vec<_>>(),
+            vec![
+                (Rule::StartSketchAt, LintSeverity::Error),
+                (Rule::MagicNumber, LintSeverity::Hint)
+            ]
+        );
+    }
+
+    #[test]
+    fn suggestions_are_attached_to_lsp_diagnostics() {
+        let code = r#"startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([1, 1], %, "unused")"#;
+        let program = parse(code);
+
+        let discovered = lint(&program, &StdLib::new(), &LintSettings::default());
+        assert_eq!(discovered.len(), 1);
+        let diagnostic = discovered[0].to_lsp_diagnostic(code);
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostic.code, Some(NumberOrString::String("unused_tag".to_string())));
+        assert_eq!(diagnostic.source.as_deref(), Some(DIAGNOSTIC_SOURCE));
+
+        let suggestion: LspSuggestion = serde_json::from_value(diagnostic.data.unwrap()).unwrap();
+        assert_eq!(suggestion.title, "Remove the unused tag");
+        assert_eq!(
+            suggestion.edits,
+            vec![TextEdit {
+                range: SourceRange([code.len() - 11, code.len() - 1]).to_lsp_range(code),
+                new_text: String::new(),
+            }]
+        );
+    }
+}
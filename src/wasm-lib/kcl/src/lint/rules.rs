@@ -0,0 +1,470 @@
+//! The checks behind each lint rule.
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Suggestion, Violation};
+use crate::{
+    ast::{
+        types::{CallExpression, Identifier, LiteralValue, Program, UnaryOperator, Value},
+        walk::{walk, Node},
+    },
+    executor::SourceRange,
+    std::StdLib,
+};
+
+/// Numbers which are clear enough without a name.
+fn is_obvious_number(value: &LiteralValue) -> bool {
+    match value {
+        LiteralValue::IInteger(n) => *n == 0 || *n == 1,
+        LiteralValue::Fractional(n) => *n == 0.0 || *n == 1.0,
+        LiteralValue::String(_) | LiteralValue::Bool(_) => true,
+    }
+}
+
+pub(super) fn magic_number(program: &Program) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    walk(program, &mut |node, ancestors| {
+        let Node::Literal(literal) = node else {
+            return;
+        };
+        if is_obvious_number(&literal.value) {
+            return;
+        }
+
+        // A number is named if it's the whole value of a variable, or part of an array, object or
+        // negation which is the whole value of a variable.
+        // Indexes into arrays are fine too.
+        for ancestor in ancestors.iter().rev() {
+            match ancestor {
+                Node::ArrayExpression(_) | Node::ObjectExpression(_) | Node::ObjectProperty(_) => continue,
+                Node::UnaryExpression(expression) if expression.operator == UnaryOperator::Neg => continue,
+                Node::VariableDeclarator(_) | Node::MemberExpression(_) => return,
+                _ => break,
+            }
+        }
+
+        violations.push(Violation::new(
+            format!(
+                "`{}` is a magic number, bind it to a variable which says what it means",
+                literal.raw
+            ),
+            literal.into(),
+        ));
+    });
+    violations
+}
+
+/// If the name isn't camelCase, what it would be if it was.
+/// Leading underscores are kept, and SCREAMING_SNAKE_CASE is fine for constants.
+fn camel_case_name(name: &str) -> Option<String> {
+    let trimmed = name.trim_start_matches('_');
+    let is_screaming = trimmed.chars().any(|c| c.is_ascii_uppercase())
+        && trimmed
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if trimmed.is_empty() || is_screaming {
+        return None;
+    }
+    if !trimmed.contains('_') && !trimmed.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let mut camel = name[..name.len() - trimmed.len()].to_string();
+    for (i, word) in trimmed.split('_').filter(|word| !word.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        let Some(first) = chars.next() else {
+            continue;
+        };
+        if i == 0 {
+            camel.push(first.to_ascii_lowercase());
+        } else {
+            camel.push(first.to_ascii_uppercase());
+        }
+        camel.extend(chars);
+    }
+    Some(camel)
+}
+
+/// Rename a declaration and everything which refers to it, if it's one the AST knows how to rename.
+fn rename_suggestion(
+    program: &Program,
+    id: &Identifier,
+    new_name: &str,
+    used_names: &HashSet<&str>,
+) -> Option<Suggestion> {
+    // Don't suggest a name which is already used, or one which is a keyword.
+    if used_names.contains(new_name) {
+        return None;
+    }
+    // Keywords are only recognised when something follows them.
+    let tokens = crate::token::lexer(&format!("{new_name} ")).ok()?;
+    if !matches!(tokens.first(), Some(token) if token.token_type == crate::token::TokenType::Word && token.value == new_name)
+    {
+        return None;
+    }
+
+    let mut renamed = program.clone();
+    renamed.rename_symbol(new_name, id.start);
+    // Renaming keeps the shape of the AST, so the identifiers line up one to one.
+    // Only the ones which changed are edited, which leaves the rest of the code as the user wrote it.
+    let before = identifiers(program);
+    let after = identifiers(&renamed);
+    if before.len() != after.len() {
+        return None;
+    }
+    let edits: Vec<_> = before
+        .into_iter()
+        .zip(after)
+        .filter(|(before, after)| before.name != after.name)
+        .map(|(before, after)| (SourceRange::from(before), after.name.clone()))
+        .collect();
+    if edits.is_empty() {
+        return None;
+    }
+    Some(Suggestion {
+        title: format!("Rename `{}` to `{new_name}`", id.name),
+        edits,
+    })
+}
+
+/// Every identifier in the program, in source order.
+fn identifiers(program: &Program) -> Vec<&Identifier> {
+    let mut identifiers = Vec::new();
+    walk(program, &mut |node, _| {
+        if let Node::Identifier(identifier) = node {
+            identifiers.push(identifier);
+        }
+    });
+    identifiers
+}
+
+pub(super) fn camel_case(program: &Program) -> Vec<Violation> {
+    let mut declared = Vec::new();
+    let mut used_names = HashSet::new();
+    walk(program, &mut |node, ancestors| {
+        let Node::Identifier(identifier) = node else {
+            return;
+        };
+        used_names.insert(identifier.name.as_str());
+        // Names from imports belong to the other file, so only aliases are checked.
+        let is_declaration = match ancestors.last() {
+            Some(Node::VariableDeclarator(declarator)) => std::ptr::eq(&declarator.id, identifier),
+            Some(Node::Parameter(_)) => true,
+            Some(Node::ForStatement(statement)) => std::ptr::eq(&statement.variable, identifier),
+            Some(Node::ImportItem(item)) => item.alias.as_ref().is_some_and(|alias| std::ptr::eq(alias, identifier)),
+            _ => false,
+        };
+        if is_declaration {
+            declared.push(identifier);
+        }
+    });
+
+    declared
+        .into_iter()
+        .filter_map(|id| {
+            let new_name = camel_case_name(&id.name)?;
+            Some(
+                Violation::new(
+                    format!("`{}` should be camelCase, e.g. `{new_name}`", id.name),
+                    id.into(),
+                )
+                .with_suggestion(rename_suggestion(program, id, &new_name, &used_names)),
+            )
+        })
+        .collect()
+}
+
+pub(super) fn start_sketch_at(program: &Program) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    walk(program, &mut |node, ancestors| {
+        let Node::CallExpression(call) = node else {
+            return;
+        };
+        if call.callee.name != "startSketchAt" {
+            return;
+        }
+
+        // The replacement is a pipe expression, which can only go where a whole expression can.
+        let can_be_pipe = match ancestors.last() {
+            Some(Node::PipeExpression(pipe)) => pipe.body.first().map(SourceRange::from) == Some(call.into()),
+            Some(Node::VariableDeclarator(_) | Node::ExpressionStatement(_) | Node::ReturnStatement(_)) => true,
+            _ => false,
+        };
+        let suggestion = match call.arguments.as_slice() {
+            [point] if can_be_pipe => Some(Suggestion {
+                title: "Use `startSketchOn` and `startProfileAt`".to_string(),
+                edits: vec![
+                    (
+                        SourceRange([call.start, point.start()]),
+                        "startSketchOn('XY') |> startProfileAt(".to_string(),
+                    ),
+                    (SourceRange([point.end(), call.end]), ", %)".to_string()),
+                ],
+            }),
+            _ => None,
+        };
+
+        violations.push(
+            Violation::new(
+                "`startSketchAt` is deprecated, use `startSketchOn` and `startProfileAt` so the plane is explicit"
+                    .to_string(),
+                call.into(),
+            )
+            .with_suggestion(suggestion),
+        );
+    });
+    violations
+}
+
+pub(super) fn nested_pipe(program: &Program) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    walk(program, &mut |node, ancestors| {
+        let Node::PipeExpression(pipe) = node else {
+            return;
+        };
+
+        // A function's body starts afresh, even if the function is in a pipe.
+        let is_nested = ancestors
+            .iter()
+            .rev()
+            .take_while(|ancestor| !matches!(ancestor, Node::FunctionExpression(_)))
+            .any(|ancestor| matches!(ancestor, Node::PipeExpression(_)));
+        if is_nested {
+            violations.push(Violation::new(
+                "Pipe expressions shouldn't be nested inside other pipe expressions, bind this one to a variable"
+                    .to_string(),
+                pipe.into(),
+            ));
+        }
+    });
+    violations
+}
+
+pub(super) fn unused_tag(program: &Program, stdlib: &StdLib) -> Vec<Violation> {
+    let mut tag_indexes: HashMap<&str, Option<usize>> = HashMap::new();
+    let mut tags: Vec<(&CallExpression, usize)> = Vec::new();
+    let mut strings: HashMap<&str, usize> = HashMap::new();
+    walk(program, &mut |node, ancestors| match node {
+        Node::CallExpression(call) => {
+            let index = *tag_indexes
+                .entry(call.callee.name.as_str())
+                .or_insert_with(|| stdlib.tag_arg_index(&call.callee.name));
+            if let Some(index) = index {
+                if matches!(call.arguments.get(index), Some(Value::Literal(_))) {
+                    tags.push((call, index));
+                }
+            }
+        }
+        Node::Literal(literal) => {
+            if let LiteralValue::String(s) = &literal.value {
+                *strings.entry(s.as_str()).or_default() += 1;
+            }
+        }
+        // Tags can also be looked up as properties, e.g. `part.value.seg01`.
+        Node::Identifier(identifier) => {
+            if let Some(Node::MemberExpression(member)) = ancestors.last() {
+                if SourceRange::from(&member.property) == identifier.into() {
+                    *strings.entry(identifier.name.as_str()).or_default() += 1;
+                }
+            }
+        }
+        _ => {}
+    });
+
+    tags.into_iter()
+        .filter_map(|(call, index)| {
+            let Value::Literal(tag) = &call.arguments[index] else {
+                return None;
+            };
+            let LiteralValue::String(name) = &tag.value else {
+                return None;
+            };
+            // The tag itself is one use of the string.
+            if strings.get(name.as_str()).copied().unwrap_or_default() > 1 {
+                return None;
+            }
+
+            let suggestion = index.checked_sub(1).map(|previous| Suggestion {
+                title: "Remove the unused tag".to_string(),
+                edits: vec![(SourceRange([call.arguments[previous].end(), tag.end]), String::new())],
+            });
+            Some(
+                Violation::new(format!("The tag `{name}` is never used"), tag.as_ref().into())
+                    .with_suggestion(suggestion),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn parse(code: &str) -> Program {
+        let tokens = crate::token::lexer(code).unwrap();
+        crate::parser::Parser::new(tokens).ast().unwrap()
+    }
+
+    fn messages(violations: Vec<Violation>) -> Vec<String> {
+        violations.into_iter().map(|violation| violation.message).collect()
+    }
+
+    #[test]
+    fn magic_numbers() {
+        let program = parse(
+            r#"const width = 10
+const origin = [0, -2.5]
+const size = {w: 3, h: [4]}
+const area = width * 12
+const first = origin[1]
+const part = startSketchOn('XY')
+  |> startProfileAt(origin, %)
+  |> line([width, 1], %)
+  |> line([0, 7], %)"#,
+        );
+        assert_eq!(
+            messages(magic_number(&program)),
+            vec![
+                "`12` is a magic number, bind it to a variable which says what it means",
+                "`7` is a magic number, bind it to a variable which says what it means",
+            ]
+        );
+    }
+
+    #[test]
+    fn camel_case_names() {
+        assert_eq!(camel_case_name("width"), None);
+        assert_eq!(camel_case_name("myWidth2"), None);
+        assert_eq!(camel_case_name("_unused"), None);
+        assert_eq!(camel_case_name("HALF_TURN"), None);
+        assert_eq!(camel_case_name("my_width"), Some("myWidth".to_string()));
+        assert_eq!(camel_case_name("_my_width"), Some("_myWidth".to_string()));
+        assert_eq!(camel_case_name("Width"), Some("width".to_string()));
+        assert_eq!(camel_case_name("part__one_2"), Some("partOne2".to_string()));
+
+        let program = parse(
+            r#"const part_width = 2
+fn double_it = (some_value) => {
+  return some_value * part_width
+}
+for My_index in [0..2] {
+  const Total = double_it(My_index)
+}"#,
+        );
+        let violations = camel_case(&program);
+        assert_eq!(
+            messages(violations.clone()),
+            vec![
+                "`part_width` should be camelCase, e.g. `partWidth`",
+                "`double_it` should be camelCase, e.g. `doubleIt`",
+                "`some_value` should be camelCase, e.g. `someValue`",
+                "`My_index` should be camelCase, e.g. `myIndex`",
+                "`Total` should be camelCase, e.g. `total`",
+            ]
+        );
+
+        // Top level names and function parameters can be renamed everywhere they're used,
+        // with one edit for each use.
+        let suggestion = violations[0].suggestion.clone().unwrap();
+        assert_eq!(suggestion.title, "Rename `part_width` to `partWidth`");
+        assert_eq!(
+            suggestion.edits,
+            vec![
+                (SourceRange([6, 16]), "partWidth".to_string()),
+                (SourceRange([76, 86]), "partWidth".to_string()),
+            ]
+        );
+        let suggestion = violations[2].suggestion.clone().unwrap();
+        assert_eq!(
+            suggestion.edits,
+            vec![
+                (SourceRange([37, 47]), "someValue".to_string()),
+                (SourceRange([63, 73]), "someValue".to_string()),
+            ]
+        );
+        assert_eq!(violations[3].suggestion, None);
+        assert_eq!(violations[4].suggestion, None);
+    }
+
+    #[test]
+    fn camel_case_suggestions_avoid_existing_names_and_keywords() {
+        let program = parse(
+            r#"const my_width = 2
+const myWidth = 3
+const If = 4"#,
+        );
+        let violations = camel_case(&program);
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().all(|violation| violation.suggestion.is_none()));
+    }
+
+    #[test]
+    fn start_sketch_at_is_replaced() {
+        let code = r#"const part = startSketchAt([0, 0])
+  |> line([1, 1], %)
+const other = startSketchAt([2, 2])
+const nested = f(startSketchAt([3, 3]))"#;
+        let program = parse(code);
+        let violations = start_sketch_at(&program);
+        assert_eq!(violations.len(), 3);
+
+        let mut fixed = code.to_string();
+        for violation in violations[..2].iter().rev() {
+            for (range, new_text) in violation.suggestion.as_ref().unwrap().edits.iter().rev() {
+                fixed.replace_range(range.start()..range.end(), new_text);
+            }
+        }
+        assert_eq!(
+            fixed,
+            r#"const part = startSketchOn('XY') |> startProfileAt([0, 0], %)
+  |> line([1, 1], %)
+const other = startSketchOn('XY') |> startProfileAt([2, 2], %)
+const nested = f(startSketchAt([3, 3]))"#
+        );
+        assert_eq!(violations[2].suggestion, None);
+    }
+
+    #[test]
+    fn nested_pipes() {
+        let program = parse(
+            r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([startSketchOn('XY') |> startProfileAt([0, 0], %) |> segEndX(%), 1], %)
+fn shape = (s) => {
+  return s |> line([1, 1], %)
+}
+const other = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> shape(%)"#,
+        );
+        let violations = nested_pipe(&program);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].source_range, SourceRange([75, 137]));
+    }
+
+    #[test]
+    fn unused_tags() {
+        let code = r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %, "start")
+  |> line([1, 0], %, "bottom")
+  |> line([0, 1], %, "side")
+  |> lineTo([0, segEndY("side", %)], %, 'top')
+  |> close(%, "closing")
+  |> extrude(2, %)
+  |> fillet({radius: 0.1, tags: [getOppositeEdge("bottom", %)]}, %)
+const face = startSketchOn(part, "closing")"#;
+        let program = parse(code);
+        let violations = unused_tag(&program, &StdLib::new());
+        assert_eq!(
+            messages(violations.clone()),
+            vec!["The tag `start` is never used", "The tag `top` is never used"]
+        );
+
+        let edits = &violations[1].suggestion.as_ref().unwrap().edits;
+        let (range, new_text) = &edits[0];
+        assert_eq!(&code[range.start()..range.end()], ", 'top'");
+        assert_eq!(new_text, "");
+    }
+}
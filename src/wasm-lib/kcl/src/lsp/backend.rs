@@ -3,6 +3,7 @@
 use std::sync::Arc;
 
 use anyhow::Result;
+use ropey::Rope;
 use tokio::sync::RwLock;
 use tower_lsp::lsp_types::{
     CreateFilesParams, DeleteFilesParams, DidChangeConfigurationParams, DidChangeTextDocumentParams,
@@ -13,7 +14,7 @@ use tower_lsp::lsp_types::{
 
 use crate::{
     fs::FileSystem,
-    lsp::safemap::SafeMap,
+    lsp::{safemap::SafeMap, util::utf16_position_to_char},
     thread::{JoinHandle, Thread},
 };
 
@@ -104,7 +105,16 @@ where
         if let Some(tower_lsp::lsp_types::DocumentDiagnosticReport::Full(diagnostics)) =
             self.current_diagnostics_map().get(uri).await
         {
-            !diagnostics.full_document_diagnostic_report.items.is_empty()
+            // Warnings and lints don't stop the code from executing, so they don't need it to be
+            // checked again.
+            diagnostics
+                .full_document_diagnostic_report
+                .items
+                .iter()
+                .any(|diagnostic| {
+                    diagnostic.severity == Some(tower_lsp::lsp_types::DiagnosticSeverity::ERROR)
+                        && diagnostic.source.as_deref() != Some(crate::lint::DIAGNOSTIC_SOURCE)
+                })
         } else {
             false
         }
@@ -127,11 +137,12 @@ where
             current_handle.cancel();
         }
 
+        // Update the code straight away, since the next change is applied to it.
+        self.insert_code_map(params.uri.to_string(), params.text.as_bytes().to_vec())
+            .await;
+
         let cloned = self.clone();
         let task = JoinHandle::new(async move {
-            cloned
-                .insert_code_map(params.uri.to_string(), params.text.as_bytes().to_vec())
-                .await;
             cloned.inner_on_change(params, false).await;
             cloned.set_current_handle(None).await;
         });
@@ -286,10 +297,42 @@ where
         self.on_change(new_params).await;
     }
 
-    async fn do_did_change(&self, mut params: DidChangeTextDocumentParams) {
+    async fn do_did_change(&self, params: DidChangeTextDocumentParams) {
+        // Apply the changes to the code we have, in order. A change without a range is the whole
+        // document.
+        let current_code = self
+            .code_map()
+            .get(params.text_document.uri.as_str())
+            .await
+            .unwrap_or_default();
+        let mut rope = Rope::from_str(&String::from_utf8_lossy(&current_code));
+        for change in params.content_changes {
+            let Some(range) = change.range else {
+                rope = Rope::from_str(&change.text);
+                continue;
+            };
+            match (
+                utf16_position_to_char(range.start, &rope),
+                utf16_position_to_char(range.end, &rope),
+            ) {
+                (Some(start), Some(end)) if start <= end && end <= rope.len_chars() => {
+                    rope.remove(start..end);
+                    rope.insert(start, &change.text);
+                }
+                _ => {
+                    self.client()
+                        .log_message(
+                            MessageType::WARNING,
+                            format!("ignoring change outside of the document: {:?}", range),
+                        )
+                        .await;
+                }
+            }
+        }
+
         let new_params = TextDocumentItem {
             uri: params.text_document.uri,
-            text: std::mem::take(&mut params.content_changes[0].text),
+            text: rope.to_string(),
             version: params.text_document.version,
             language_id: Default::default(),
         };
@@ -0,0 +1,459 @@
+//! Completions for what the code around the cursor expects: the keys of an object passed to a stdlib
+//! function, the values of a string argument, the tags of segments, and the members of an object.
+
+use schemars::schema::{Schema, SchemaObject, SingleOrVec};
+use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind, Documentation, MarkupContent, MarkupKind};
+
+use crate::{
+    ast::{
+        types::{LiteralValue, ObjectExpression, Program, Value},
+        walk::{is_top_level, walk, Node},
+    },
+    docs::{completion_item_from_enum_schema, get_description_string_from_schema, get_type_string_from_schema},
+    executor::{MemoryItem, ProgramMemory, SourceRange},
+    parser::incremental::{reparse, Parsed},
+    std::StdLib,
+};
+
+/// Completions for what the code at `pos` expects, or `None` if it could be any expression, in which
+/// case every function and variable is a completion.
+/// `previous` is the code last parsed without errors, and `memory` is what the last execution left behind.
+pub fn completions(
+    previous: Option<&Parsed>,
+    stdlib: &StdLib,
+    code: &str,
+    memory: Option<&ProgramMemory>,
+    pos: usize,
+) -> Option<Vec<CompletionItem>> {
+    let before = code.get(..pos)?;
+    let word_start = before.trim_end_matches(is_identifier_char).len();
+
+    // The object is whatever's before the `.`, and the member is still being typed.
+    if let Some(path) = before[..word_start].strip_suffix('.').and_then(member_path) {
+        return Some(member_completions(
+            previous.map(|previous| &previous.program),
+            memory,
+            &path,
+        ));
+    }
+
+    // The word being typed usually doesn't parse yet, e.g. an object key without its value, so
+    // look at the code without it.
+    let code = format!("{}{}", &before[..word_start], &code[pos..]);
+    let pos = word_start;
+    let parsed = previous
+        .and_then(|previous| reparse(previous, &code))
+        .or_else(|| Parsed::new(&code).ok())?;
+    let program = &parsed.program;
+
+    let mut context = None;
+    walk(program, &mut |node, ancestors| {
+        let range = node.source_range();
+        // Strictly inside the node, i.e. between its braces or quotes.
+        if range.start() >= pos || pos >= range.end() {
+            return;
+        }
+        match node {
+            Node::ObjectExpression(object)
+                if !object.properties.iter().any(|property| {
+                    let value = SourceRange::from(&property.value);
+                    value.start() <= pos && pos <= value.end()
+                }) =>
+            {
+                context = Some(Context::ObjectKey(object, ancestors.to_vec()));
+            }
+            Node::Literal(literal) if matches!(literal.value, LiteralValue::String(_)) => {
+                context = Some(Context::String(node, ancestors.to_vec()));
+            }
+            _ => {}
+        }
+    });
+
+    match context? {
+        Context::ObjectKey(object, ancestors) => {
+            let Some(expected) = expected(stdlib, &ancestors, Node::ObjectExpression(object)) else {
+                return Some(Vec::new());
+            };
+            Some(key_completions(&expected.schema, object))
+        }
+        Context::String(node, ancestors) => {
+            let Some(expected) = expected(stdlib, &ancestors, node) else {
+                return Some(Vec::new());
+            };
+            let mut completions = enum_completions(&expected.schema);
+            if is_tag_name(&expected.name) && !expected.declares_tag {
+                completions.extend(tag_completions(program, stdlib));
+            }
+            Some(completions)
+        }
+    }
+}
+
+/// Where the cursor is, among the places which expect something in particular.
+enum Context<'a> {
+    /// Where a key of the object goes.
+    ObjectKey(&'a ObjectExpression, Vec<Node<'a>>),
+    /// Inside a string.
+    String(Node<'a>, Vec<Node<'a>>),
+}
+
+/// What a stdlib function expects in some part of one of its arguments.
+struct Expected {
+    /// The name of the argument, or of the object property it's the value of.
+    name: String,
+    schema: Schema,
+    /// If it's the argument which gives a tag to the segment the function makes.
+    declares_tag: bool,
+}
+
+/// What's expected where `node` is, if it's part of an argument of a call to a stdlib function.
+fn expected(stdlib: &StdLib, ancestors: &[Node], node: Node) -> Option<Expected> {
+    let call_index = ancestors
+        .iter()
+        .rposition(|ancestor| matches!(ancestor, Node::CallExpression(_)))?;
+    let Node::CallExpression(call) = ancestors[call_index] else {
+        return None;
+    };
+    let func = stdlib
+        .get(&call.callee.name)
+        .or_else(|| stdlib.get_kcl(&call.callee.name).map(|func| func.std_lib()))?;
+
+    // The nodes from the argument down to this one.
+    let mut path: Vec<Node> = ancestors[call_index + 1..].to_vec();
+    path.push(node);
+    let argument = path[0].source_range();
+    let index = call
+        .arguments
+        .iter()
+        .position(|value| SourceRange::from(value) == argument)?;
+    let arg = func.args().into_iter().nth(index)?;
+
+    let mut expected = Expected {
+        name: arg.name,
+        schema: arg.schema,
+        declares_tag: stdlib.tag_arg_index(&call.callee.name) == Some(index),
+    };
+    for window in path.windows(2) {
+        match window[0] {
+            Node::ObjectExpression(_) => {}
+            Node::ObjectProperty(property) => {
+                expected.schema = property_schema(&expected.schema, &property.key.name)?;
+                expected.name = property.key.name.clone();
+            }
+            Node::ArrayExpression(_) => expected.schema = items_schema(&expected.schema)?,
+            _ => return None,
+        }
+    }
+    Some(expected)
+}
+
+/// The object keys the schema allows which aren't in the object yet.
+fn key_completions(schema: &Schema, object: &ObjectExpression) -> Vec<CompletionItem> {
+    let mut completions: Vec<CompletionItem> = Vec::new();
+    for variant in variants(schema) {
+        let Some(object_validation) = &variant.object else {
+            continue;
+        };
+        for (key, property) in &object_validation.properties {
+            if object.properties.iter().any(|existing| &existing.key.name == key)
+                || completions.iter().any(|completion| &completion.label == key)
+            {
+                continue;
+            }
+            completions.push(completion_item(
+                key,
+                CompletionItemKind::PROPERTY,
+                get_type_string_from_schema(property).ok().map(|(type_, _)| type_),
+                get_description_string_from_schema(property),
+            ));
+        }
+    }
+    completions
+}
+
+/// The strings the schema allows, like the names of the standard planes.
+fn enum_completions(schema: &Schema) -> Vec<CompletionItem> {
+    let mut completions = Vec::new();
+    for variant in variants(schema) {
+        let Some(enum_values) = &variant.enum_values else {
+            continue;
+        };
+        if enum_values.len() == 1 {
+            let schema = Schema::Object(variant.clone());
+            if let Ok(completion) = completion_item_from_enum_schema(&schema, CompletionItemKind::ENUM_MEMBER) {
+                completions.push(completion);
+            }
+            continue;
+        }
+        for value in enum_values {
+            if let serde_json::Value::String(value) = value {
+                completions.push(completion_item(value, CompletionItemKind::ENUM_MEMBER, None, None));
+            }
+        }
+    }
+    completions
+}
+
+/// The tags given to segments anywhere in the program.
+fn tag_completions(program: &Program, stdlib: &StdLib) -> Vec<CompletionItem> {
+    let mut completions: Vec<CompletionItem> = Vec::new();
+    walk(program, &mut |node, _| {
+        let Node::CallExpression(call) = node else {
+            return;
+        };
+        let Some(index) = stdlib.tag_arg_index(&call.callee.name) else {
+            return;
+        };
+        let Some(Value::Literal(literal)) = call.arguments.get(index) else {
+            return;
+        };
+        let LiteralValue::String(tag) = &literal.value else {
+            return;
+        };
+        if !tag.is_empty() && !completions.iter().any(|completion| &completion.label == tag) {
+            completions.push(completion_item(
+                tag,
+                CompletionItemKind::REFERENCE,
+                Some(format!("tag of a {} segment", call.callee.name)),
+                None,
+            ));
+        }
+    });
+    completions
+}
+
+/// The keys of the object at `path`, e.g. `["part", "size"]` for `part.size.`, from its declaration if
+/// it's an object literal, otherwise from its value the last time the program was executed.
+fn member_completions(program: Option<&Program>, memory: Option<&ProgramMemory>, path: &[&str]) -> Vec<CompletionItem> {
+    let (name, members) = path.split_first().expect("a member path always has a name");
+
+    if let Some(object) = program.and_then(|program| declared_object(program, name, members)) {
+        return object
+            .properties
+            .iter()
+            .map(|property| completion_item(&property.key.name, CompletionItemKind::PROPERTY, None, None))
+            .collect();
+    }
+
+    let Some(MemoryItem::UserVal(user_val)) = memory.and_then(|memory| memory.root.get(*name)) else {
+        return Vec::new();
+    };
+    let mut value = &user_val.value;
+    for member in members {
+        let Some(member) = value.get(member) else {
+            return Vec::new();
+        };
+        value = member;
+    }
+    let Some(object) = value.as_object() else {
+        return Vec::new();
+    };
+    object
+        .iter()
+        .map(|(key, value)| {
+            let detail = match value {
+                serde_json::Value::Number(number) => number.to_string(),
+                serde_json::Value::String(string) => format!("{:?}", string),
+                serde_json::Value::Bool(boolean) => boolean.to_string(),
+                serde_json::Value::Array(_) => "array".to_string(),
+                serde_json::Value::Object(_) => "object".to_string(),
+                serde_json::Value::Null => "null".to_string(),
+            };
+            completion_item(key, CompletionItemKind::PROPERTY, Some(detail), None)
+        })
+        .collect()
+}
+
+/// The object literal a top level variable is declared as, or one nested in it by the `members` keys.
+fn declared_object<'a>(program: &'a Program, name: &str, members: &[&str]) -> Option<&'a ObjectExpression> {
+    let mut found = None;
+    walk(program, &mut |node, ancestors| {
+        if let Node::VariableDeclarator(declarator) = node {
+            if declarator.id.name == name && is_top_level(ancestors) {
+                found = Some(&declarator.init);
+            }
+        }
+    });
+    let mut value = found?;
+    for member in members {
+        let Value::ObjectExpression(object) = value else {
+            return None;
+        };
+        value = &object
+            .properties
+            .iter()
+            .find(|property| &property.key.name == member)?
+            .value;
+    }
+    match value {
+        Value::ObjectExpression(object) => Some(object.as_ref()),
+        _ => None,
+    }
+}
+
+/// The names in a member expression like `part.size`, if that's what the code ends with.
+fn member_path(code: &str) -> Option<Vec<&str>> {
+    let mut path = Vec::new();
+    let mut rest = code;
+    loop {
+        let start = rest.trim_end_matches(is_identifier_char).len();
+        let name = &rest[start..];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        path.push(name);
+        match rest[..start].strip_suffix('.') {
+            Some(before) => rest = before,
+            None => break,
+        }
+    }
+    path.reverse();
+    Some(path)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// If the argument or property takes the name of an existing segment tag.
+fn is_tag_name(name: &str) -> bool {
+    name.to_lowercase().contains("tag") || name == "segment_name"
+}
+
+/// The schema and the schemas it's one of, any of or all of.
+fn variants(schema: &Schema) -> Vec<&SchemaObject> {
+    let Schema::Object(object) = schema else {
+        return Vec::new();
+    };
+    let mut all = vec![object];
+    if let Some(subschemas) = &object.subschemas {
+        for schemas in [&subschemas.one_of, &subschemas.any_of, &subschemas.all_of]
+            .into_iter()
+            .flatten()
+        {
+            all.extend(schemas.iter().flat_map(variants));
+        }
+    }
+    all
+}
+
+/// The schema of a property of an object with the given schema.
+fn property_schema(schema: &Schema, key: &str) -> Option<Schema> {
+    variants(schema).into_iter().find_map(|variant| {
+        variant
+            .object
+            .as_ref()
+            .and_then(|object| object.properties.get(key))
+            .cloned()
+    })
+}
+
+/// The schema of the items of an array with the given schema.
+fn items_schema(schema: &Schema) -> Option<Schema> {
+    variants(schema).into_iter().find_map(|variant| {
+        match variant.array.as_ref().and_then(|array| array.items.as_ref())? {
+            SingleOrVec::Single(items) => Some(items.as_ref().clone()),
+            SingleOrVec::Vec(items) => items.first().cloned(),
+        }
+    })
+}
+
+fn completion_item(
+    label: &str,
+    kind: CompletionItemKind,
+    detail: Option<String>,
+    documentation: Option<String>,
+) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        label_details: None,
+        kind: Some(kind),
+        detail,
+        documentation: documentation.map(|value| {
+            Documentation::MarkupContent(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            })
+        }),
+        deprecated: None,
+        preselect: None,
+        sort_text: None,
+        filter_text: None,
+        insert_text: None,
+        insert_text_format: None,
+        insert_text_mode: None,
+        text_edit: None,
+        additional_text_edits: None,
+        command: None,
+        commit_characters: None,
+        data: None,
+        tags: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// The labels of the completions where `$` is in the code.
+    fn labels(code: &str) -> Option<Vec<String>> {
+        let pos = code.find('$').unwrap();
+        let code = code.replacen('$', "", 1);
+        let completions = completions(None, &StdLib::new(), &code, None, pos)?;
+        Some(completions.into_iter().map(|completion| completion.label).collect())
+    }
+
+    const SKETCH: &str = r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, 'seg01')
+  |> line([10, 0], %, 'seg02')
+  |> close(%)
+  |> extrude(5, %)
+"#;
+
+    #[test]
+    fn fillet_keys() {
+        let code = format!("{}  |> fillet({{ r$ }}, %)\n", SKETCH);
+        assert_eq!(labels(&code).unwrap(), vec!["radius", "tags"]);
+        // Keys already in the object aren't offered again.
+        let code = format!("{}  |> fillet({{ radius: 1, $ }}, %)\n", SKETCH);
+        assert_eq!(labels(&code).unwrap(), vec!["tags"]);
+    }
+
+    #[test]
+    fn tag_names() {
+        let code = format!("{}  |> fillet({{ radius: 1, tags: ['s$'] }}, %)\n", SKETCH);
+        assert_eq!(labels(&code).unwrap(), vec!["seg01", "seg02"]);
+        let code = format!("{}const length = segLen('$', part)\n", SKETCH);
+        assert_eq!(labels(&code).unwrap(), vec!["seg01", "seg02"]);
+    }
+
+    #[test]
+    fn plane_names() {
+        let labels = labels("const part = startSketchOn('X$')\n").unwrap();
+        for plane in ["XY", "-XY", "XZ", "-XZ", "YZ", "-YZ"] {
+            assert!(labels.contains(&plane.to_string()), "{} in {:?}", plane, labels);
+        }
+    }
+
+    #[test]
+    fn object_members() {
+        let code = r#"const size = { width: 1, depth: { min: 2, max: 3 } }
+const w = size."#;
+        let pos = code.len();
+        let previous = Parsed::new("const size = { width: 1, depth: { min: 2, max: 3 } }\n").unwrap();
+        let completions = completions(Some(&previous), &StdLib::new(), code, None, pos).unwrap();
+        let labels: Vec<_> = completions.into_iter().map(|completion| completion.label).collect();
+        assert_eq!(labels, vec!["width", "depth"]);
+
+        assert_eq!(member_path("size.depth"), Some(vec!["size", "depth"]));
+        assert_eq!(member_path("1"), None);
+    }
+
+    #[test]
+    fn anywhere_else() {
+        assert_eq!(labels("const part = $\n"), None);
+    }
+}
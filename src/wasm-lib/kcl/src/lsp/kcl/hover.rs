@@ -0,0 +1,308 @@
+//! Hover and signature help for the names declared in a program, rather than the stdlib's: the
+//! signatures and docs of user-defined functions, and the values of variables from the last time the
+//! program was executed.
+
+use tower_lsp::lsp_types::{
+    Documentation, Hover, HoverContents, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel,
+    SignatureHelp, SignatureInformation,
+};
+
+use crate::{
+    ast::{
+        analysis,
+        types::{describe_type, FunctionExpression, NonCodeValue, Parameter, Program, Value, VariableDeclarator},
+        walk::{is_top_level, walk, Node},
+    },
+    executor::{MemoryItem, ProgramMemory, SourceRange},
+    std::StdLib,
+};
+
+/// A variable declared in the program, along with the comments before it.
+struct Declaration<'a> {
+    declarator: &'a VariableDeclarator,
+    docs: Option<String>,
+    is_top_level: bool,
+}
+
+impl<'a> Declaration<'a> {
+    /// The function the variable is, if it's a function.
+    fn function(&self) -> Option<&'a FunctionExpression> {
+        match &self.declarator.init {
+            Value::FunctionExpression(function) => Some(function.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Hover for the name at `pos`, if it's declared in the program.
+/// Values are only shown if the program has been executed, in which case `memory` is what it left behind.
+pub fn hover(
+    program: &Program,
+    stdlib: &StdLib,
+    code: &str,
+    memory: Option<&ProgramMemory>,
+    pos: usize,
+) -> Option<Hover> {
+    let symbol = analysis::symbol_at(program, stdlib, pos)?;
+    let declaration = declaration(program, symbol.declaration)?;
+    let name = &declaration.declarator.id.name;
+
+    let mut value = match declaration.function() {
+        Some(function) => format!("```{}```", signature(name, function)),
+        None => {
+            // Only the top level variables are kept in memory after executing.
+            let item = memory
+                .filter(|_| declaration.is_top_level)
+                .and_then(|memory| memory.root.get(name));
+            if item.is_none() && declaration.docs.is_none() {
+                return None;
+            }
+            match item {
+                Some(item) => format!("```{} = {}```", name, summarize(item)),
+                None => format!("```{}```", name),
+            }
+        }
+    };
+    if let Some(docs) = &declaration.docs {
+        value.push('\n');
+        value.push_str(docs);
+    }
+
+    // The range of the name being hovered, whether it's the declaration or a use.
+    let range = std::iter::once(symbol.declaration)
+        .chain(symbol.uses)
+        .find(|range| range.start() <= pos && pos <= range.end())?;
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: Some(range.to_lsp_range(code)),
+    })
+}
+
+/// Signature help for the call at `pos`, if it calls a function declared in the program.
+pub fn signature_help(program: &Program, stdlib: &StdLib, pos: usize) -> Option<SignatureHelp> {
+    // The innermost call around the position.
+    let mut found = None;
+    walk(program, &mut |node, _| {
+        if let Node::CallExpression(call) = node {
+            let range = SourceRange::from(call);
+            if range.start() <= pos && pos <= range.end() {
+                found = Some(call);
+            }
+        }
+    });
+    let call = found?;
+
+    let symbol = analysis::symbol_at(program, stdlib, call.callee.start)?;
+    let declaration = declaration(program, symbol.declaration)?;
+    let function = declaration.function()?;
+
+    // The argument the position is in, or the next one if it's between them.
+    let active_parameter = call
+        .arguments
+        .iter()
+        .take_while(|argument| SourceRange::from(*argument).end() < pos)
+        .count() as u32;
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label: signature(&declaration.declarator.id.name, function),
+            documentation: declaration.docs.map(|docs| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: docs,
+                })
+            }),
+            parameters: Some(
+                function
+                    .params
+                    .iter()
+                    .map(|param| ParameterInformation {
+                        label: ParameterLabel::Simple(parameter_label(param)),
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: Some(active_parameter),
+        }],
+        active_signature: Some(0),
+        active_parameter: Some(active_parameter),
+    })
+}
+
+/// The variable whose name is at `range`, with the comments before the statement declaring it.
+fn declaration(program: &Program, range: SourceRange) -> Option<Declaration<'_>> {
+    let mut found = None;
+    walk(program, &mut |node, ancestors| {
+        let Node::VariableDeclarator(declarator) = node else {
+            return;
+        };
+        if SourceRange::from(&declarator.id) != range {
+            return;
+        }
+        // The declarator is inside a declaration, inside the body the declaration is an item of.
+        let docs = match ancestors {
+            [.., Node::Program(body), Node::VariableDeclaration(declaration)] => {
+                let index = body
+                    .body
+                    .iter()
+                    .position(|item| SourceRange::from(item) == SourceRange::from(*declaration));
+                index.and_then(|index| docs(body, index))
+            }
+            _ => None,
+        };
+        found = Some(Declaration {
+            declarator,
+            docs,
+            is_top_level: is_top_level(ancestors),
+        });
+    });
+    found
+}
+
+/// The comments directly before the item at `index` of the body, without a blank line between them and it.
+fn docs(body: &Program, index: usize) -> Option<String> {
+    // Comments and blank lines are kept with the item before them.
+    let nodes = if index == 0 {
+        &body.non_code_meta.start
+    } else {
+        body.non_code_meta.non_code_nodes.get(&(index - 1))?
+    };
+
+    let mut lines = Vec::new();
+    for node in nodes.iter().rev() {
+        match &node.value {
+            NonCodeValue::BlockComment { value, .. } => lines.push(value.as_str()),
+            // The blank line is before this comment.
+            NonCodeValue::NewLineBlockComment { value, .. } => {
+                lines.push(value.as_str());
+                break;
+            }
+            NonCodeValue::NewLine | NonCodeValue::InlineComment { .. } | NonCodeValue::Shebang { .. } => break,
+        }
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    lines.reverse();
+    Some(lines.join("\n"))
+}
+
+/// A function's signature, in the same form as its declaration, e.g. `box(width: number, height?)`.
+fn signature(name: &str, function: &FunctionExpression) -> String {
+    let params: Vec<String> = function.params.iter().map(parameter_label).collect();
+    match &function.return_type {
+        Some(return_type) => format!("{}({}): {}", name, params.join(", "), return_type),
+        None => format!("{}({})", name, params.join(", ")),
+    }
+}
+
+fn parameter_label(param: &Parameter) -> String {
+    let optional = if param.optional { "?" } else { "" };
+    match &param.type_ {
+        Some(type_) => format!("{}{}: {}", param.identifier.name, optional, type_),
+        None => format!("{}{}", param.identifier.name, optional),
+    }
+}
+
+/// A short description of a value: numbers and strings as they are, and geometry by its id and how many
+/// paths it has.
+fn summarize(item: &MemoryItem) -> String {
+    match item {
+        MemoryItem::UserVal(user_val) => match user_val.value.as_f64() {
+            Some(number) => number.to_string(),
+            None if user_val.value.is_string() || user_val.value.is_boolean() => user_val.value.to_string(),
+            None => describe_type(item),
+        },
+        MemoryItem::SketchGroup(sketch_group) => {
+            format!(
+                "sketch_group {} with {} paths",
+                sketch_group.id,
+                sketch_group.value.len()
+            )
+        }
+        MemoryItem::ExtrudeGroup(extrude_group) => format!(
+            "extrude_group {} with {} paths",
+            extrude_group.id,
+            extrude_group.sketch_group_values.len()
+        ),
+        MemoryItem::SketchGroups { value } => format!("{} sketch_groups", value.len()),
+        MemoryItem::ExtrudeGroups { value } => format!("{} extrude_groups", value.len()),
+        _ => describe_type(item),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn parse(code: &str) -> Program {
+        let tokens = crate::token::lexer(code).unwrap();
+        crate::parser::Parser::new(tokens).ast().unwrap()
+    }
+
+    fn hover_value(code: &str, at: &str) -> Option<String> {
+        let program = parse(code);
+        let pos = code.rfind(at).unwrap();
+        let hover = hover(&program, &StdLib::new(), code, None, pos)?;
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("Expected markup");
+        };
+        Some(markup.value)
+    }
+
+    #[test]
+    fn hover_user_function() {
+        let code = r#"const width = 4
+
+// A box with the given size.
+// The height defaults to the width.
+fn box = (size: number, height?) => {
+  return size
+}
+
+const part = box(width)
+"#;
+        assert_eq!(
+            hover_value(code, "box(width)").unwrap(),
+            "```box(size: number, height?)```\nA box with the given size.\nThe height defaults to the width."
+        );
+        // Variables without values or docs have nothing to show.
+        assert_eq!(hover_value(code, "width)"), None);
+    }
+
+    #[test]
+    fn signature_help_user_function() {
+        let code = r#"fn box = (size, height?) => {
+  return size
+}
+const part = box(1, 2)
+"#;
+        let program = parse(code);
+        let help = signature_help(&program, &StdLib::new(), code.find("2)").unwrap()).unwrap();
+        assert_eq!(help.signatures[0].label, "box(size, height?)");
+        assert_eq!(help.signatures[0].documentation, None);
+        assert_eq!(help.active_parameter, Some(1));
+        assert_eq!(
+            help.signatures[0].parameters,
+            Some(vec![
+                ParameterInformation {
+                    label: ParameterLabel::Simple("size".to_string()),
+                    documentation: None,
+                },
+                ParameterInformation {
+                    label: ParameterLabel::Simple("height?".to_string()),
+                    documentation: None,
+                },
+            ])
+        );
+        // There's no signature outside of a call.
+        assert_eq!(
+            signature_help(&program, &StdLib::new(), code.find("size\n").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Inlay hints for the `kcl` lsp server: the names of the parameters of stdlib functions, and the
+//! values of numeric expressions from the last time the program was executed.
+
+use tower_lsp::lsp_types::{InlayHint, InlayHintKind, InlayHintLabel};
+
+use crate::{
+    ast::{
+        types::{BinaryExpression, BinaryOperator, BinaryPart, LiteralValue, Program, UnaryOperator, Value},
+        walk::{is_top_level, walk, Node},
+    },
+    executor::{MemoryItem, ProgramMemory, SourceRange},
+    std::StdLib,
+};
+
+/// Every inlay hint for the program, in the order they appear in the code.
+/// Values are only shown if the program has been executed, in which case `memory` is what it left behind.
+pub fn inlay_hints(program: &Program, stdlib: &StdLib, code: &str, memory: Option<&ProgramMemory>) -> Vec<InlayHint> {
+    let mut hints = Vec::new();
+    walk(program, &mut |node, ancestors| match node {
+        Node::CallExpression(call) => {
+            let Some(func) = stdlib
+                .get(&call.callee.name)
+                .or_else(|| stdlib.get_kcl(&call.callee.name).map(|func| func.std_lib()))
+            else {
+                return;
+            };
+            for (arg, value) in func.args().iter().zip(&call.arguments) {
+                // The name is already there if the argument is a variable with the same name.
+                if matches!(value, Value::Identifier(identifier) if identifier.name == arg.name) {
+                    continue;
+                }
+                hints.push(parameter_hint(&arg.name, value.into(), code));
+            }
+        }
+        // Names inside functions, loops and if branches have different values each time they're
+        // executed, and aren't kept in memory afterwards, so only the top level has values.
+        Node::VariableDeclarator(declarator) if is_top_level(ancestors) => {
+            let Some(memory) = memory else {
+                return;
+            };
+            // A literal already shows its value.
+            if matches!(declarator.init, Value::Literal(_)) {
+                return;
+            }
+            if let Some(MemoryItem::UserVal(user_val)) = memory.root.get(&declarator.id.name) {
+                if let Some(value) = user_val.value.as_f64() {
+                    hints.push(value_hint(value, declarator.into(), code));
+                }
+            }
+        }
+        Node::BinaryExpression(expression) if is_top_level(ancestors) => {
+            let Some(memory) = memory else {
+                return;
+            };
+            match ancestors.last() {
+                // Only the outermost expression gets a value.
+                Some(Node::BinaryExpression(_)) => return,
+                // The declaration's value is already shown.
+                Some(Node::VariableDeclarator(declarator))
+                    if SourceRange::from(&declarator.init) == SourceRange::from(expression) =>
+                {
+                    return
+                }
+                _ => {}
+            }
+            if let Some(value) = evaluate_binary(expression, memory) {
+                hints.push(value_hint(value, expression.into(), code));
+            }
+        }
+        _ => {}
+    });
+
+    hints
+}
+
+/// The name of a parameter, before the argument passed for it.
+fn parameter_hint(name: &str, argument: SourceRange, code: &str) -> InlayHint {
+    InlayHint {
+        position: argument.start_to_lsp_position(code),
+        label: InlayHintLabel::String(format!("{}:", name)),
+        kind: Some(InlayHintKind::PARAMETER),
+        text_edits: None,
+        tooltip: None,
+        padding_left: None,
+        padding_right: Some(true),
+        data: None,
+    }
+}
+
+/// The value of an expression, after it.
+fn value_hint(value: f64, expression: SourceRange, code: &str) -> InlayHint {
+    // Round away floating point noise, e.g. `0.1 + 0.2`.
+    let value = (value * 1e6).round() / 1e6;
+    InlayHint {
+        position: expression.end_to_lsp_position(code),
+        label: InlayHintLabel::String(format!("= {}", value)),
+        kind: None,
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(true),
+        padding_right: None,
+        data: None,
+    }
+}
+
+/// The number a top level expression evaluates to, using the values of the names in memory.
+/// Anything that isn't arithmetic on numbers, like a function call, has no value here.
+fn evaluate(part: &BinaryPart, memory: &ProgramMemory) -> Option<f64> {
+    match part {
+        BinaryPart::Literal(literal) => match literal.value {
+            LiteralValue::IInteger(value) => Some(value as f64),
+            LiteralValue::Fractional(value) => Some(value),
+            LiteralValue::String(_) | LiteralValue::Bool(_) => None,
+        },
+        BinaryPart::Identifier(identifier) => match memory.root.get(&identifier.name)? {
+            MemoryItem::UserVal(user_val) => user_val.value.as_f64(),
+            _ => None,
+        },
+        BinaryPart::UnaryExpression(expression) => match expression.operator {
+            UnaryOperator::Neg => Some(-evaluate(&expression.argument, memory)?),
+            UnaryOperator::Not => None,
+        },
+        BinaryPart::BinaryExpression(expression) => evaluate_binary(expression, memory),
+        BinaryPart::CallExpression(_) | BinaryPart::MemberExpression(_) => None,
+    }
+}
+
+fn evaluate_binary(expression: &BinaryExpression, memory: &ProgramMemory) -> Option<f64> {
+    let left = evaluate(&expression.left, memory)?;
+    let right = evaluate(&expression.right, memory)?;
+    match expression.operator {
+        BinaryOperator::Add => Some(left + right),
+        BinaryOperator::Sub => Some(left - right),
+        BinaryOperator::Mul => Some(left * right),
+        BinaryOperator::Div => Some(left / right),
+        BinaryOperator::Mod => Some(left % right),
+        BinaryOperator::Pow => Some(left.powf(right)),
+        // Comparisons and boolean operators aren't numbers.
+        _ => None,
+    }
+}
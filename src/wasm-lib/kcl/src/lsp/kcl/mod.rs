@@ -4,7 +4,11 @@ use std::{collections::HashMap, io::Write, str::FromStr, sync::Arc};
 
 use tokio::sync::RwLock;
 
+mod completions;
 pub mod custom_notifications;
+mod hover;
+mod inlay_hints;
+mod workspace_symbols;
 
 use anyhow::Result;
 #[cfg(feature = "cli")]
@@ -13,22 +17,26 @@ use sha2::Digest;
 use tower_lsp::{
     jsonrpc::Result as RpcResult,
     lsp_types::{
-        CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse, CreateFilesParams,
-        DeleteFilesParams, DiagnosticOptions, DiagnosticServerCapabilities, DidChangeConfigurationParams,
-        DidChangeTextDocumentParams, DidChangeWatchedFilesParams, DidChangeWorkspaceFoldersParams,
-        DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentDiagnosticParams,
-        DocumentDiagnosticReport, DocumentDiagnosticReportResult, DocumentFilter, DocumentFormattingParams,
-        DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, Documentation, FoldingRange, FoldingRangeParams,
-        FoldingRangeProviderCapability, FullDocumentDiagnosticReport, Hover, HoverContents, HoverParams,
-        HoverProviderCapability, InitializeParams, InitializeResult, InitializedParams, InlayHint, InlayHintParams,
-        InsertTextFormat, MarkupContent, MarkupKind, MessageType, OneOf, Position, RelatedFullDocumentDiagnosticReport,
-        RenameFilesParams, RenameParams, SemanticToken, SemanticTokenType, SemanticTokens, SemanticTokensFullOptions,
+        CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionProviderCapability,
+        CodeActionResponse, CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams,
+        CompletionResponse, CreateFilesParams, DeleteFilesParams, Diagnostic, DiagnosticOptions,
+        DiagnosticServerCapabilities, DiagnosticSeverity, DidChangeConfigurationParams, DidChangeTextDocumentParams,
+        DidChangeWatchedFilesParams, DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams,
+        DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentDiagnosticParams, DocumentDiagnosticReport,
+        DocumentDiagnosticReportResult, DocumentFilter, DocumentFormattingParams, DocumentSymbol, DocumentSymbolParams,
+        DocumentSymbolResponse, Documentation, FileChangeType, FileOperationFilter, FileOperationPattern,
+        FileOperationPatternKind, FileOperationRegistrationOptions, FoldingRange, FoldingRangeParams,
+        FoldingRangeProviderCapability, FullDocumentDiagnosticReport, GotoDefinitionParams, GotoDefinitionResponse,
+        Hover, HoverContents, HoverParams, HoverProviderCapability, InitializeParams, InitializeResult,
+        InitializedParams, InlayHint, InlayHintParams, InsertTextFormat, Location, MarkupContent, MarkupKind,
+        MessageType, OneOf, Position, ReferenceParams, RelatedFullDocumentDiagnosticReport, RenameFilesParams,
+        RenameParams, SemanticToken, SemanticTokenType, SemanticTokens, SemanticTokensFullOptions,
         SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams, SemanticTokensRegistrationOptions,
         SemanticTokensResult, SemanticTokensServerCapabilities, ServerCapabilities, SignatureHelp,
-        SignatureHelpOptions, SignatureHelpParams, StaticRegistrationOptions, TextDocumentItem,
+        SignatureHelpOptions, SignatureHelpParams, StaticRegistrationOptions, SymbolInformation, TextDocumentItem,
         TextDocumentRegistrationOptions, TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-        TextEdit, WorkDoneProgressOptions, WorkspaceEdit, WorkspaceFolder, WorkspaceFoldersServerCapabilities,
-        WorkspaceServerCapabilities,
+        TextEdit, Url, WorkDoneProgressOptions, WorkspaceEdit, WorkspaceFileOperationsServerCapabilities,
+        WorkspaceFolder, WorkspaceFoldersServerCapabilities, WorkspaceServerCapabilities, WorkspaceSymbolParams,
     },
     Client, LanguageServer,
 };
@@ -36,9 +44,9 @@ use tower_lsp::{
 use super::backend::{InnerHandle, UpdateHandle};
 use crate::{
     ast::types::VariableKind,
-    errors::KclError,
     executor::SourceRange,
-    lsp::{backend::Backend as _, safemap::SafeMap},
+    fs::FileSystem,
+    lsp::{backend::Backend as _, safemap::SafeMap, util::uri_to_path},
     parser::PIPE_OPERATOR,
 };
 
@@ -74,6 +82,8 @@ pub struct Backend {
     pub token_map: SafeMap<String, Vec<crate::token::Token>>,
     /// AST maps.
     pub ast_map: SafeMap<String, crate::ast::types::Program>,
+    /// The code last parsed without errors, with its tokens and AST, for reparsing only what changes.
+    pub parsed_map: SafeMap<String, crate::parser::incremental::Parsed>,
     /// Memory maps.
     pub memory_map: SafeMap<String, crate::executor::ProgramMemory>,
     /// Current code.
@@ -82,6 +92,8 @@ pub struct Backend {
     pub diagnostics_map: SafeMap<String, DocumentDiagnosticReport>,
     /// Symbols map.
     pub symbols_map: SafeMap<String, Vec<DocumentSymbol>>,
+    /// The symbols in every kcl file of the workspace, whether it's open or not.
+    pub workspace_symbols_map: SafeMap<String, Vec<SymbolInformation>>,
     /// Semantic tokens map.
     pub semantic_tokens_map: SafeMap<String, Vec<SemanticToken>>,
     /// The Zoo API client.
@@ -92,6 +104,8 @@ pub struct Backend {
     pub executor_ctx: Arc<RwLock<Option<crate::executor::ExecutorContext>>>,
     /// If we are currently allowed to execute the ast.
     pub can_execute: Arc<RwLock<bool>>,
+    /// Which lint rules to check, and how seriously.
+    pub lint_settings: Arc<RwLock<crate::settings::types::LintSettings>>,
 
     pub is_initialized: Arc<RwLock<bool>>,
     pub current_handle: UpdateHandle,
@@ -156,8 +170,10 @@ impl crate::lsp::backend::Backend for Backend {
         self.code_map.clear().await;
         self.token_map.clear().await;
         self.ast_map.clear().await;
+        self.parsed_map.clear().await;
         self.diagnostics_map.clear().await;
         self.symbols_map.clear().await;
+        self.workspace_symbols_map.clear().await;
         self.semantic_tokens_map.clear().await;
     }
 
@@ -168,13 +184,23 @@ impl crate::lsp::backend::Backend for Backend {
     async fn inner_on_change(&self, params: TextDocumentItem, force: bool) {
         // We already updated the code map in the shared backend.
 
+        // Only lex and parse the items which changed since the code last parsed, if we can.
+        let reparsed = match self.parsed_map.get(&params.uri.to_string()).await {
+            Some(previous) => crate::parser::incremental::reparse(&previous, &params.text),
+            None => None,
+        };
+
         // Lets update the tokens.
-        let tokens = match crate::token::lexer(&params.text) {
-            Ok(tokens) => tokens,
-            Err(err) => {
-                self.add_to_diagnostics(&params, err).await;
-                return;
-            }
+        let tokens = match &reparsed {
+            Some(parsed) => parsed.tokens.clone(),
+            None => match crate::token::lexer(&params.text) {
+                Ok(tokens) => tokens,
+                Err(err) => {
+                    self.add_to_diagnostics(&params, vec![err.to_lsp_diagnostic(&params.text)])
+                        .await;
+                    return;
+                }
+            },
         };
 
         // Get the previous tokens.
@@ -207,15 +233,30 @@ impl crate::lsp::backend::Backend for Backend {
         }
 
         // Lets update the ast.
-        let parser = crate::parser::Parser::new(tokens);
-        let result = parser.ast();
-        let ast = match result {
-            Ok(ast) => ast,
-            Err(err) => {
-                self.add_to_diagnostics(&params, err).await;
-                return;
+        let ast = match reparsed {
+            Some(parsed) => parsed.program,
+            None => {
+                let parser = crate::parser::Parser::new(tokens.clone());
+                match parser.ast() {
+                    Ok(ast) => ast,
+                    Err(err) => {
+                        self.add_to_diagnostics(&params, vec![err.to_lsp_diagnostic(&params.text)])
+                            .await;
+                        return;
+                    }
+                }
             }
         };
+        self.parsed_map
+            .insert(
+                params.uri.to_string(),
+                crate::parser::incremental::Parsed {
+                    code: params.text.clone(),
+                    tokens,
+                    program: ast.clone(),
+                },
+            )
+            .await;
 
         // Check if the ast changed.
         let ast_changed = match self.ast_map.get(&params.uri.to_string()).await {
@@ -234,9 +275,14 @@ impl crate::lsp::backend::Backend for Backend {
         if ast_changed {
             self.ast_map.insert(params.uri.to_string(), ast.clone()).await;
             // Update the symbols map.
-            self.symbols_map
-                .insert(params.uri.to_string(), ast.get_lsp_symbols(&params.text))
+            let symbols = ast.get_lsp_symbols(&params.text);
+            self.workspace_symbols_map
+                .insert(
+                    params.uri.to_string(),
+                    workspace_symbols::symbol_information(&params.uri, &symbols),
+                )
                 .await;
+            self.symbols_map.insert(params.uri.to_string(), symbols).await;
         }
 
         // Send the notification to the client that the ast was updated.
@@ -248,17 +294,46 @@ impl crate::lsp::backend::Backend for Backend {
                 .await;
         }
 
+        // Check the program before executing it, so every mistake we can find is reported at once.
+        // Errors from the analysis mean it would fail to execute, so we don't try; everything else
+        // is kept alongside the results of executing it.
+        let stdlib = crate::std::StdLib::new();
+        let mut diagnostics: Vec<Diagnostic> = crate::ast::analysis::analyze(&ast, &stdlib)
+            .iter()
+            .map(|finding| finding.to_lsp_diagnostic(&params.text))
+            .collect();
+        let has_errors = diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Some(DiagnosticSeverity::ERROR));
+
+        // The type checker only knows the types it can infer, so the program still runs even if it
+        // reports something.
+        diagnostics.extend(
+            crate::ast::type_check::check_types(&ast)
+                .iter()
+                .map(|err| err.to_lsp_diagnostic(&params.text)),
+        );
+
+        // Lints never stop the code from executing, even the ones the project treats as errors.
+        diagnostics.extend(self.lint(&ast, &stdlib, &params.text).await);
+
+        if has_errors {
+            self.add_to_diagnostics(&params, diagnostics).await;
+            return;
+        }
+
         // Execute the code if we have an executor context.
         // This function automatically executes if we should & updates the diagnostics if we got
         // errors.
-        let result = self.execute(&params, ast).await;
+        let result = self.execute(&params, ast, &diagnostics).await;
         if result.is_err() {
             // We return early because we got errors, and we don't want to clear the diagnostics.
             return;
         }
 
         // Lets update the diagnostics, since we got no errors.
-        self.clear_diagnostics(&params.uri).await;
+        // This clears them unless there are warnings.
+        self.add_to_diagnostics(&params, diagnostics).await;
     }
 }
 
@@ -356,9 +431,44 @@ impl Backend {
             .await;
     }
 
-    async fn add_to_diagnostics(&self, params: &TextDocumentItem, err: KclError) {
-        let diagnostic = err.to_lsp_diagnostic(&params.text);
-        // We got errors, update the diagnostics.
+    /// Check the program against the project's lint rules.
+    async fn lint(&self, ast: &crate::ast::types::Program, stdlib: &crate::std::StdLib, code: &str) -> Vec<Diagnostic> {
+        let lint_settings = self.lint_settings.read().await.clone();
+        crate::lint::lint(ast, stdlib, &lint_settings)
+            .iter()
+            .map(|discovered| discovered.to_lsp_diagnostic(code))
+            .collect()
+    }
+
+    /// Replace the lints in every file's diagnostics, e.g. after the lint settings changed.
+    async fn update_lints(&self) {
+        let stdlib = crate::std::StdLib::new();
+        for (filename, ast) in self.ast_map.inner().await {
+            let Some(code) = self.code_map.get(&filename).await else {
+                continue;
+            };
+            let Ok(uri) = url::Url::parse(&filename) else {
+                continue;
+            };
+            let params = TextDocumentItem {
+                uri,
+                text: String::from_utf8_lossy(&code).to_string(),
+                version: Default::default(),
+                language_id: Default::default(),
+            };
+
+            let mut diagnostics = match self.diagnostics_map.get(&filename).await {
+                Some(DocumentDiagnosticReport::Full(report)) => report.full_document_diagnostic_report.items,
+                _ => Vec::new(),
+            };
+            diagnostics.retain(|diagnostic| diagnostic.source.as_deref() != Some(crate::lint::DIAGNOSTIC_SOURCE));
+            diagnostics.extend(self.lint(&ast, &stdlib, &params.text).await);
+            self.add_to_diagnostics(&params, diagnostics).await;
+        }
+    }
+
+    async fn add_to_diagnostics(&self, params: &TextDocumentItem, diagnostics: Vec<Diagnostic>) {
+        // Update the diagnostics, replacing the ones from before.
         self.diagnostics_map
             .insert(
                 params.uri.to_string(),
@@ -366,7 +476,7 @@ impl Backend {
                     related_documents: None,
                     full_document_diagnostic_report: FullDocumentDiagnosticReport {
                         result_id: None,
-                        items: vec![diagnostic.clone()],
+                        items: diagnostics.clone(),
                     },
                 }),
             )
@@ -375,39 +485,28 @@ impl Backend {
         // Publish the diagnostic.
         // If the client supports it.
         self.client
-            .publish_diagnostics(params.uri.clone(), vec![diagnostic], None)
-            .await;
-    }
-
-    async fn clear_diagnostics(&self, uri: &url::Url) {
-        self.diagnostics_map
-            .insert(
-                uri.to_string(),
-                DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
-                    related_documents: None,
-                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
-                        result_id: None,
-                        items: vec![],
-                    },
-                }),
-            )
+            .publish_diagnostics(params.uri.clone(), diagnostics, None)
             .await;
-
-        // Publish the diagnostic, we reset it here so the client knows the code compiles now.
-        // If the client supports it.
-        self.client.publish_diagnostics(uri.clone(), vec![], None).await;
     }
 
-    async fn execute(&self, params: &TextDocumentItem, ast: crate::ast::types::Program) -> Result<()> {
+    /// Execute the program, if we can.
+    /// `warnings` are published along with the error if it fails.
+    async fn execute(
+        &self,
+        params: &TextDocumentItem,
+        ast: crate::ast::types::Program,
+        warnings: &[Diagnostic],
+    ) -> Result<()> {
         // Check if we can execute.
         if !self.can_execute().await {
             return Ok(());
         }
 
         // Execute the code if we have an executor context.
-        let Some(executor_ctx) = self.executor_ctx().await else {
+        let Some(mut executor_ctx) = self.executor_ctx().await else {
             return Ok(());
         };
+        executor_ctx.settings.project_directory = self.project_directory(&params.uri).await;
 
         if !self.is_initialized().await {
             // We are not initialized yet.
@@ -420,7 +519,9 @@ impl Backend {
         let memory = match executor_ctx.run(ast, None).await {
             Ok(memory) => memory,
             Err(err) => {
-                self.add_to_diagnostics(params, err).await;
+                let mut diagnostics = warnings.to_vec();
+                diagnostics.push(err.to_lsp_diagnostic(&params.text));
+                self.add_to_diagnostics(params, diagnostics).await;
 
                 // Since we already published the diagnostics we don't really care about the error
                 // string.
@@ -439,6 +540,84 @@ impl Backend {
         Ok(())
     }
 
+    /// The directory the imports in the document at `uri` are relative to.
+    /// This is the root of its project, found the same way as when running it from the command line.
+    pub(crate) async fn project_directory(&self, uri: &Url) -> Option<String> {
+        crate::fs::project_directory(self.fs.as_ref(), &uri_to_path(uri))
+            .await
+            .map(|dir| dir.display().to_string())
+    }
+
+    /// The refactors which apply to the code in the requested range.
+    async fn refactor_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let filename = params.text_document.uri.to_string();
+        let Some(current_code) = self.code_map.get(&filename).await else {
+            return Vec::new();
+        };
+        let Ok(current_code) = String::from_utf8(current_code) else {
+            return Vec::new();
+        };
+        let Some(ast) = self.ast_map.get(&filename).await else {
+            return Vec::new();
+        };
+        // Some refactors use the values from executing the code.
+        let memory = self.memory_map.get(&filename).await;
+
+        let range = SourceRange([
+            position_to_char_index(params.range.start, &current_code),
+            position_to_char_index(params.range.end, &current_code),
+        ]);
+        let stdlib = crate::std::StdLib::new();
+        let format_options = crate::ast::types::FormatOptions {
+            insert_final_newline: current_code.ends_with('\n'),
+            ..Default::default()
+        };
+        let whole_file = SourceRange([0, current_code.len()]).to_lsp_range(&current_code);
+
+        crate::ast::refactor::refactors(&ast, &stdlib, range, memory.as_ref())
+            .into_iter()
+            .map(|refactor| {
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: refactor.title,
+                    kind: Some(match refactor.kind {
+                        crate::ast::refactor::RefactorKind::Extract => CodeActionKind::REFACTOR_EXTRACT,
+                        crate::ast::refactor::RefactorKind::Inline => CodeActionKind::REFACTOR_INLINE,
+                        crate::ast::refactor::RefactorKind::Rewrite => CodeActionKind::REFACTOR_REWRITE,
+                    }),
+                    diagnostics: None,
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(
+                            params.text_document.uri.clone(),
+                            vec![TextEdit {
+                                range: whole_file,
+                                new_text: refactor.program.recast(&format_options, 0),
+                            }],
+                        )])),
+                        document_changes: None,
+                        change_annotations: None,
+                    }),
+                    command: None,
+                    is_preferred: None,
+                    disabled: None,
+                    data: None,
+                })
+            })
+            .collect()
+    }
+
+    /// The name or tag at a position in a file, along with the file's code.
+    async fn symbol_at(&self, uri: &Url, position: Position) -> Option<(crate::ast::analysis::Symbol, String)> {
+        let filename = uri.to_string();
+        let current_code = self.code_map.get(&filename).await?;
+        let current_code = String::from_utf8(current_code).ok()?;
+        let ast = self.ast_map.get(&filename).await?;
+
+        let pos = position_to_char_index(position, &current_code);
+        let stdlib = crate::std::StdLib::new();
+        let symbol = crate::ast::analysis::symbol_at(&ast, &stdlib, pos)?;
+        Some((symbol, current_code))
+    }
+
     fn get_semantic_token_type_index(&self, token_type: SemanticTokenType) -> Option<usize> {
         self.token_types.iter().position(|x| *x == token_type)
     }
@@ -455,6 +634,31 @@ impl Backend {
             match item {
                 crate::ast::types::BodyItem::ExpressionStatement(_) => continue,
                 crate::ast::types::BodyItem::ReturnStatement(_) => continue,
+                crate::ast::types::BodyItem::ForStatement(_) => continue,
+                crate::ast::types::BodyItem::ImportStatement(import) => {
+                    for item in &import.items {
+                        completions.push(CompletionItem {
+                            label: item.identifier().name.to_string(),
+                            label_details: None,
+                            kind: Some(CompletionItemKind::VARIABLE),
+                            detail: Some(format!("imported from {}", import.path)),
+                            documentation: None,
+                            deprecated: None,
+                            preselect: None,
+                            sort_text: None,
+                            filter_text: None,
+                            insert_text: None,
+                            insert_text_format: None,
+                            insert_text_mode: None,
+                            text_edit: None,
+                            additional_text_edits: None,
+                            command: None,
+                            commit_characters: None,
+                            data: None,
+                            tags: None,
+                        });
+                    }
+                }
                 crate::ast::types::BodyItem::VariableDeclaration(variable) => {
                     // We only want to complete variables.
                     for declaration in &variable.declarations {
@@ -491,6 +695,42 @@ impl Backend {
         completions
     }
 
+    /// Index the symbols in every kcl file of a workspace folder.
+    async fn index_workspace_folder(&self, folder: &WorkspaceFolder) -> Result<()> {
+        let project_dir = folder.uri.to_string().replace("file://", "");
+        let files = self.fs.get_all_files(&project_dir, Default::default()).await?;
+        for file in files {
+            if file.extension().and_then(|extension| extension.to_str()) != Some("kcl") {
+                continue;
+            }
+            let uri = Url::parse(&format!("file://{}", file.display()))?;
+            self.index_file_from_disk(&uri).await;
+        }
+
+        Ok(())
+    }
+
+    /// Index the symbols in a file from what's on disk, keeping the ones we have if it doesn't parse.
+    async fn index_file_from_disk(&self, uri: &Url) {
+        let path = uri.to_string().replace("file://", "");
+        let Ok(contents) = self.fs.read(&path, Default::default()).await else {
+            self.workspace_symbols_map.remove(&uri.to_string()).await;
+            return;
+        };
+        if let Some(symbols) = workspace_symbols::index(uri, &contents) {
+            self.workspace_symbols_map.insert(uri.to_string(), symbols).await;
+        }
+    }
+
+    /// Remove the symbols of a file, or of every file in a folder.
+    async fn remove_from_workspace_symbols(&self, uri: &str) {
+        for key in self.workspace_symbols_map.inner().await.into_keys() {
+            if workspace_symbols::is_within(&key, uri) {
+                self.workspace_symbols_map.remove(&key).await;
+            }
+        }
+    }
+
     pub async fn create_zip(&self) -> Result<Vec<u8>> {
         // Collect all the file data we know.
         let mut buf = vec![];
@@ -666,13 +906,15 @@ impl LanguageServer for Backend {
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
-                    trigger_characters: Some(vec![".".to_string()]),
+                    trigger_characters: Some(vec![".".to_string(), "'".to_string()]),
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
                     ..Default::default()
                 }),
+                definition_provider: Some(OneOf::Left(true)),
                 diagnostic_provider: Some(DiagnosticServerCapabilities::Options(DiagnosticOptions {
                     ..Default::default()
                 })),
@@ -680,6 +922,7 @@ impl LanguageServer for Backend {
                 folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 inlay_hint_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
                 rename_provider: Some(OneOf::Left(true)),
                 semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(
                     SemanticTokensRegistrationOptions {
@@ -711,7 +954,7 @@ impl LanguageServer for Backend {
                 }),
                 text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
                     open_close: Some(true),
-                    change: Some(TextDocumentSyncKind::FULL),
+                    change: Some(TextDocumentSyncKind::INCREMENTAL),
                     ..Default::default()
                 })),
                 workspace: Some(WorkspaceServerCapabilities {
@@ -719,8 +962,14 @@ impl LanguageServer for Backend {
                         supported: Some(true),
                         change_notifications: Some(OneOf::Left(true)),
                     }),
-                    file_operations: None,
+                    file_operations: Some(WorkspaceFileOperationsServerCapabilities {
+                        did_create: Some(file_operation_registration_options()),
+                        did_rename: Some(file_operation_registration_options()),
+                        did_delete: Some(file_operation_registration_options()),
+                        ..Default::default()
+                    }),
                 }),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
                 ..Default::default()
             },
             ..Default::default()
@@ -736,27 +985,94 @@ impl LanguageServer for Backend {
     }
 
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
-        self.do_did_change_workspace_folders(params).await
+        self.do_did_change_workspace_folders(params.clone()).await;
+
+        for removed in &params.event.removed {
+            self.remove_from_workspace_symbols(removed.uri.as_str()).await;
+        }
+        for added in &params.event.added {
+            if let Err(err) = self.index_workspace_folder(added).await {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("indexing symbols in `{}` failed: {:?}", added.uri, err),
+                    )
+                    .await;
+            }
+        }
     }
 
     async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
-        self.do_did_change_configuration(params).await
+        // The settings are the project's settings, e.g. `{"settings": {"lint": {"camel_case": "off"}}}`.
+        let project_settings =
+            serde_json::from_value::<crate::settings::types::project::ProjectConfiguration>(params.settings.clone());
+        self.do_did_change_configuration(params).await;
+
+        let Ok(project_settings) = project_settings else {
+            return;
+        };
+        let lint_settings = project_settings.settings.lint;
+        if *self.lint_settings.read().await == lint_settings {
+            return;
+        }
+        *self.lint_settings.write().await = lint_settings;
+        self.update_lints().await;
     }
 
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
-        self.do_did_change_watched_files(params).await
+        self.do_did_change_watched_files(params.clone()).await;
+
+        for change in params.changes {
+            if change.typ == FileChangeType::DELETED {
+                self.remove_from_workspace_symbols(change.uri.as_str()).await;
+            } else {
+                self.index_file_from_disk(&change.uri).await;
+            }
+        }
     }
 
     async fn did_create_files(&self, params: CreateFilesParams) {
-        self.do_did_create_files(params).await
+        self.do_did_create_files(params.clone()).await;
+
+        for file in params.files {
+            if let Ok(uri) = Url::parse(&file.uri) {
+                self.index_file_from_disk(&uri).await;
+            }
+        }
     }
 
     async fn did_rename_files(&self, params: RenameFilesParams) {
-        self.do_did_rename_files(params).await
+        self.do_did_rename_files(params.clone()).await;
+
+        // Move the symbols of the file, or of every file in the folder, to where it is now.
+        for file in params.files {
+            for (old_uri, symbols) in self.workspace_symbols_map.inner().await {
+                if !workspace_symbols::is_within(&old_uri, &file.old_uri) {
+                    continue;
+                }
+                self.workspace_symbols_map.remove(&old_uri).await;
+                let new_uri = format!("{}{}", file.new_uri, &old_uri[file.old_uri.len()..]);
+                let Ok(uri) = Url::parse(&new_uri) else {
+                    continue;
+                };
+                let symbols = symbols
+                    .into_iter()
+                    .map(|mut symbol| {
+                        symbol.location.uri = uri.clone();
+                        symbol
+                    })
+                    .collect();
+                self.workspace_symbols_map.insert(new_uri, symbols).await;
+            }
+        }
     }
 
     async fn did_delete_files(&self, params: DeleteFilesParams) {
-        self.do_did_delete_files(params).await
+        self.do_did_delete_files(params.clone()).await;
+
+        for file in params.files {
+            self.remove_from_workspace_symbols(&file.uri).await;
+        }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
@@ -817,6 +1133,13 @@ impl LanguageServer for Backend {
             return Ok(None);
         };
 
+        // Names declared in the code come first, since they can shadow the stdlib.
+        let memory = self.memory_map.get(&filename).await;
+        let stdlib = crate::std::StdLib::new();
+        if let Some(hover) = hover::hover(&ast, &stdlib, current_code, memory.as_ref(), pos) {
+            return Ok(Some(hover));
+        }
+
         let Some(hover) = ast.get_hover_value_for_position(pos, current_code) else {
             return Ok(None);
         };
@@ -865,6 +1188,26 @@ impl LanguageServer for Backend {
     }
 
     async fn completion(&self, params: CompletionParams) -> RpcResult<Option<CompletionResponse>> {
+        let filename = params.text_document_position.text_document.uri.to_string();
+
+        // Where the code expects something in particular, only that is a completion.
+        if let Some(current_code) = self.code_map.get(&filename).await {
+            if let Ok(current_code) = std::str::from_utf8(&current_code) {
+                let pos = position_to_char_index(params.text_document_position.position, current_code);
+                let previous = self.parsed_map.get(&filename).await;
+                let memory = self.memory_map.get(&filename).await;
+                if let Some(completions) = completions::completions(
+                    previous.as_ref(),
+                    &crate::std::StdLib::new(),
+                    current_code,
+                    memory.as_ref(),
+                    pos,
+                ) {
+                    return Ok(Some(CompletionResponse::Array(completions)));
+                }
+            }
+        }
+
         let mut completions = vec![CompletionItem {
             label: PIPE_OPERATOR.to_string(),
             label_details: None,
@@ -937,6 +1280,11 @@ impl LanguageServer for Backend {
             return Ok(None);
         };
 
+        // Functions declared in the code come first, since they can shadow the stdlib.
+        if let Some(signature) = hover::signature_help(&ast, &crate::std::StdLib::new(), pos) {
+            return Ok(Some(signature));
+        }
+
         let Some(value) = ast.get_value_for_position(pos) else {
             return Ok(None);
         };
@@ -975,10 +1323,30 @@ impl LanguageServer for Backend {
         }
     }
 
-    async fn inlay_hint(&self, _params: InlayHintParams) -> RpcResult<Option<Vec<InlayHint>>> {
-        // TODO: do this
+    async fn inlay_hint(&self, params: InlayHintParams) -> RpcResult<Option<Vec<InlayHint>>> {
+        let filename = params.text_document.uri.to_string();
 
-        Ok(None)
+        let Some(current_code) = self.code_map.get(&filename).await else {
+            return Ok(None);
+        };
+        let Ok(current_code) = std::str::from_utf8(&current_code) else {
+            return Ok(None);
+        };
+
+        let Some(ast) = self.ast_map.get(&filename).await else {
+            return Ok(None);
+        };
+
+        // Values are only known once the code has been executed.
+        let memory = self.memory_map.get(&filename).await;
+
+        let stdlib = crate::std::StdLib::new();
+        let hints = inlay_hints::inlay_hints(&ast, &stdlib, current_code, memory.as_ref())
+            .into_iter()
+            .filter(|hint| params.range.start <= hint.position && hint.position <= params.range.end)
+            .collect();
+
+        Ok(Some(hints))
     }
 
     async fn semantic_tokens_full(&self, params: SemanticTokensParams) -> RpcResult<Option<SemanticTokensResult>> {
@@ -994,6 +1362,26 @@ impl LanguageServer for Backend {
         })))
     }
 
+    async fn symbol(&self, params: WorkspaceSymbolParams) -> RpcResult<Option<Vec<SymbolInformation>>> {
+        let mut symbols: Vec<SymbolInformation> = self
+            .workspace_symbols_map
+            .inner()
+            .await
+            .into_values()
+            .flatten()
+            .filter(|symbol| workspace_symbols::matches(&params.query, &symbol.name))
+            .collect();
+        symbols.sort_by(|a, b| {
+            (&a.name, a.location.uri.as_str(), a.location.range.start).cmp(&(
+                &b.name,
+                b.location.uri.as_str(),
+                b.location.range.start,
+            ))
+        });
+
+        Ok(Some(symbols))
+    }
+
     async fn document_symbol(&self, params: DocumentSymbolParams) -> RpcResult<Option<DocumentSymbolResponse>> {
         let filename = params.text_document.uri.to_string();
 
@@ -1041,6 +1429,90 @@ impl LanguageServer for Backend {
         }]))
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> RpcResult<Option<CodeActionResponse>> {
+        let filename = params.text_document.uri.to_string();
+
+        // Offer the fixes suggested by lints and analysis in the requested range.
+        let mut actions: Vec<CodeActionOrCommand> = Vec::new();
+        if let Some(DocumentDiagnosticReport::Full(report)) = self.diagnostics_map.get(&filename).await {
+            actions.extend(
+                report
+                    .full_document_diagnostic_report
+                    .items
+                    .into_iter()
+                    .filter(|diagnostic| {
+                        diagnostic.range.start <= params.range.end && params.range.start <= diagnostic.range.end
+                    })
+                    .filter_map(|diagnostic| {
+                        let suggestion: crate::lint::LspSuggestion =
+                            serde_json::from_value(diagnostic.data.clone()?).ok()?;
+                        Some(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: suggestion.title,
+                            kind: Some(CodeActionKind::QUICKFIX),
+                            diagnostics: Some(vec![diagnostic]),
+                            edit: Some(WorkspaceEdit {
+                                changes: Some(HashMap::from([(params.text_document.uri.clone(), suggestion.edits)])),
+                                document_changes: None,
+                                change_annotations: None,
+                            }),
+                            command: None,
+                            is_preferred: Some(true),
+                            disabled: None,
+                            data: None,
+                        }))
+                    }),
+            );
+        }
+
+        actions.extend(self.refactor_actions(&params).await);
+
+        // The client can ask for only some kinds of actions, e.g. only quick fixes.
+        if let Some(only) = &params.context.only {
+            actions.retain(|action| {
+                let CodeActionOrCommand::CodeAction(CodeAction { kind: Some(kind), .. }) = action else {
+                    return false;
+                };
+                only.iter()
+                    .any(|only| kind == only || kind.as_str().starts_with(&format!("{}.", only.as_str())))
+            });
+        }
+
+        Ok(Some(actions))
+    }
+
+    async fn goto_definition(&self, params: GotoDefinitionParams) -> RpcResult<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let Some((symbol, current_code)) = self
+            .symbol_at(&uri, params.text_document_position_params.position)
+            .await
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(GotoDefinitionResponse::Scalar(Location {
+            uri,
+            range: symbol.declaration.to_lsp_range(&current_code),
+        })))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> RpcResult<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let Some((symbol, current_code)) = self.symbol_at(&uri, params.text_document_position.position).await else {
+            return Ok(None);
+        };
+
+        let declaration = params.context.include_declaration.then_some(symbol.declaration);
+        let locations = declaration
+            .into_iter()
+            .chain(symbol.uses)
+            .map(|source_range| Location {
+                uri: uri.clone(),
+                range: source_range.to_lsp_range(&current_code),
+            })
+            .collect();
+        Ok(Some(locations))
+    }
+
     async fn rename(&self, params: RenameParams) -> RpcResult<Option<WorkspaceEdit>> {
         let filename = params.text_document_position.text_document.uri.to_string();
 
@@ -1132,6 +1604,31 @@ pub fn get_signatures_from_stdlib(stdlib: &crate::std::StdLib) -> Result<HashMap
 }
 
 /// Convert a position to a character index from the start of the file.
+/// The files the client tells us about creating, renaming and deleting: kcl files, and the folders
+/// they're in.
+fn file_operation_registration_options() -> FileOperationRegistrationOptions {
+    FileOperationRegistrationOptions {
+        filters: vec![
+            FileOperationFilter {
+                scheme: Some("file".to_string()),
+                pattern: FileOperationPattern {
+                    glob: "**/*.kcl".to_string(),
+                    matches: Some(FileOperationPatternKind::File),
+                    options: None,
+                },
+            },
+            FileOperationFilter {
+                scheme: Some("file".to_string()),
+                pattern: FileOperationPattern {
+                    glob: "**/*".to_string(),
+                    matches: Some(FileOperationPatternKind::Folder),
+                    options: None,
+                },
+            },
+        ],
+    }
+}
+
 fn position_to_char_index(position: Position, code: &str) -> usize {
     // Get the character position from the start of the file.
     let mut char_position = 0;
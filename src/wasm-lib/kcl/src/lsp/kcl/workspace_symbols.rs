@@ -0,0 +1,109 @@
+//! Symbols from every file in the workspace, open or not, for finding where something is declared
+//! across a whole project.
+
+use tower_lsp::lsp_types::{DocumentSymbol, Location, SymbolInformation, SymbolKind, Url};
+
+/// The symbols declared in a file, with the symbols nested in them, e.g. the properties of an object,
+/// named with what they're in.
+pub fn symbol_information(uri: &Url, symbols: &[DocumentSymbol]) -> Vec<SymbolInformation> {
+    fn add(uri: &Url, symbols: &[DocumentSymbol], container_name: Option<&str>, all: &mut Vec<SymbolInformation>) {
+        for symbol in symbols {
+            #[allow(deprecated)]
+            all.push(SymbolInformation {
+                name: symbol.name.clone(),
+                kind: symbol.kind,
+                tags: symbol.tags.clone(),
+                deprecated: None,
+                location: Location {
+                    uri: uri.clone(),
+                    range: symbol.selection_range,
+                },
+                container_name: container_name.map(|name| name.to_string()),
+            });
+            // A function's parameters can't be used outside of it.
+            if symbol.kind == SymbolKind::FUNCTION {
+                continue;
+            }
+            if let Some(children) = &symbol.children {
+                add(uri, children, Some(&symbol.name), all);
+            }
+        }
+    }
+
+    let mut all = Vec::new();
+    add(uri, symbols, None, &mut all);
+    all
+}
+
+/// The symbols of a file which isn't open, or `None` if it isn't kcl or doesn't parse.
+pub fn index(uri: &Url, code: &[u8]) -> Option<Vec<SymbolInformation>> {
+    if !uri.path().ends_with(".kcl") {
+        return None;
+    }
+    let code = std::str::from_utf8(code).ok()?;
+    let parsed = crate::parser::incremental::Parsed::new(code).ok()?;
+    Some(symbol_information(uri, &parsed.program.get_lsp_symbols(code)))
+}
+
+/// If the name matches what the user typed, which is every character of the query in order, ignoring
+/// case, e.g. `m5b` matches `m5Bolt`.
+pub fn matches(query: &str, name: &str) -> bool {
+    let mut name = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| name.any(|c| c == wanted))
+}
+
+/// If the uri is the file or folder `parent`, or inside the folder.
+pub fn is_within(uri: &str, parent: &str) -> bool {
+    uri == parent || uri.starts_with(&format!("{}/", parent.trim_end_matches('/')))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn index_nested_symbols() {
+        let uri: Url = "file:///project/bolts.kcl".parse().unwrap();
+        let code = "const m5Bolt = { length: 10 }\nfn head = (size) => {\n  return size\n}\n";
+        let symbols = index(&uri, code.as_bytes()).unwrap();
+        let names: Vec<_> = symbols
+            .iter()
+            .map(|symbol| (symbol.name.as_str(), symbol.container_name.as_deref()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("m5Bolt", None), ("length", Some("m5Bolt")), ("head", None)]
+        );
+        assert_eq!(symbols[2].kind, SymbolKind::FUNCTION);
+        assert_eq!(symbols[0].location.range.start.line, 0);
+        assert_eq!(symbols[2].location.range.start.line, 1);
+
+        // Only kcl which parses is indexed.
+        assert_eq!(
+            index(&"file:///project/notes.txt".parse().unwrap(), code.as_bytes()),
+            None
+        );
+        assert_eq!(index(&uri, b"const = 1"), None);
+    }
+
+    #[test]
+    fn fuzzy_matches() {
+        assert!(matches("m5b", "m5Bolt"));
+        assert!(matches("BOLT", "m5Bolt"));
+        assert!(matches("", "m5Bolt"));
+        assert!(!matches("bm5", "m5Bolt"));
+    }
+
+    #[test]
+    fn within_folder() {
+        assert!(is_within("file:///project/a.kcl", "file:///project"));
+        assert!(is_within("file:///project/a.kcl", "file:///project/"));
+        assert!(is_within("file:///project/a.kcl", "file:///project/a.kcl"));
+        assert!(!is_within("file:///project2/a.kcl", "file:///project"));
+    }
+}
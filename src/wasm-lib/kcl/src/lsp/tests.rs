@@ -66,15 +66,18 @@ async fn kcl_lsp_server(execute: bool) -> Result<crate::lsp::kcl::Backend> {
         token_types,
         token_map: Default::default(),
         ast_map: Default::default(),
+        parsed_map: Default::default(),
         memory_map: Default::default(),
         code_map: Default::default(),
         diagnostics_map: Default::default(),
         symbols_map: Default::default(),
+        workspace_symbols_map: Default::default(),
         semantic_tokens_map: Default::default(),
         zoo_client,
         can_send_telemetry: true,
         executor_ctx: Arc::new(tokio::sync::RwLock::new(executor_ctx)),
         can_execute: Arc::new(tokio::sync::RwLock::new(can_execute)),
+        lint_settings: Default::default(),
         is_initialized: Default::default(),
         current_handle: Default::default(),
     })
@@ -122,6 +125,27 @@ async fn copilot_lsp_server() -> Result<crate::lsp::copilot::Backend> {
     Ok(server.clone())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 12)]
+async fn test_kcl_lsp_project_directory() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Imports are relative to the closest directory with a `project.toml`, or else the file's own
+    // directory. The space checks that paths are decoded from the URIs.
+    let dir = std::env::temp_dir().join(format!("kcl_lsp_project_{}", uuid::Uuid::new_v4()));
+    let project = dir.join("my project");
+    std::fs::create_dir_all(project.join("parts")).unwrap();
+    std::fs::write(project.join("project.toml"), "").unwrap();
+    let in_project = server
+        .project_directory(&tower_lsp::lsp_types::Url::from_file_path(project.join("parts").join("main.kcl")).unwrap())
+        .await;
+    let elsewhere = server
+        .project_directory(&tower_lsp::lsp_types::Url::from_file_path(dir.join("elsewhere").join("main.kcl")).unwrap())
+        .await;
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(in_project, Some(project.display().to_string()));
+    assert_eq!(elsewhere, Some(dir.join("elsewhere").display().to_string()));
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 12)]
 async fn test_updating_kcl_lsp_files() {
     let server = kcl_lsp_server(false).await.unwrap();
@@ -156,7 +180,7 @@ async fn test_updating_kcl_lsp_files() {
         }
     );
 
-    assert_eq!(server.code_map.len().await, 10);
+    assert_eq!(server.code_map.len().await, 14);
 
     // Run open file.
     server
@@ -172,7 +196,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 11);
+    assert_eq!(server.code_map.len().await, 15);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -189,7 +213,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 11);
+    assert_eq!(server.code_map.len().await, 15);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -209,7 +233,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -236,7 +260,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -258,7 +282,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -279,7 +303,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 13);
+    assert_eq!(server.code_map.len().await, 17);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -301,7 +325,7 @@ async fn test_updating_kcl_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -336,7 +360,7 @@ async fn test_updating_kcl_lsp_files() {
     );
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -373,7 +397,7 @@ async fn test_updating_kcl_lsp_files() {
             name: "my-project2".to_string(),
         }
     );
-    assert_eq!(server.code_map.len().await, 10);
+    assert_eq!(server.code_map.len().await, 14);
     // Just make sure that one of the current files read from disk is accurate.
     assert_eq!(
         server.code_map.get(&format!("{}/util.rs", string_path)).await.unwrap(),
@@ -415,7 +439,7 @@ async fn test_updating_copilot_lsp_files() {
         }
     );
 
-    assert_eq!(server.code_map.len().await, 10);
+    assert_eq!(server.code_map.len().await, 14);
 
     // Run open file.
     server
@@ -431,7 +455,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 11);
+    assert_eq!(server.code_map.len().await, 15);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -447,7 +471,7 @@ async fn test_updating_copilot_lsp_files() {
         .await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 11);
+    assert_eq!(server.code_map.len().await, 15);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -467,7 +491,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -494,7 +518,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -516,7 +540,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -537,7 +561,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 13);
+    assert_eq!(server.code_map.len().await, 17);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -559,7 +583,7 @@ async fn test_updating_copilot_lsp_files() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -594,7 +618,7 @@ async fn test_updating_copilot_lsp_files() {
     );
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -626,7 +650,7 @@ async fn test_updating_copilot_lsp_files() {
     );
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 12);
+    assert_eq!(server.code_map.len().await, 16);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -663,7 +687,7 @@ async fn test_updating_copilot_lsp_files() {
             name: "my-project2".to_string(),
         }
     );
-    assert_eq!(server.code_map.len().await, 10);
+    assert_eq!(server.code_map.len().await, 14);
 }
 
 #[tokio::test(flavor = "multi_thread")]
@@ -700,7 +724,7 @@ async fn test_kcl_lsp_create_zip() {
         }
     );
 
-    assert_eq!(server.code_map.len().await, 10);
+    assert_eq!(server.code_map.len().await, 14);
 
     // Run open file.
     server
@@ -716,7 +740,7 @@ async fn test_kcl_lsp_create_zip() {
     server.wait_on_handle().await;
 
     // Check the code map.
-    assert_eq!(server.code_map.len().await, 11);
+    assert_eq!(server.code_map.len().await, 15);
     assert_eq!(
         server.code_map.get("file:///test.kcl").await.unwrap(),
         "test".as_bytes()
@@ -740,12 +764,103 @@ async fn test_kcl_lsp_create_zip() {
         files.insert(file.name().to_string(), file.size());
     }
 
-    assert_eq!(files.len(), 11);
+    assert_eq!(files.len(), 15);
     let util_path = format!("{}/util.rs", string_path).replace("file://", "");
     assert!(files.contains_key(&util_path));
     assert_eq!(files.get("/test.kcl"), Some(&4));
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_workspace_symbols() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Make a project with kcl files in it, which aren't open.
+    let project_dir = std::env::temp_dir().join(format!("test-symbols-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(project_dir.join("parts")).unwrap();
+    std::fs::write(
+        project_dir.join("parts").join("bolts.kcl"),
+        "const m5Bolt = { length: 10 }\nconst m6Bolt = { length: 12 }\n",
+    )
+    .unwrap();
+    std::fs::write(project_dir.join("main.kcl"), "const width = 5\n").unwrap();
+    std::fs::write(project_dir.join("notes.txt"), "const m5Notes = 1\n").unwrap();
+    let project_uri = format!("file://{}", project_dir.display());
+
+    server
+        .did_change_workspace_folders(tower_lsp::lsp_types::DidChangeWorkspaceFoldersParams {
+            event: tower_lsp::lsp_types::WorkspaceFoldersChangeEvent {
+                added: vec![tower_lsp::lsp_types::WorkspaceFolder {
+                    uri: project_uri.as_str().try_into().unwrap(),
+                    name: "my-project".to_string(),
+                }],
+                removed: vec![],
+            },
+        })
+        .await;
+
+    let find = |query: &str| {
+        let server = &server;
+        let query = query.to_string();
+        async move {
+            server
+                .symbol(tower_lsp::lsp_types::WorkspaceSymbolParams {
+                    query,
+                    work_done_progress_params: Default::default(),
+                    partial_result_params: Default::default(),
+                })
+                .await
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|symbol| (symbol.name, symbol.location.uri.to_string()))
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let bolts_uri = format!("{}/parts/bolts.kcl", project_uri);
+    assert_eq!(find("m5b").await, vec![("m5Bolt".to_string(), bolts_uri.clone())]);
+    assert_eq!(find("width").await.len(), 1);
+
+    // Renaming the folder moves its symbols.
+    std::fs::rename(project_dir.join("parts"), project_dir.join("hardware")).unwrap();
+    server
+        .did_rename_files(tower_lsp::lsp_types::RenameFilesParams {
+            files: vec![tower_lsp::lsp_types::FileRename {
+                old_uri: format!("{}/parts", project_uri),
+                new_uri: format!("{}/hardware", project_uri),
+            }],
+        })
+        .await;
+    assert_eq!(
+        find("m5b").await,
+        vec![("m5Bolt".to_string(), format!("{}/hardware/bolts.kcl", project_uri))]
+    );
+
+    // Changes on disk are indexed.
+    let main_uri = format!("{}/main.kcl", project_uri);
+    std::fs::write(project_dir.join("main.kcl"), "const height = 5\n").unwrap();
+    server
+        .did_change_watched_files(tower_lsp::lsp_types::DidChangeWatchedFilesParams {
+            changes: vec![tower_lsp::lsp_types::FileEvent {
+                uri: main_uri.as_str().try_into().unwrap(),
+                typ: tower_lsp::lsp_types::FileChangeType::CHANGED,
+            }],
+        })
+        .await;
+    assert_eq!(find("width").await, vec![]);
+    assert_eq!(find("height").await, vec![("height".to_string(), main_uri.clone())]);
+
+    // Deleted files have no symbols.
+    server
+        .did_delete_files(tower_lsp::lsp_types::DeleteFilesParams {
+            files: vec![tower_lsp::lsp_types::FileDelete { uri: main_uri }],
+        })
+        .await;
+    assert_eq!(find("height").await, vec![]);
+
+    std::fs::remove_dir_all(project_dir).unwrap();
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_kcl_lsp_completions() {
     let server = kcl_lsp_server(false).await.unwrap();
@@ -839,6 +954,84 @@ async fn test_kcl_lsp_completions_const_raw() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_completions_in_context() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, 10], %, 'seg01')
+  |> close(%)
+  |> extrude(5, %)
+  |> fillet({ r }, %)"#
+                    .to_string(),
+            },
+        })
+        .await;
+
+    let completion_labels = |line: u32, character: u32| {
+        let server = &server;
+        async move {
+            let completions = server
+                .completion(tower_lsp::lsp_types::CompletionParams {
+                    text_document_position: tower_lsp::lsp_types::TextDocumentPositionParams {
+                        text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                            uri: "file:///test.kcl".try_into().unwrap(),
+                        },
+                        position: tower_lsp::lsp_types::Position { line, character },
+                    },
+                    context: None,
+                    partial_result_params: Default::default(),
+                    work_done_progress_params: Default::default(),
+                })
+                .await
+                .unwrap()
+                .unwrap();
+            let tower_lsp::lsp_types::CompletionResponse::Array(completions) = completions else {
+                panic!("Expected array of completions");
+            };
+            completions
+                .into_iter()
+                .map(|completion| completion.label)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Only the keys of the fillet data go in the object.
+    assert_eq!(completion_labels(5, 15).await, vec!["radius", "tags"]);
+
+    // Finish the object, so the rest of the code parses.
+    server
+        .did_change(tower_lsp::lsp_types::DidChangeTextDocumentParams {
+            text_document: tower_lsp::lsp_types::VersionedTextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                version: 2,
+            },
+            content_changes: vec![tower_lsp::lsp_types::TextDocumentContentChangeEvent {
+                range: Some(tower_lsp::lsp_types::Range {
+                    start: tower_lsp::lsp_types::Position { line: 5, character: 14 },
+                    end: tower_lsp::lsp_types::Position { line: 5, character: 15 },
+                }),
+                range_length: None,
+                text: "radius: 1".to_string(),
+            }],
+        })
+        .await;
+
+    // Only the planes go in the string.
+    let planes = completion_labels(0, 28).await;
+    assert!(planes.contains(&"XY".to_string()));
+    assert!(planes.contains(&"-YZ".to_string()));
+    assert!(!planes.contains(&"startSketchOn".to_string()));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_kcl_lsp_on_hover() {
     let server = kcl_lsp_server(false).await.unwrap();
@@ -980,6 +1173,69 @@ async fn test_kcl_lsp_signature_help() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn serial_test_kcl_lsp_hover_user_names() {
+    let server = kcl_lsp_server(true).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"// Half of the given length.
+fn half = (length: number) => {
+  return length / 2
+}
+
+const width = half(16)
+const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width, 0], %)
+"#
+                .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let hover = |line, character| {
+        let server = &server;
+        async move {
+            let hover = server
+                .hover(tower_lsp::lsp_types::HoverParams {
+                    text_document_position_params: tower_lsp::lsp_types::TextDocumentPositionParams {
+                        text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                            uri: "file:///test.kcl".try_into().unwrap(),
+                        },
+                        position: tower_lsp::lsp_types::Position { line, character },
+                    },
+                    work_done_progress_params: Default::default(),
+                })
+                .await
+                .unwrap()
+                .unwrap();
+            let tower_lsp::lsp_types::HoverContents::Markup(markup) = hover.contents else {
+                panic!("Expected markup");
+            };
+            markup.value
+        }
+    };
+
+    assert_eq!(
+        hover(5, 15).await,
+        "```half(length: number)```\nHalf of the given length."
+    );
+    assert_eq!(hover(8, 12).await, "```width = 8```");
+    let part = hover(6, 8).await;
+    assert!(
+        part.starts_with("```part001 = sketch_group ") && part.ends_with(" with 1 paths```"),
+        "{}",
+        part
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_kcl_lsp_semantic_tokens() {
     let server = kcl_lsp_server(false).await.unwrap();
@@ -1408,6 +1664,335 @@ async fn test_kcl_lsp_rename() {
     );
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_goto_definition() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"fn square = (x) => {
+  return x * x
+}
+const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([0, square(2)], %, "thing")
+  |> line([segLen("thing", %), 0], %)
+"#
+                .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let definition = |line, character| {
+        server.goto_definition(tower_lsp::lsp_types::GotoDefinitionParams {
+            text_document_position_params: tower_lsp::lsp_types::TextDocumentPositionParams {
+                text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                    uri: "file:///test.kcl".try_into().unwrap(),
+                },
+                position: tower_lsp::lsp_types::Position { line, character },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+    };
+    let location = |line, start, end| {
+        Some(tower_lsp::lsp_types::GotoDefinitionResponse::Scalar(
+            tower_lsp::lsp_types::Location {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                range: tower_lsp::lsp_types::Range {
+                    start: tower_lsp::lsp_types::Position { line, character: start },
+                    end: tower_lsp::lsp_types::Position { line, character: end },
+                },
+            },
+        ))
+    };
+
+    // A user function call goes to the function.
+    assert_eq!(definition(5, 16).await.unwrap(), location(0, 3, 9));
+    // A parameter goes to the parameter.
+    assert_eq!(definition(1, 13).await.unwrap(), location(0, 13, 14));
+    // A tag goes to the segment it tags.
+    assert_eq!(definition(6, 20).await.unwrap(), location(5, 29, 36));
+    // A stdlib function isn't declared anywhere in the file.
+    assert_eq!(definition(6, 12).await.unwrap(), None);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_references() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const width = 4
+const area = width * width
+fn scale = (width) => {
+  return width * 2
+}"#
+                .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let references = |include_declaration| {
+        server.references(tower_lsp::lsp_types::ReferenceParams {
+            text_document_position: tower_lsp::lsp_types::TextDocumentPositionParams {
+                text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                    uri: "file:///test.kcl".try_into().unwrap(),
+                },
+                position: tower_lsp::lsp_types::Position { line: 1, character: 15 },
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: tower_lsp::lsp_types::ReferenceContext { include_declaration },
+        })
+    };
+    let location = |line, start, end| tower_lsp::lsp_types::Location {
+        uri: "file:///test.kcl".try_into().unwrap(),
+        range: tower_lsp::lsp_types::Range {
+            start: tower_lsp::lsp_types::Position { line, character: start },
+            end: tower_lsp::lsp_types::Position { line, character: end },
+        },
+    };
+
+    // The parameter of `scale` shadows the constant, so its uses aren't included.
+    assert_eq!(
+        references(false).await.unwrap(),
+        Some(vec![location(1, 13, 18), location(1, 21, 26)])
+    );
+    assert_eq!(
+        references(true).await.unwrap(),
+        Some(vec![location(0, 6, 11), location(1, 13, 18), location(1, 21, 26)])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_inlay_hints_parameter_names() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const size = 4
+const width = size * 2
+const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width, 0], %, "edge")
+"#
+                .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let hints = inlay_hints(&server).await;
+
+    // Nothing was executed, so there are no values.
+    assert_eq!(
+        hints,
+        vec![
+            (2, 30, "data:".to_string()),
+            (3, 20, "to:".to_string()),
+            (3, 28, "sketch_surface:".to_string()),
+            (4, 10, "delta:".to_string()),
+            (4, 22, "sketch_group:".to_string()),
+            (4, 25, "tag:".to_string()),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serial_test_kcl_lsp_inlay_hints_values() {
+    let server = kcl_lsp_server(true).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const size = 4
+const width = size * 2
+const part001 = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width / 2, 0], %)
+"#
+                .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let hints = inlay_hints(&server).await;
+
+    // Literals already show their value, and a declaration's value isn't repeated for its expression.
+    assert_eq!(
+        hints
+            .into_iter()
+            .filter(|(_, _, label)| label.starts_with('='))
+            .collect::<Vec<_>>(),
+        vec![(1, 22, "= 8".to_string()), (4, 20, "= 4".to_string())]
+    );
+}
+
+/// The line, character and label of every inlay hint in `file:///test.kcl`.
+async fn inlay_hints(server: &crate::lsp::kcl::Backend) -> Vec<(u32, u32, String)> {
+    let hints = server
+        .inlay_hint(tower_lsp::lsp_types::InlayHintParams {
+            text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+            },
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 0, character: 0 },
+                end: tower_lsp::lsp_types::Position { line: 10, character: 0 },
+            },
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    hints
+        .into_iter()
+        .map(|hint| {
+            let tower_lsp::lsp_types::InlayHintLabel::String(label) = hint.label else {
+                panic!("Expected a string label, got {:?}", hint.label);
+            };
+            (hint.position.line, hint.position.character, label)
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_incremental_changes() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: "const width = 4\n\nconst height = 2\n".to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // Change a value, then add a line after it.
+    server
+        .did_change(tower_lsp::lsp_types::DidChangeTextDocumentParams {
+            text_document: tower_lsp::lsp_types::VersionedTextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                version: 2,
+            },
+            content_changes: vec![
+                tower_lsp::lsp_types::TextDocumentContentChangeEvent {
+                    range: Some(tower_lsp::lsp_types::Range {
+                        start: tower_lsp::lsp_types::Position { line: 0, character: 14 },
+                        end: tower_lsp::lsp_types::Position { line: 0, character: 15 },
+                    }),
+                    range_length: None,
+                    text: "5".to_string(),
+                },
+                tower_lsp::lsp_types::TextDocumentContentChangeEvent {
+                    range: Some(tower_lsp::lsp_types::Range {
+                        start: tower_lsp::lsp_types::Position { line: 1, character: 0 },
+                        end: tower_lsp::lsp_types::Position { line: 1, character: 0 },
+                    }),
+                    range_length: None,
+                    text: "const depth = 1\n".to_string(),
+                },
+            ],
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // The changes were applied in order, and the AST is the same as parsing all of the code.
+    let code = "const width = 5\nconst depth = 1\n\nconst height = 2\n";
+    assert_eq!(server.code_map.get("file:///test.kcl").await.unwrap(), code.as_bytes());
+    let tokens = crate::token::lexer(code).unwrap();
+    let ast = crate::parser::Parser::new(tokens.clone()).ast().unwrap();
+    assert_eq!(server.token_map.get("file:///test.kcl").await.unwrap(), tokens);
+    assert_eq!(server.ast_map.get("file:///test.kcl").await.unwrap(), ast);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_incremental_changes_after_astral_characters() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: "const s = \"😀x\"\nconst t = 1\n".to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // The emoji is two UTF-16 code units, so `x` starts at character 13.
+    // The second change is past the end of its line, so it's clamped to the end.
+    server
+        .did_change(tower_lsp::lsp_types::DidChangeTextDocumentParams {
+            text_document: tower_lsp::lsp_types::VersionedTextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                version: 2,
+            },
+            content_changes: vec![
+                tower_lsp::lsp_types::TextDocumentContentChangeEvent {
+                    range: Some(tower_lsp::lsp_types::Range {
+                        start: tower_lsp::lsp_types::Position { line: 0, character: 13 },
+                        end: tower_lsp::lsp_types::Position { line: 0, character: 14 },
+                    }),
+                    range_length: None,
+                    text: "y".to_string(),
+                },
+                tower_lsp::lsp_types::TextDocumentContentChangeEvent {
+                    range: Some(tower_lsp::lsp_types::Range {
+                        start: tower_lsp::lsp_types::Position {
+                            line: 1,
+                            character: 100,
+                        },
+                        end: tower_lsp::lsp_types::Position {
+                            line: 1,
+                            character: 100,
+                        },
+                    }),
+                    range_length: None,
+                    text: "0".to_string(),
+                },
+            ],
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    assert_eq!(
+        server.code_map.get("file:///test.kcl").await.unwrap(),
+        "const s = \"😀y\"\nconst t = 10\n".as_bytes()
+    );
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_kcl_lsp_diagnostic_no_errors() {
     let server = kcl_lsp_server(false).await.unwrap();
@@ -1498,6 +2083,266 @@ async fn test_kcl_lsp_diagnostic_has_errors() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_lint_code_action() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %, "start")"#
+                    .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // Check the diagnostics.
+    let Some(tower_lsp::lsp_types::DocumentDiagnosticReport::Full(diagnostics)) =
+        server.diagnostics_map.get("file:///test.kcl").await
+    else {
+        panic!("Expected full diagnostics");
+    };
+    let diagnostics = diagnostics.full_document_diagnostic_report.items;
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "The tag `start` is never used");
+    assert_eq!(
+        diagnostics[0].severity,
+        Some(tower_lsp::lsp_types::DiagnosticSeverity::WARNING)
+    );
+
+    // Send code action request.
+    let actions = server
+        .code_action(tower_lsp::lsp_types::CodeActionParams {
+            text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+            },
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 1, character: 34 },
+                end: tower_lsp::lsp_types::Position { line: 1, character: 34 },
+            },
+            // Only ask for fixes, since refactors apply to this code too.
+            context: tower_lsp::lsp_types::CodeActionContext {
+                only: Some(vec![tower_lsp::lsp_types::CodeActionKind::QUICKFIX]),
+                ..Default::default()
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Check the code action.
+    assert_eq!(actions.len(), 1);
+    let tower_lsp::lsp_types::CodeActionOrCommand::CodeAction(action) = &actions[0] else {
+        panic!("Expected a code action");
+    };
+    assert_eq!(action.title, "Remove the unused tag");
+    let u: tower_lsp::lsp_types::Url = "file:///test.kcl".try_into().unwrap();
+    assert_eq!(
+        action
+            .edit
+            .as_ref()
+            .unwrap()
+            .changes
+            .as_ref()
+            .unwrap()
+            .get(&u)
+            .unwrap()
+            .clone(),
+        vec![tower_lsp::lsp_types::TextEdit {
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 1, character: 29 },
+                end: tower_lsp::lsp_types::Position { line: 1, character: 38 },
+            },
+            new_text: "".to_string(),
+        }]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_code_actions_fix_and_refactor() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> lineto([1, 1], %)"#
+                    .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // Send code action request.
+    let actions = server
+        .code_action(tower_lsp::lsp_types::CodeActionParams {
+            text_document: tower_lsp::lsp_types::TextDocumentIdentifier {
+                uri: "file:///test.kcl".try_into().unwrap(),
+            },
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 2, character: 6 },
+                end: tower_lsp::lsp_types::Position { line: 2, character: 6 },
+            },
+            context: Default::default(),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Check the code actions.
+    let actions: Vec<_> = actions
+        .into_iter()
+        .map(|action| {
+            let tower_lsp::lsp_types::CodeActionOrCommand::CodeAction(action) = action else {
+                panic!("Expected a code action");
+            };
+            action
+        })
+        .collect();
+    assert_eq!(
+        actions
+            .iter()
+            .map(|action| (action.title.as_str(), action.kind.clone().unwrap()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Did you mean `lineTo`?", tower_lsp::lsp_types::CodeActionKind::QUICKFIX),
+            (
+                "Extract pipe to function `makePart`",
+                tower_lsp::lsp_types::CodeActionKind::REFACTOR_EXTRACT
+            ),
+        ]
+    );
+    let u: tower_lsp::lsp_types::Url = "file:///test.kcl".try_into().unwrap();
+    assert_eq!(
+        actions[0]
+            .edit
+            .as_ref()
+            .unwrap()
+            .changes
+            .as_ref()
+            .unwrap()
+            .get(&u)
+            .unwrap()
+            .clone(),
+        vec![tower_lsp::lsp_types::TextEdit {
+            range: tower_lsp::lsp_types::Range {
+                start: tower_lsp::lsp_types::Position { line: 2, character: 5 },
+                end: tower_lsp::lsp_types::Position { line: 2, character: 11 },
+            },
+            new_text: "lineTo".to_string(),
+        }]
+    );
+    assert_eq!(
+        actions[1]
+            .edit
+            .as_ref()
+            .unwrap()
+            .changes
+            .as_ref()
+            .unwrap()
+            .get(&u)
+            .unwrap()[0]
+            .new_text,
+        r#"fn makePart = () => {
+  return startSketchOn('XY')
+    |> startProfileAt([0, 0], %)
+    |> lineto([1, 1], %)
+}
+const part = makePart()"#
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_kcl_lsp_lint_settings_from_configuration() {
+    let server = kcl_lsp_server(false).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"const my_thing = 1"#.to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    let lint_diagnostics = || async {
+        let Some(tower_lsp::lsp_types::DocumentDiagnosticReport::Full(diagnostics)) =
+            server.diagnostics_map.get("file:///test.kcl").await
+        else {
+            panic!("Expected full diagnostics");
+        };
+        diagnostics
+            .full_document_diagnostic_report
+            .items
+            .into_iter()
+            .map(|diagnostic| (diagnostic.message, diagnostic.severity))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        lint_diagnostics().await,
+        vec![(
+            "`my_thing` should be camelCase, e.g. `myThing`".to_string(),
+            Some(tower_lsp::lsp_types::DiagnosticSeverity::WARNING)
+        )]
+    );
+
+    // Make the rule an error, and a magic number a hint.
+    server
+        .did_change_configuration(tower_lsp::lsp_types::DidChangeConfigurationParams {
+            settings: serde_json::json!({
+                "settings": {
+                    "lint": {
+                        "camel_case": "error",
+                        "magic_number": "hint",
+                    }
+                }
+            }),
+        })
+        .await;
+    assert_eq!(
+        lint_diagnostics().await,
+        vec![(
+            "`my_thing` should be camelCase, e.g. `myThing`".to_string(),
+            Some(tower_lsp::lsp_types::DiagnosticSeverity::ERROR)
+        )]
+    );
+    // Lints don't count as errors which need the code to be executed again.
+    assert!(!server.has_diagnostics("file:///test.kcl").await);
+
+    // Turn the rule off.
+    server
+        .did_change_configuration(tower_lsp::lsp_types::DidChangeConfigurationParams {
+            settings: serde_json::json!({
+                "settings": {
+                    "lint": {
+                        "camel_case": "off",
+                    }
+                }
+            }),
+        })
+        .await;
+    assert_eq!(lint_diagnostics().await, vec![]);
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_copilot_lsp_set_editor_info() {
     let server = copilot_lsp_server().await.unwrap();
@@ -2843,3 +3688,39 @@ async fn test_kcl_lsp_folding() {
         }
     );
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn serial_test_kcl_lsp_type_errors_dont_stop_execution() {
+    let server = kcl_lsp_server(true).await.unwrap();
+
+    // Send open file.
+    server
+        .did_open(tower_lsp::lsp_types::DidOpenTextDocumentParams {
+            text_document: tower_lsp::lsp_types::TextDocumentItem {
+                uri: "file:///test.kcl".try_into().unwrap(),
+                language_id: "kcl".to_string(),
+                version: 1,
+                text: r#"fn name = () => string { return 1 + 2 }
+const x = 5"#
+                    .to_string(),
+            },
+        })
+        .await;
+    server.wait_on_handle().await;
+
+    // The type error is reported.
+    let diagnostics = server.diagnostics_map.get("file:///test.kcl").await.unwrap().clone();
+    if let tower_lsp::lsp_types::DocumentDiagnosticReport::Full(diagnostics) = diagnostics {
+        assert!(diagnostics
+            .full_document_diagnostic_report
+            .items
+            .iter()
+            .any(|diagnostic| diagnostic.message.contains("was declared to return `string`")));
+    } else {
+        panic!("Expected full diagnostics");
+    }
+
+    // But the program still ran.
+    let memory = server.memory_map.get("file:///test.kcl").await.unwrap().clone();
+    assert!(memory.get("x", crate::executor::SourceRange::default()).is_ok());
+}
@@ -1,12 +1,56 @@
 //! Utility functions for working with ropes and positions.
 
+use std::path::{Path, PathBuf};
+
 use ropey::Rope;
-use tower_lsp::lsp_types::Position;
+use tower_lsp::lsp_types::{Position, Url};
 
 pub fn position_to_offset(position: Position, rope: &Rope) -> Option<usize> {
     Some(rope.try_line_to_char(position.line as usize).ok()? + position.character as usize)
 }
 
+/// Convert a position from the client, whose character counts UTF-16 code units, to a char index.
+/// Characters past the end of the line are clamped to it, as the LSP spec asks.
+pub fn utf16_position_to_char(position: Position, rope: &Rope) -> Option<usize> {
+    let line_start = rope.try_line_to_char(position.line as usize).ok()?;
+    let Some(line) = rope.get_line(position.line as usize) else {
+        // The position is just past the last line.
+        return Some(line_start);
+    };
+    let mut line_len = line.len_chars();
+    while line_len > 0 && matches!(line.char(line_len - 1), '\n' | '\r') {
+        line_len -= 1;
+    }
+    let character = (position.character as usize).min(line.char_to_utf16_cu(line_len));
+    Some(line_start + line.utf16_cu_to_char(character))
+}
+
+/// The path of the file or directory a URI points to, with its percent-encoding decoded.
+pub fn uri_to_path(uri: &Url) -> PathBuf {
+    // `Url::to_file_path` isn't available in wasm, so decode the path ourselves there.
+    #[cfg(not(target_arch = "wasm32"))]
+    let path = uri.to_file_path().ok();
+    #[cfg(target_arch = "wasm32")]
+    let path = None;
+    let path = path.unwrap_or_else(|| {
+        PathBuf::from(
+            percent_encoding::percent_decode_str(uri.path())
+                .decode_utf8_lossy()
+                .into_owned(),
+        )
+    });
+    // Drop any trailing slash.
+    path.components().collect()
+}
+
+/// The `file://` URI of a path.
+pub fn path_to_uri(path: &Path) -> Option<Url> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Url::from_file_path(path).ok();
+    #[cfg(target_arch = "wasm32")]
+    return Url::parse(&format!("file://{}", path.display())).ok();
+}
+
 pub fn get_text_before(offset: usize, rope: &Rope) -> Option<String> {
     if offset == 0 {
         return Some("".to_string());
@@ -5,6 +5,7 @@ use crate::{
     token::{Token, TokenType},
 };
 
+pub mod incremental;
 mod math;
 pub(crate) mod parser_impl;
 
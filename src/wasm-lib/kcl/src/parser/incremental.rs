@@ -0,0 +1,247 @@
+//! Parsing code again after an edit, without lexing and parsing the parts the edit didn't touch.
+//!
+//! The language server parses on every keystroke, so for large files it only lexes and parses the
+//! top level items around the edit, and reuses the tokens and items before and after them.
+
+use crate::{
+    ast::types::Program,
+    executor::SourceRange,
+    parser::Parser,
+    token::{Token, TokenType},
+};
+
+/// Code, along with its tokens and the program parsed from them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed {
+    pub code: String,
+    pub tokens: Vec<Token>,
+    pub program: Program,
+}
+
+impl Parsed {
+    /// Lex and parse all of the code.
+    pub fn new(code: &str) -> Result<Self, crate::errors::KclError> {
+        let tokens = crate::token::lexer(code)?;
+        let program = Parser::new(tokens.clone()).ast()?;
+        Ok(Self {
+            code: code.to_string(),
+            tokens,
+            program,
+        })
+    }
+}
+
+/// Parse `code`, an edited version of the previously parsed code, lexing and parsing only the top
+/// level items the edit touched. The other items keep their tokens and nodes, moved to where they
+/// are in the new code.
+///
+/// Returns `None` if the edited items don't parse on their own, or could join onto the items around
+/// them, in which case the whole code needs parsing, which also finds any errors.
+pub fn reparse(previous: &Parsed, code: &str) -> Option<Parsed> {
+    let old_code = previous.code.as_str();
+    let body = &previous.program.body;
+    if body.is_empty() {
+        return None;
+    }
+
+    // Only the bytes between the common prefix and suffix were edited.
+    let prefix = common_prefix(old_code, code);
+    let suffix = common_suffix(&old_code[prefix..], &code[prefix..]);
+    let edit_end = old_code.len() - suffix;
+    let offset = code.len() as isize - old_code.len() as isize;
+
+    // Each item owns the code from its start to the start of the next item, including the ends,
+    // since typing right after an item can continue it. The first item also owns the code before it.
+    let starts: Vec<usize> = body
+        .iter()
+        .enumerate()
+        .map(|(index, item)| if index == 0 { 0 } else { SourceRange::from(item).start() })
+        .collect();
+    let end_of = |index: usize| starts.get(index + 1).copied().unwrap_or(old_code.len());
+    let first = (0..body.len()).find(|&index| prefix <= end_of(index))?;
+    let last = (0..body.len()).rev().find(|&index| starts[index] <= edit_end)?;
+    if first == 0 && last == body.len() - 1 {
+        // Everything changed.
+        return None;
+    }
+
+    // The items after the edited ones must still start where a new item can, rather than continuing
+    // the last edited item, e.g. with `(` or `|>`. The same goes for the first edited item.
+    let starts_item = |tokens: &[Token], start: usize| {
+        tokens
+            .iter()
+            .any(|token| token.start == start && matches!(token.token_type, TokenType::Keyword | TokenType::Word))
+    };
+    let region_start = starts[first];
+    let old_region_end = end_of(last);
+    if (first > 0 && !starts_item(&previous.tokens, region_start))
+        || (last < body.len() - 1 && !starts_item(&previous.tokens, old_region_end))
+    {
+        return None;
+    }
+    let region_end = old_region_end.checked_add_signed(offset)?;
+
+    let mut region_tokens = crate::token::lexer(&code[region_start..region_end]).ok()?;
+    for token in &mut region_tokens {
+        token.start += region_start;
+        token.end += region_start;
+    }
+    if first > 0 && !starts_item(&region_tokens, region_start) {
+        return None;
+    }
+    let region = Parser::new(region_tokens.clone()).ast().ok()?;
+    // Comments and blank lines before the first edited item belong to the item before it, and
+    // code which is only comments is parsed as nothing at all.
+    if region.body.is_empty() || (first > 0 && !region.non_code_meta.start.is_empty()) {
+        return None;
+    }
+
+    let tokens = previous
+        .tokens
+        .iter()
+        .take_while(|token| token.end <= region_start)
+        .cloned()
+        .chain(region_tokens)
+        .chain(
+            previous
+                .tokens
+                .iter()
+                .skip_while(|token| token.start < old_region_end)
+                .map(|token| {
+                    let mut token = token.clone();
+                    token.start = token.start.saturating_add_signed(offset);
+                    token.end = token.end.saturating_add_signed(offset);
+                    token
+                }),
+        )
+        .collect();
+
+    let mut program = previous.program.clone();
+    let mut after: Vec<_> = program.body.drain(first..).skip(last + 1 - first).collect();
+    for item in &mut after {
+        item.shift_source_ranges(offset);
+    }
+    let added = region.body.len() as isize - (last + 1 - first) as isize;
+    program.body.extend(region.body);
+    program.body.extend(after);
+
+    // Non-code nodes are kept by the index of the item they come after.
+    let old_nodes = std::mem::take(&mut program.non_code_meta.non_code_nodes);
+    for (index, mut nodes) in old_nodes {
+        if index < first {
+            program.non_code_meta.non_code_nodes.insert(index, nodes);
+        } else if index > last {
+            for node in &mut nodes {
+                node.shift_source_ranges(offset);
+            }
+            program
+                .non_code_meta
+                .non_code_nodes
+                .insert(index.checked_add_signed(added)?, nodes);
+        }
+    }
+    for (index, nodes) in region.non_code_meta.non_code_nodes {
+        program.non_code_meta.non_code_nodes.insert(first + index, nodes);
+    }
+    if first == 0 {
+        program.non_code_meta.start = region.non_code_meta.start;
+        program.start = region.start;
+    }
+    program.end = if last == body.len() - 1 {
+        region.end
+    } else {
+        program.end.saturating_add_signed(offset)
+    };
+
+    Some(Parsed {
+        code: code.to_string(),
+        tokens,
+        program,
+    })
+}
+
+/// The length in bytes of the start the two strings have in common, up to a whole character.
+fn common_prefix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().zip(b.bytes()).take_while(|(a, b)| a == b).count();
+    while !a.is_char_boundary(len) {
+        len -= 1;
+    }
+    len
+}
+
+/// The length in bytes of the end the two strings have in common, up to a whole character.
+fn common_suffix(a: &str, b: &str) -> usize {
+    let mut len = a.bytes().rev().zip(b.bytes().rev()).take_while(|(a, b)| a == b).count();
+    while !a.is_char_boundary(a.len() - len) {
+        len -= 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const CODE: &str = r#"// The width of the part.
+const width = 4
+
+const part = startSketchOn('XY')
+  |> startProfileAt([0, 0], %)
+  |> line([width, 0], %) // The bottom.
+  |> close(%)
+
+// Make it solid.
+const solid = extrude(2, part)
+"#;
+
+    /// Replace the first `old` in the code with `new`, and reparse it.
+    fn edit(old: &str, new: &str) -> (Option<Parsed>, String) {
+        let previous = Parsed::new(CODE).unwrap();
+        let code = CODE.replacen(old, new, 1);
+        (reparse(&previous, &code), code)
+    }
+
+    #[test]
+    fn reparse_same_as_parse() {
+        for (old, new) in [
+            // Inside an item.
+            ("[width, 0]", "[width * 2, 0]"),
+            // At the end of an item.
+            ("= 4", "= 42"),
+            // Between items.
+            ("\n// Make it solid.", "\nconst height = 3\n// Make it solid."),
+            // Removing an item.
+            (
+                "const part = startSketchOn('XY')\n  |> startProfileAt([0, 0], %)\n  |> line([width, 0], %) // The bottom.\n  |> close(%)\n\n",
+                "",
+            ),
+            // The comments at the start.
+            ("The width", "How wide"),
+            // The last item.
+            ("extrude(2", "extrude(3"),
+            // Multi-byte characters.
+            ("The bottom.", "The bottom 📐."),
+        ] {
+            let (reparsed, code) = edit(old, new);
+            assert_eq!(
+                reparsed,
+                Some(Parsed::new(&code).unwrap()),
+                "replacing {:?} with {:?}",
+                old,
+                new
+            );
+        }
+    }
+
+    #[test]
+    fn reparse_needs_whole_parse() {
+        // The edited item doesn't parse on its own.
+        let (reparsed, _) = edit("const part = ", "const part = (");
+        assert_eq!(reparsed, None);
+        // Nothing is left to reuse.
+        let (reparsed, _) = edit(CODE, "const width = 5\n");
+        assert_eq!(reparsed, None);
+    }
+}
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use winnow::{
-    combinator::{alt, delimited, opt, peek, preceded, repeat, separated, terminated},
+    combinator::{alt, cut_err, delimited, opt, peek, preceded, repeat, separated, terminated},
     dispatch,
     error::{ErrMode, StrContext, StrContextValue},
     prelude::*,
@@ -10,11 +10,12 @@ use winnow::{
 
 use crate::{
     ast::types::{
-        ArrayExpression, BinaryExpression, BinaryOperator, BinaryPart, BodyItem, CallExpression, CommentStyle,
-        ExpressionStatement, FnArgPrimitive, FnArgType, FunctionExpression, Identifier, Literal, LiteralIdentifier,
-        LiteralValue, MemberExpression, MemberObject, NonCodeMeta, NonCodeNode, NonCodeValue, ObjectExpression,
-        ObjectProperty, Parameter, PipeExpression, PipeSubstitution, Program, ReturnStatement, UnaryExpression,
-        UnaryOperator, Value, VariableDeclaration, VariableDeclarator, VariableKind,
+        ArrayExpression, BinaryExpression, BinaryOperator, BinaryPart, BodyItem, CallExpression, CommentStyle, ElseIf,
+        ExpressionStatement, FnArgPrimitive, FnArgType, ForIterable, ForStatement, FunctionExpression, Identifier,
+        IfExpression, ImportItem, ImportStatement, ItemVisibility, Literal, LiteralIdentifier, LiteralValue,
+        MemberExpression, MemberObject, NonCodeMeta, NonCodeNode, NonCodeValue, ObjectExpression, ObjectProperty,
+        Parameter, PipeExpression, PipeSubstitution, Program, ReturnStatement, UnaryExpression, UnaryOperator, Value,
+        VariableDeclaration, VariableDeclarator, VariableKind,
     },
     errors::{KclError, KclErrorDetails},
     executor::SourceRange,
@@ -293,6 +294,14 @@ fn binary_operator(i: TokenSlice) -> PResult<BinaryOperator> {
             "*" => BinaryOperator::Mul,
             "%" => BinaryOperator::Mod,
             "^" => BinaryOperator::Pow,
+            "==" => BinaryOperator::Eq,
+            "!=" => BinaryOperator::Neq,
+            "<" => BinaryOperator::Lt,
+            "<=" => BinaryOperator::Lte,
+            ">" => BinaryOperator::Gt,
+            ">=" => BinaryOperator::Gte,
+            "&&" => BinaryOperator::And,
+            "||" => BinaryOperator::Or,
             _ => {
                 return Err(KclError::Syntax(KclErrorDetails {
                     source_ranges: token.as_source_ranges(),
@@ -302,7 +311,7 @@ fn binary_operator(i: TokenSlice) -> PResult<BinaryOperator> {
         };
         Ok(op)
     })
-    .context(expected("a binary operator (like + or *)"))
+    .context(expected("a binary operator (like + or ==)"))
     .parse_next(i)
 }
 
@@ -320,7 +329,8 @@ fn operand(i: TokenSlice) -> PResult<BinaryPart> {
                 | Value::PipeExpression(_)
                 | Value::PipeSubstitution(_)
                 | Value::ArrayExpression(_)
-                | Value::ObjectExpression(_) => {
+                | Value::ObjectExpression(_)
+                | Value::IfExpression(_) => {
                     return Err(KclError::Syntax(KclErrorDetails {
                         source_ranges,
                         message: TODO_783.to_owned(),
@@ -693,6 +703,13 @@ fn body_items_within_function(i: TokenSlice) -> PResult<WithinFunction> {
             (declaration.map(BodyItem::VariableDeclaration), opt(noncode_just_after_code)).map(WithinFunction::BodyItem),
         Token { ref value, .. } if value == "return" =>
             (return_stmt.map(BodyItem::ReturnStatement), opt(noncode_just_after_code)).map(WithinFunction::BodyItem),
+        Token { ref value, .. } if value == "for" =>
+            (for_stmt.map(|f| BodyItem::ForStatement(Box::new(f))), opt(noncode_just_after_code)).map(WithinFunction::BodyItem),
+        // `import` and `export` aren't keywords, so fall back to an expression if they're used as names.
+        Token { ref value, .. } if value == "import" =>
+            (alt((import_stmt.map(|i| BodyItem::ImportStatement(Box::new(i))), expression.map(BodyItem::ExpressionStatement))), opt(noncode_just_after_code)).map(WithinFunction::BodyItem),
+        Token { ref value, .. } if value == "export" =>
+            (alt((declaration.map(BodyItem::VariableDeclaration), expression.map(BodyItem::ExpressionStatement))), opt(noncode_just_after_code)).map(WithinFunction::BodyItem),
         token if !token.is_code_token() => {
             non_code_node.map(WithinFunction::NonCode)
         },
@@ -870,6 +887,224 @@ pub fn return_stmt(i: TokenSlice) -> PResult<ReturnStatement> {
     })
 }
 
+/// Parse the given keyword, e.g. `if` or `else`.
+fn keyword<'i>(expected: &'static str) -> impl FnMut(TokenSlice<'_, 'i>) -> PResult<Token> {
+    move |i: TokenSlice| {
+        any.try_map(|token: Token| {
+            if matches!(token.token_type, TokenType::Keyword) && token.value == expected {
+                Ok(token)
+            } else {
+                Err(KclError::Syntax(KclErrorDetails {
+                    source_ranges: token.as_source_ranges(),
+                    message: format!("expected the '{}' keyword, found {}", expected, token.value.as_str()),
+                }))
+            }
+        })
+        .parse_next(i)
+    }
+}
+
+/// Matches a word token with the given value, e.g. `from`.
+/// Unlike keywords, these words can still be used as names elsewhere.
+fn word<'i>(expected: &'static str) -> impl FnMut(TokenSlice<'_, 'i>) -> PResult<Token> {
+    move |i: TokenSlice| {
+        any.verify(|token: &Token| matches!(token.token_type, TokenType::Word) && token.value == expected)
+            .parse_next(i)
+    }
+}
+
+/// Parse a block of code in braces, e.g. the branches of an if expression.
+/// Unlike a function body, the block may be empty.
+/// Returns the block and the end of its closing brace.
+fn block(i: TokenSlice) -> PResult<(Program, usize)> {
+    let open = open_brace(i)?;
+    let body = opt(function_body).parse_next(i)?;
+    ignore_whitespace(i);
+    let close = close_brace(i)?;
+    let body = body.unwrap_or_else(|| Program {
+        start: open.end,
+        end: close.start,
+        body: Vec::new(),
+        non_code_meta: NonCodeMeta::default(),
+    });
+    Ok((body, close.end))
+}
+
+/// The condition and block of an `if` or `else if`, i.e. everything after the keyword.
+fn if_branch(i: TokenSlice) -> PResult<(Value, Program, usize)> {
+    require_whitespace(i)?;
+    let cond = value
+        .context(expected("the condition of an if expression, e.g. 'x' or 'true'"))
+        .parse_next(i)?;
+    ignore_whitespace(i);
+    let (then_val, end) = block
+        .context(expected("a block of code in braces, e.g. '{ 1 }'"))
+        .parse_next(i)?;
+    Ok((cond, then_val, end))
+}
+
+/// E.g. ` else if x { ... }`
+fn else_if(i: TokenSlice) -> PResult<ElseIf> {
+    let start = preceded(opt(whitespace), keyword("else")).parse_next(i)?.start;
+    require_whitespace(i)?;
+    keyword("if").parse_next(i)?;
+    // Once we've seen `else if`, this must be an else-if branch, so don't backtrack.
+    let (cond, then_val, end) = cut_err(if_branch).parse_next(i)?;
+    Ok(ElseIf {
+        start,
+        end,
+        cond,
+        then_val,
+    })
+}
+
+/// E.g. `if x { 1 } else if y { 2 } else { 3 }`
+fn if_expr(i: TokenSlice) -> PResult<IfExpression> {
+    let start = keyword("if")
+        .context(expected("the 'if' keyword, which starts an if expression"))
+        .parse_next(i)?
+        .start;
+    // Nothing else starts with `if`, so any error from here on is the user's mistake.
+    let (cond, then_val, mut end) = cut_err(if_branch).parse_next(i)?;
+    let else_ifs: Vec<ElseIf> = repeat(0.., else_if).parse_next(i)?;
+    if let Some(last) = else_ifs.last() {
+        end = last.end;
+    }
+    let final_else = opt(preceded(
+        (opt(whitespace), keyword("else"), opt(whitespace)),
+        cut_err(block),
+    ))
+    .parse_next(i)?;
+    let final_else = final_else.map(|(block, block_end)| {
+        end = block_end;
+        block
+    });
+    Ok(IfExpression {
+        start,
+        end,
+        cond,
+        then_val,
+        else_ifs,
+        final_else,
+    })
+}
+
+/// E.g. `[0..n]`, in a for loop.
+fn for_range(i: TokenSlice) -> PResult<ForIterable> {
+    let start = open_bracket(i)?.start;
+    ignore_whitespace(i);
+    let from = value(i)?;
+    ignore_whitespace(i);
+    double_period(i)?;
+    ignore_whitespace(i);
+    let to = value(i)?;
+    ignore_whitespace(i);
+    let end = close_bracket(i)?.end;
+    Ok(ForIterable::Range { start, end, from, to })
+}
+
+/// The `in` between a for loop's variable and what it iterates over.
+fn in_word(i: TokenSlice) -> PResult<Token> {
+    word("in")
+        .context(expected("the word 'in', e.g. 'for i in [0..10]'"))
+        .parse_next(i)
+}
+
+/// E.g. `for i in [0..n] { ... }` or `for point in points { ... }`
+fn for_stmt(i: TokenSlice) -> PResult<ForStatement> {
+    let start = keyword("for")
+        .context(expected("the 'for' keyword, which starts a loop"))
+        .parse_next(i)?
+        .start;
+    // Nothing else starts with `for`, so any error from here on is the user's mistake.
+    let (variable, iterable, (body, end)) = cut_err((
+        preceded(require_whitespace, binding_name),
+        preceded(
+            (require_whitespace, in_word, require_whitespace),
+            alt((for_range, value.map(|value| ForIterable::Array { value }))),
+        ),
+        preceded(
+            opt(whitespace),
+            block.context(expected("the body of the loop, in braces")),
+        ),
+    ))
+    .parse_next(i)?;
+
+    if let Some(BodyItem::ReturnStatement(return_statement)) = body
+        .body
+        .iter()
+        .find(|item| matches!(item, BodyItem::ReturnStatement(_)))
+    {
+        return Err(ErrMode::Cut(
+            KclError::Syntax(KclErrorDetails {
+                source_ranges: vec![SourceRange([return_statement.start, return_statement.end])],
+                message: "you can't return from inside a for loop".to_owned(),
+            })
+            .into(),
+        ));
+    }
+
+    Ok(ForStatement {
+        start,
+        end,
+        variable,
+        iterable,
+        body,
+    })
+}
+
+/// Parse one name in an import statement, e.g. `sphere` or `sphere as ball`.
+fn import_item(i: TokenSlice) -> PResult<ImportItem> {
+    let name = binding_name
+        .context(expected("the name of something the other file exports"))
+        .parse_next(i)?;
+    let alias = opt(preceded(
+        (require_whitespace, word("as"), require_whitespace),
+        binding_name.context(expected("the name to import it as")),
+    ))
+    .parse_next(i)?;
+    Ok(ImportItem {
+        start: name.start,
+        end: alias.as_ref().unwrap_or(&name).end,
+        name,
+        alias,
+    })
+}
+
+/// E.g. `import { cube, sphere as ball } from "shapes.kcl"`
+fn import_stmt(i: TokenSlice) -> PResult<ImportStatement> {
+    let start = word("import")
+        .context(expected("the word 'import'"))
+        .parse_next(i)?
+        .start;
+    // `import("part.obj")` calls the stdlib function that loads CAD files,
+    // so this is only an import statement if a brace comes next.
+    preceded(opt(whitespace), open_brace).parse_next(i)?;
+    let (items, path) = cut_err((
+        delimited(
+            opt(whitespace),
+            separated(1.., import_item, comma_sep),
+            (opt(comma), opt(whitespace), close_brace),
+        ),
+        preceded(
+            (require_whitespace, word("from"), require_whitespace),
+            string_literal.context(expected("the path of the file to import from, in quotes")),
+        ),
+    ))
+    .parse_next(i)?;
+
+    let end = path.end;
+    let LiteralValue::String(path) = path.value else {
+        unreachable!("string_literal only parses strings");
+    };
+    Ok(ImportStatement {
+        start,
+        end,
+        items,
+        path,
+    })
+}
+
 /// Parse a KCL value
 fn value(i: TokenSlice) -> PResult<Value> {
     alt((
@@ -909,6 +1144,7 @@ fn value_allowed_in_pipe_expr(i: TokenSlice) -> PResult<Value> {
         array.map(Box::new).map(Value::ArrayExpression),
         object.map(Box::new).map(Value::ObjectExpression),
         pipe_sub.map(Box::new).map(Value::PipeSubstitution),
+        if_expr.map(Box::new).map(Value::IfExpression),
         function_expression.map(Box::new).map(Value::FunctionExpression),
         unnecessarily_bracketed,
     ))
@@ -936,6 +1172,7 @@ fn possible_operands(i: TokenSlice) -> PResult<Value> {
 /// Parse a variable/constant declaration.
 fn declaration(i: TokenSlice) -> PResult<VariableDeclaration> {
     const EXPECTED: &str = "expected a variable declaration keyword (e.g. 'let') but found";
+    let export = opt(terminated(word("export"), require_whitespace)).parse_next(i)?;
     let (kind, start, dec_end) = any
         .try_map(|token: Token| {
             let Some(kind) = token.declaration_keyword() else {
@@ -995,6 +1232,10 @@ fn declaration(i: TokenSlice) -> PResult<VariableDeclaration> {
     .map_err(|e| e.cut())?;
 
     let end = val.end();
+    let (start, visibility) = match export {
+        Some(export) => (export.start, ItemVisibility::Export),
+        None => (start, ItemVisibility::Default),
+    };
     Ok(VariableDeclaration {
         start,
         end,
@@ -1004,6 +1245,7 @@ fn declaration(i: TokenSlice) -> PResult<VariableDeclaration> {
             id,
             init: val,
         }],
+        visibility,
         kind,
     })
 }
@@ -1057,7 +1299,7 @@ fn unary_expression(i: TokenSlice) -> PResult<UnaryExpression> {
     let (operator, op_token) = any
         .try_map(|token: Token| match token.token_type {
             TokenType::Operator if token.value == "-" => Ok((UnaryOperator::Neg, token)),
-            // TODO: negation. Original parser doesn't support `not` yet.
+            TokenType::Bang => Ok((UnaryOperator::Not, token)),
             TokenType::Operator => Err(KclError::Syntax(KclErrorDetails {
                 source_ranges: token.as_source_ranges(),
                 message: format!(
@@ -1074,7 +1316,7 @@ fn unary_expression(i: TokenSlice) -> PResult<UnaryExpression> {
                 ),
             })),
         })
-        .context(expected("a unary expression, e.g. -x or -3"))
+        .context(expected("a unary expression, e.g. -x or !x"))
         .parse_next(i)?;
     let argument = operand.parse_next(i)?;
     Ok(UnaryExpression {
@@ -1732,6 +1974,27 @@ const mySk1 = startSketchAt([0, 0])"#;
         assert_eq!(actual.operator, BinaryOperator::Sub);
     }
 
+    #[test]
+    fn test_pow_precedence() {
+        // `^` has always bound looser than `*` and `+`, so keep that, and bind it tighter than comparisons.
+        for (input, outer, inner) in [
+            ("2 * 3 ^ 2", BinaryOperator::Pow, BinaryOperator::Mul),
+            ("1 + 2 ^ 2", BinaryOperator::Pow, BinaryOperator::Add),
+            ("x ^ 2 <= 9", BinaryOperator::Lte, BinaryOperator::Pow),
+        ] {
+            let tokens = crate::token::lexer(input).unwrap();
+            let actual = match binary_expression.parse(&tokens) {
+                Ok(x) => x,
+                Err(e) => panic!("{e:?}"),
+            };
+            assert_eq!(actual.operator, outer, "{input}");
+            let BinaryPart::BinaryExpression(left) = &actual.left else {
+                panic!("expected a nested binary expression on the left of {input}");
+            };
+            assert_eq!(left.operator, inner, "{input}");
+        }
+    }
+
     #[test]
     fn test_arg() {
         for input in [
@@ -2683,6 +2946,7 @@ e
                         ],
                     })),
                 }],
+                visibility: ItemVisibility::Default,
                 kind: VariableKind::Const,
             })],
             non_code_meta: NonCodeMeta::default(),
@@ -2909,6 +3173,146 @@ let myBox = box([0,0], -3, -16, -10)
         let err = parser.ast().unwrap_err();
         println!("{err}")
     }
+
+    #[test]
+    fn parse_if_else() {
+        let tokens = crate::token::lexer(
+            r#"const x = if a {
+  1
+} else if b {
+  2
+} else {
+  3
+}"#,
+        )
+        .unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let BodyItem::VariableDeclaration(ref declaration) = program.body[0] else {
+            panic!("expected a variable declaration");
+        };
+        let Value::IfExpression(ref if_expr) = declaration.declarations[0].init else {
+            panic!("expected an if expression");
+        };
+        assert_eq!(if_expr.start, 10);
+        assert_eq!(if_expr.end, 53);
+        assert_eq!(if_expr.else_ifs.len(), 1);
+        assert!(if_expr.final_else.is_some());
+    }
+
+    #[test]
+    fn parse_if_without_else() {
+        for code in ["if a { 1 }", "if a {}", "if a { } else {}", "if f(1, 2) {\n  g()\n}"] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let program = crate::parser::Parser::new(tokens).ast();
+            assert!(program.is_ok(), "could not parse {code}: {program:?}");
+        }
+    }
+
+    #[test]
+    fn parse_if_missing_block_errors() {
+        for code in [
+            "const x = if a",
+            "const x = if a { 1 } else",
+            "const x = if a { 1 } else if { 2 }",
+        ] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let result = crate::parser::Parser::new(tokens).ast();
+            assert!(result.is_err(), "{code} should not parse");
+        }
+    }
+
+    #[test]
+    fn parse_for_loop() {
+        for code in [
+            "for i in [0..10] {}",
+            "for i in [a..b + 1] {\n  f(i)\n}",
+            "for point in points {\n  const x = point[0]\n}",
+            "for i in [1, 2, 3] { f(i) }",
+        ] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let program = crate::parser::Parser::new(tokens).ast();
+            assert!(program.is_ok(), "could not parse {code}: {program:?}");
+        }
+    }
+
+    #[test]
+    fn parse_import() {
+        let code = r#"import { cube, sphere as ball, } from "lib/shapes.kcl""#;
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let BodyItem::ImportStatement(import) = &program.body[0] else {
+            panic!("expected an import statement, found {:?}", program.body[0]);
+        };
+        assert_eq!(import.path, "lib/shapes.kcl");
+        assert_eq!(import.start, 0);
+        assert_eq!(import.end, code.len());
+        let bound: Vec<_> = import
+            .items
+            .iter()
+            .map(|item| item.identifier().name.as_str())
+            .collect();
+        assert_eq!(bound, vec!["cube", "ball"]);
+        assert_eq!(import.items[1].name.name, "sphere");
+    }
+
+    #[test]
+    fn parse_import_function_still_works() {
+        // The stdlib `import` function shares its name with import statements.
+        for code in [
+            r#"const model = import("tests/inputs/cube.obj")"#,
+            r#"import("tests/inputs/cube.obj")"#,
+        ] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let program = crate::parser::Parser::new(tokens).ast().unwrap();
+            assert!(
+                !matches!(program.body[0], BodyItem::ImportStatement(_)),
+                "{code} should not parse as an import statement"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_export() {
+        let code = "export const width = 4";
+        let tokens = crate::token::lexer(code).unwrap();
+        let program = crate::parser::Parser::new(tokens).ast().unwrap();
+        let BodyItem::VariableDeclaration(declaration) = &program.body[0] else {
+            panic!("expected a variable declaration, found {:?}", program.body[0]);
+        };
+        assert_eq!(declaration.visibility, ItemVisibility::Export);
+        assert_eq!(declaration.start, 0);
+        assert_eq!(declaration.kind, VariableKind::Const);
+    }
+
+    #[test]
+    fn parse_import_errors() {
+        for code in [
+            r#"import {} from "shapes.kcl""#,
+            r#"import { cube } "shapes.kcl""#,
+            r#"import { cube } from shapes"#,
+            r#"import { cube as } from "shapes.kcl""#,
+        ] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let result = crate::parser::Parser::new(tokens).ast();
+            assert!(result.is_err(), "{code} should not parse, but gave {result:?}");
+        }
+    }
+
+    #[test]
+    fn parse_for_loop_errors() {
+        for (code, message) in [
+            ("for i [0..10] {}", "Unexpected token"),
+            ("for i in [0..10]", "Unexpected end of file"),
+            (
+                "fn f = () => {\n  for i in [0..10] {\n    return i\n  }\n}",
+                "you can't return from inside a for loop",
+            ),
+        ] {
+            let tokens = crate::token::lexer(code).unwrap();
+            let err = crate::parser::Parser::new(tokens).ast().unwrap_err();
+            assert!(err.message().contains(message), "{code} gave the wrong error: {err}");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2943,6 +3347,8 @@ mod snapshot_math_tests {
     snapshot_test!(i, "1 * ((( 2 + 3 )))");
     snapshot_test!(j, "distance * p * FOS * 6 / (sigmaAllow * width)");
     snapshot_test!(k, "2 + (((3)))");
+    snapshot_test!(l, "a + 1 > b * 2 && !c || d == 4");
+    snapshot_test!(m, "x ^ 2 <= 9");
 }
 
 #[cfg(test)]
@@ -3080,4 +3486,27 @@ mod snapshot_tests {
     snapshot_test!(at, "line([0, l], %)");
     snapshot_test!(au, include_str!("../../../tests/executor/inputs/cylinder.kcl"));
     snapshot_test!(av, "fn f = (angle?) => { return default(angle, 360) }");
+    snapshot_test!(
+        aw,
+        r#"const x = if a {
+  1
+} else if b {
+  const y = 2
+  y
+} else {
+  3
+}"#
+    );
+    snapshot_test!(ax, "if true {}");
+    snapshot_test!(
+        ay,
+        r#"for i in [0..n] {
+  f(i)
+}"#
+    );
+    snapshot_test!(
+        az,
+        r#"import { cube, sphere as ball } from "shapes.kcl"
+export fn double = (x) => { return x * 2 }"#
+    );
 }
@@ -525,6 +525,68 @@ pub struct CommandBarSettings {
     pub include_settings: DefaultTrue,
 }
 
+/// How seriously to treat what each lint rule finds.
+/// The rules are described in the `lint` module.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, ts_rs::TS, PartialEq, Eq, Validate)]
+#[serde(rename_all = "snake_case")]
+#[ts(export)]
+pub struct LintSettings {
+    /// Numbers used in an expression instead of being bound to a named variable.
+    #[serde(default = "LintSeverity::off")]
+    pub magic_number: LintSeverity,
+    /// Variable, function and parameter names which aren't camelCase.
+    #[serde(default = "LintSeverity::warning")]
+    pub camel_case: LintSeverity,
+    /// Sketches started with `startSketchAt` instead of `startSketchOn`.
+    #[serde(default = "LintSeverity::warning")]
+    pub start_sketch_at: LintSeverity,
+    /// Pipe expressions nested inside other pipe expressions.
+    #[serde(default = "LintSeverity::warning")]
+    pub nested_pipe: LintSeverity,
+    /// Tags on segments which are never used.
+    #[serde(default = "LintSeverity::warning")]
+    pub unused_tag: LintSeverity,
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        Self {
+            // Sketches are made of numbers, so this is too noisy to be on unless a project asks for it.
+            magic_number: LintSeverity::Off,
+            camel_case: LintSeverity::Warning,
+            start_sketch_at: LintSeverity::Warning,
+            nested_pipe: LintSeverity::Warning,
+            unused_tag: LintSeverity::Warning,
+        }
+    }
+}
+
+/// How seriously to treat what a lint rule finds.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, JsonSchema, ts_rs::TS, Display, FromStr, PartialEq, Eq)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+#[display(style = "snake_case")]
+pub enum LintSeverity {
+    /// Don't check the rule.
+    Off,
+    /// Show it as a hint.
+    Hint,
+    /// Show it as a warning.
+    Warning,
+    /// Show it as an error. The program is still executed.
+    Error,
+}
+
+impl LintSeverity {
+    fn off() -> Self {
+        Self::Off
+    }
+
+    fn warning() -> Self {
+        Self::Warning
+    }
+}
+
 /// The types of onboarding status.
 #[derive(Debug, Default, Eq, PartialEq, Clone, Deserialize, Serialize, JsonSchema, ts_rs::TS, Display, FromStr)]
 #[ts(export)]
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::settings::types::{
-    AppColor, AppSettings, AppTheme, CommandBarSettings, ModelingSettings, TextEditorSettings,
+    is_default, AppColor, AppSettings, AppTheme, CommandBarSettings, LintSettings, ModelingSettings, TextEditorSettings,
 };
 
 /// High level project configuration.
@@ -74,6 +74,10 @@ pub struct PerProjectSettings {
     #[serde(default, alias = "commandBar")]
     #[validate(nested)]
     pub command_bar: CommandBarSettings,
+    /// Settings that affect which lint rules are checked, and how seriously.
+    #[serde(default, skip_serializing_if = "is_default")]
+    #[validate(nested)]
+    pub lint: LintSettings,
 }
 
 #[cfg(test)]
@@ -84,7 +88,7 @@ mod tests {
         AppSettings, AppTheme, CommandBarSettings, ModelingSettings, PerProjectSettings, ProjectConfiguration,
         TextEditorSettings,
     };
-    use crate::settings::types::{AppearanceSettings, UnitLength};
+    use crate::settings::types::{AppearanceSettings, LintSettings, LintSeverity, UnitLength};
 
     #[test]
     // Test that we can deserialize a project file from the old format.
@@ -138,6 +142,7 @@ includeSettings = false
                     command_bar: CommandBarSettings {
                         include_settings: false.into(),
                     },
+                    lint: Default::default(),
                 }
             }
         );
@@ -168,6 +173,33 @@ includeSettings = false
         assert_eq!(parsed, ProjectConfiguration::default());
     }
 
+    #[test]
+    fn test_project_settings_lint() {
+        let settings_file = r#"[settings.lint]
+magic_number = "hint"
+camel_case = "off""#;
+
+        let parsed = ProjectConfiguration::backwards_compatible_toml_parse(settings_file).unwrap();
+        assert_eq!(
+            parsed.settings.lint,
+            LintSettings {
+                magic_number: LintSeverity::Hint,
+                camel_case: LintSeverity::Off,
+                ..Default::default()
+            }
+        );
+
+        // Write the file back out.
+        let serialized = toml::to_string(&parsed).unwrap();
+        assert!(serialized.contains(
+            r#"[settings.lint]
+magic_number = "hint"
+camel_case = "off"
+start_sketch_at = "warning"
+"#
+        ));
+    }
+
     #[test]
     fn test_project_settings_color_validation_error() {
         let settings_file = r#"[settings.app.appearance]
@@ -115,6 +115,11 @@ pub fn name_in_stdlib(name: &str) -> bool {
     CORE_FNS.iter().any(|f| f.name() == name)
 }
 
+/// Get the core stdlib function with the given name, if there is one.
+pub fn get_core_fn(name: &str) -> Option<&'static dyn StdLibFn> {
+    CORE_FNS.iter().find(|f| f.name() == name).map(|f| f.as_ref())
+}
+
 pub struct StdLib {
     pub fns: HashMap<String, Box<dyn StdLibFn>>,
     pub kcl_fns: HashMap<String, Box<dyn KclStdLibFn>>,
@@ -176,6 +181,15 @@ impl StdLib {
     pub fn contains_key(&self, key: &str) -> bool {
         self.fns.contains_key(key) || self.kcl_fns.contains_key(key)
     }
+
+    /// Which argument of a function gives a tag to the segment it makes, if it makes one.
+    pub fn tag_arg_index(&self, name: &str) -> Option<usize> {
+        let func = self.get(name)?;
+        // Functions which refer to an existing tag require it.
+        func.args()
+            .iter()
+            .position(|arg| arg.name == "tag" && !arg.required && arg.type_ == "String")
+    }
 }
 
 impl Default for StdLib {
@@ -26,7 +26,7 @@ pub fn token(i: &mut Located<&str>) -> PResult<Token> {
         ':' => colon,
         '.' => alt((number, double_period, period)),
         '#' => hash,
-        '!' => bang,
+        '!' => alt((operator, bang)),
         ' ' | '\t' | '\n' => whitespace,
         _ => alt((operator, keyword,type_, word))
     }
@@ -89,7 +89,7 @@ fn word(i: &mut Located<&str>) -> PResult<Token> {
 
 fn operator(i: &mut Located<&str>) -> PResult<Token> {
     let (value, range) = alt((
-        ">=", "<=", "==", "=>", "!= ", "|>", "*", "+", "-", "/", "%", "=", "<", ">", r"\", "|", "^",
+        ">=", "<=", "==", "=>", "!=", "|>", "&&", "||", "*", "+", "-", "/", "%", "=", "<", ">", r"\", "|", "^",
     ))
     .with_span()
     .parse_next(i)?;
@@ -257,7 +257,8 @@ mod tests {
     #[test]
     fn test_operator() {
         for valid in [
-            "+", "+ ", "-", "<=", "<= ", ">=", ">= ", "> ", "< ", "| ", "|> ", "^ ", "% ", "+* ",
+            "+", "+ ", "-", "<=", "<= ", ">=", ">= ", "> ", "< ", "| ", "|> ", "^ ", "% ", "+* ", "==", "!=", "!=a",
+            "&&", "|| ",
         ] {
             assert_parse_ok(operator, valid);
         }
@@ -1523,6 +1524,62 @@ const things = "things"
         assert_tokens(expected, actual);
     }
 
+    #[test]
+    fn test_lexer_comparison_and_boolean_operators() {
+        let actual = lexer("!a!=b&&c||d").unwrap();
+        let expected = vec![
+            Token {
+                token_type: TokenType::Bang,
+                value: "!".to_string(),
+                start: 0,
+                end: 1,
+            },
+            Token {
+                token_type: TokenType::Word,
+                value: "a".to_string(),
+                start: 1,
+                end: 2,
+            },
+            Token {
+                token_type: TokenType::Operator,
+                value: "!=".to_string(),
+                start: 2,
+                end: 4,
+            },
+            Token {
+                token_type: TokenType::Word,
+                value: "b".to_string(),
+                start: 4,
+                end: 5,
+            },
+            Token {
+                token_type: TokenType::Operator,
+                value: "&&".to_string(),
+                start: 5,
+                end: 7,
+            },
+            Token {
+                token_type: TokenType::Word,
+                value: "c".to_string(),
+                start: 7,
+                end: 8,
+            },
+            Token {
+                token_type: TokenType::Operator,
+                value: "||".to_string(),
+                start: 8,
+                end: 10,
+            },
+            Token {
+                token_type: TokenType::Word,
+                value: "d".to_string(),
+                start: 10,
+                end: 11,
+            },
+        ];
+        assert_tokens(expected, actual);
+    }
+
     #[test]
     fn test_unrecognized_token() {
         let actual = lexer("12 ; 8").unwrap();
@@ -20,6 +20,7 @@ pub async fn execute_wasm(
     engine_manager: kcl_lib::engine::conn_wasm::EngineCommandManager,
     fs_manager: kcl_lib::fs::wasm::FileSystemManager,
     is_mock: bool,
+    project_directory: Option<String>,
 ) -> Result<JsValue, String> {
     console_error_panic_hook::set_once();
     // deserialize the ast from a stringified json
@@ -38,9 +39,11 @@ pub async fn execute_wasm(
         stdlib: std::sync::Arc::new(kcl_lib::std::StdLib::new()),
         settings: ExecutorSettings {
             units,
+            project_directory,
             ..Default::default()
         },
         is_mock,
+        import_stack: Vec::new(),
     };
 
     let memory = ctx.run(program, Some(memory)).await.map_err(String::from)?;
@@ -234,6 +237,7 @@ pub async fn kcl_lsp_run(
                 ..Default::default()
             },
             is_mock: false,
+            import_stack: Vec::new(),
         })
     } else {
         None
@@ -266,16 +270,19 @@ pub async fn kcl_lsp_run(
         token_types,
         token_map: Default::default(),
         ast_map: Default::default(),
+        parsed_map: Default::default(),
         memory_map: Default::default(),
         code_map: Default::default(),
         diagnostics_map: Default::default(),
         symbols_map: Default::default(),
+        workspace_symbols_map: Default::default(),
         semantic_tokens_map: Default::default(),
         zoo_client,
         can_send_telemetry: privacy_settings.can_train_on_data,
         can_execute: Arc::new(tokio::sync::RwLock::new(executor_ctx.is_some())),
         executor_ctx: Arc::new(tokio::sync::RwLock::new(executor_ctx)),
 
+        lint_settings: Default::default(),
         is_initialized: Default::default(),
         current_handle: Default::default(),
     })
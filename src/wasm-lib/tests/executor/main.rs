@@ -1,32 +1,82 @@
+use std::sync::Arc;
+
 use anyhow::Result;
-use kcl_lib::executor::ExecutorSettings;
+use kcl_lib::{engine::EngineManager, executor::ExecutorSettings};
 
 /// Executes a kcl program and takes a snapshot of the result.
 /// This returns the bytes of the snapshot.
-async fn execute_and_snapshot(code: &str, units: kcl_lib::settings::types::UnitLength) -> Result<image::DynamicImage> {
-    let user_agent = concat!(env!("CARGO_PKG_NAME"), ".rs/", env!("CARGO_PKG_VERSION"),);
-    let http_client = reqwest::Client::builder()
-        .user_agent(user_agent)
-        // For file conversions we need this to be long.
-        .timeout(std::time::Duration::from_secs(600))
-        .connect_timeout(std::time::Duration::from_secs(60));
-    let ws_client = reqwest::Client::builder()
-        .user_agent(user_agent)
-        // For file conversions we need this to be long.
-        .timeout(std::time::Duration::from_secs(600))
-        .connect_timeout(std::time::Duration::from_secs(60))
-        .connection_verbose(true)
-        .tcp_keepalive(std::time::Duration::from_secs(600))
-        .http1_only();
-
-    let token = std::env::var("KITTYCAD_API_TOKEN").expect("KITTYCAD_API_TOKEN not set");
-
-    // Create the client.
-    let mut client = kittycad::Client::new_from_reqwest(token, http_client, ws_client);
-    // Set a local engine address if it's set.
-    if let Ok(addr) = std::env::var("LOCAL_ENGINE_ADDR") {
-        client.set_base_url(addr);
-    }
+///
+/// With a `KITTYCAD_API_TOKEN`, this uses the engine, and records the session to
+/// `tests/executor/recordings/{name}.jsonl` if `KCL_RECORD_ENGINE` is set. Without one, it replays
+/// the recording instead, checking the program sends the same modeling commands. If there's no
+/// recording either, the test fails.
+async fn execute_and_snapshot(
+    name: &str,
+    code: &str,
+    units: kcl_lib::settings::types::UnitLength,
+) -> Result<image::DynamicImage> {
+    let recording = recording(name);
+    let settings = ExecutorSettings {
+        units,
+        highlight_edges: true,
+        enable_ssao: false,
+        project_directory: None,
+        engine_timeout: kcl_lib::executor::DEFAULT_ENGINE_TIMEOUT,
+    };
+
+    let mut replay = None;
+    let engine: Arc<Box<dyn EngineManager>> = match std::env::var("KITTYCAD_API_TOKEN") {
+        Ok(token) => {
+            let user_agent = concat!(env!("CARGO_PKG_NAME"), ".rs/", env!("CARGO_PKG_VERSION"),);
+            let http_client = reqwest::Client::builder()
+                .user_agent(user_agent)
+                // For file conversions we need this to be long.
+                .timeout(std::time::Duration::from_secs(600))
+                .connect_timeout(std::time::Duration::from_secs(60));
+            let ws_client = reqwest::Client::builder()
+                .user_agent(user_agent)
+                // For file conversions we need this to be long.
+                .timeout(std::time::Duration::from_secs(600))
+                .connect_timeout(std::time::Duration::from_secs(60))
+                .connection_verbose(true)
+                .tcp_keepalive(std::time::Duration::from_secs(600))
+                .http1_only();
+
+            // Create the client.
+            let mut client = kittycad::Client::new_from_reqwest(token, http_client, ws_client);
+            // Set a local engine address if it's set.
+            if let Ok(addr) = std::env::var("LOCAL_ENGINE_ADDR") {
+                client.set_base_url(addr);
+            }
+
+            let engine: Arc<Box<dyn EngineManager>> = Arc::new(Box::new(
+                kcl_lib::engine::conn::EngineConnection::connect(
+                    &client,
+                    settings.enable_ssao,
+                    settings.engine_timeout,
+                )
+                .await?,
+            ));
+            if std::env::var("KCL_RECORD_ENGINE").is_ok() {
+                Arc::new(Box::new(kcl_lib::engine::conn_record::EngineConnection::new(
+                    engine, &recording,
+                )?))
+            } else {
+                engine
+            }
+        }
+        Err(_) if recording.exists() => {
+            let engine = kcl_lib::engine::conn_replay::EngineConnection::new(&recording)?;
+            replay = Some(engine.clone());
+            Arc::new(Box::new(engine))
+        }
+        // Panic rather than return an error, so tests expecting the program to fail don't pass.
+        Err(_) => panic!(
+            "`{}` isn't recorded: set KITTYCAD_API_TOKEN, or record {} with it and KCL_RECORD_ENGINE",
+            name,
+            recording.display()
+        ),
+    };
 
     // Create a temporary file to write the output to.
     let output_file = std::env::temp_dir().join(format!("kcl_output_{}.png", uuid::Uuid::new_v4()));
@@ -34,15 +84,7 @@ async fn execute_and_snapshot(code: &str, units: kcl_lib::settings::types::UnitL
     let tokens = kcl_lib::token::lexer(code)?;
     let parser = kcl_lib::parser::Parser::new(tokens);
     let program = parser.ast()?;
-    let ctx = kcl_lib::executor::ExecutorContext::new(
-        &client,
-        ExecutorSettings {
-            units,
-            highlight_edges: true,
-            enable_ssao: false,
-        },
-    )
-    .await?;
+    let ctx = kcl_lib::executor::ExecutorContext::new_with_engine(engine, settings).await?;
 
     let _ = ctx.run(program, None).await?;
 
@@ -79,12 +121,20 @@ async fn execute_and_snapshot(code: &str, units: kcl_lib::settings::types::UnitL
     } else {
         anyhow::bail!("Unexpected response from engine: {:?}", resp);
     }
+    if let Some(replay) = replay {
+        replay.finish()?;
+    }
 
     // Read the output file.
     let actual = image::io::Reader::open(output_file).unwrap().decode().unwrap();
     Ok(actual)
 }
 
+/// Where the engine session of the test called `name` is recorded.
+fn recording(name: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("tests/executor/recordings/{name}.jsonl"))
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_sketch_on_face() {
     let code = r#"const part001 = startSketchOn('XY')
@@ -104,7 +154,7 @@ const part002 = startSketchOn(part001, "here")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("sketch_on_face", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face.png", &result, 0.999);
@@ -113,7 +163,7 @@ const part002 = startSketchOn(part001, "here")
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_riddle_small() {
     let code = include_str!("inputs/riddle_small.kcl");
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("riddle_small", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/riddle_small.png", &result, 0.999);
@@ -122,7 +172,7 @@ async fn serial_test_riddle_small() {
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_lego() {
     let code = include_str!("inputs/lego.kcl");
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("lego", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/lego.png", &result, 0.999);
@@ -131,16 +181,20 @@ async fn serial_test_lego() {
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_pentagon_fillet_desugar() {
     let code = include_str!("inputs/pentagon_fillet_desugar.kcl");
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Cm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "pentagon_fillet_desugar",
+        code,
+        kcl_lib::settings::types::UnitLength::Cm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/pentagon_fillet_desugar.png", &result, 0.999);
 }
 
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_pentagon_fillet_sugar() {
     let code = include_str!("inputs/pentagon_fillet_sugar.kcl");
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Cm)
+    let result = execute_and_snapshot("pentagon_fillet_sugar", code, kcl_lib::settings::types::UnitLength::Cm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/pentagon_fillet_sugar.png", &result, 0.999);
@@ -170,7 +224,7 @@ const part002 = startSketchOn(part001, "start")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("sketch_on_face_start", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face_start.png", &result, 0.999);
@@ -179,7 +233,7 @@ const part002 = startSketchOn(part001, "start")
 #[tokio::test(flavor = "multi_thread")]
 async fn serial_test_mike_stress_lines() {
     let code = include_str!("inputs/mike_stress_test.kcl");
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("mike_stress_lines", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/mike_stress_test.png", &result, 0.999);
@@ -209,7 +263,7 @@ const part002 = startSketchOn(part001, "END")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("sketch_on_face_end", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face_end.png", &result, 0.999);
@@ -239,9 +293,13 @@ const part002 = startSketchOn(part001, "END")
   |> extrude(-5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "sketch_on_face_end_negative_extrude",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/sketch_on_face_end_negative_extrude.png",
         &result,
@@ -261,7 +319,7 @@ async fn serial_test_fillet_duplicate_tags() {
     |> fillet({radius: 0.5, tags: ["thing", "thing"]}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot("fillet_duplicate_tags", code, kcl_lib::settings::types::UnitLength::Mm).await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -281,9 +339,13 @@ async fn serial_test_basic_fillet_cube_start() {
     |> fillet({radius: 2, tags: ["thing", "thing2"]}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_fillet_cube_start",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/basic_fillet_cube_start.png", &result, 0.999);
 }
 
@@ -300,7 +362,7 @@ async fn serial_test_basic_fillet_cube_end() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("basic_fillet_cube_end", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/basic_fillet_cube_end.png", &result, 0.999);
@@ -319,9 +381,13 @@ async fn serial_test_basic_fillet_cube_close_opposite() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_fillet_cube_close_opposite",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/basic_fillet_cube_close_opposite.png",
         &result,
@@ -341,9 +407,13 @@ async fn serial_test_basic_fillet_cube_next_adjacent() {
     |> fillet({radius: 2, tags: [getNextAdjacentEdge("thing3", %)]}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_fillet_cube_next_adjacent",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/basic_fillet_cube_next_adjacent.png",
         &result,
@@ -363,9 +433,13 @@ async fn serial_test_basic_fillet_cube_previous_adjacent() {
     |> fillet({radius: 2, tags: [getPreviousAdjacentEdge("thing3", %)]}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_fillet_cube_previous_adjacent",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/basic_fillet_cube_previous_adjacent.png",
         &result,
@@ -390,9 +464,13 @@ async fn serial_test_execute_with_function_sketch() {
 const fnBox = box(3, 6, 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "execute_with_function_sketch",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/function_sketch.png", &result, 0.999);
 }
 
@@ -412,9 +490,13 @@ async fn serial_test_execute_with_function_sketch_with_position() {
 
 const thing = box([0,0], 3, 6, 10)"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "execute_with_function_sketch_with_position",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/function_sketch_with_position.png",
         &result,
@@ -435,9 +517,13 @@ async fn serial_test_execute_with_angled_line() {
   |> extrude(4, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "execute_with_angled_line",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/angled_line.png", &result, 0.999);
 }
 
@@ -463,9 +549,13 @@ const bracket = startSketchOn('XY')
   |> extrude(width, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "execute_parametric_example",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/parametric.png", &result, 0.999);
 }
 
@@ -499,9 +589,13 @@ const bracket = startSketchAt([0, 0])
   |> extrude(width, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "execute_parametric_with_tan_arc_example",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/parametric_with_tan_arc.png", &result, 0.999);
 }
 
@@ -516,7 +610,12 @@ async fn serial_test_execute_engine_error_return() {
   |> extrude(4, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "execute_engine_error_return",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -529,7 +628,7 @@ async fn serial_test_execute_i_shape() {
     // This is some code from lee that starts a pipe expression with a variable.
     let code = include_str!("inputs/i_shape.kcl");
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("execute_i_shape", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/i_shape.png", &result, 0.999);
@@ -540,7 +639,7 @@ async fn serial_test_execute_i_shape() {
 async fn serial_test_execute_pipes_on_pipes() {
     let code = include_str!("inputs/pipes_on_pipes.kcl");
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("execute_pipes_on_pipes", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/pipes_on_pipes.png", &result, 0.999);
@@ -550,7 +649,7 @@ async fn serial_test_execute_pipes_on_pipes() {
 async fn serial_test_execute_cylinder() {
     let code = include_str!("inputs/cylinder.kcl");
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("execute_cylinder", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cylinder.png", &result, 0.999);
@@ -560,7 +659,7 @@ async fn serial_test_execute_cylinder() {
 async fn serial_test_execute_kittycad_svg() {
     let code = include_str!("inputs/kittycad_svg.kcl");
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("execute_kittycad_svg", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/kittycad_svg.png", &result, 0.999);
@@ -587,9 +686,13 @@ const pt1 = b1.value[0]
 const pt2 = b2.value[0]
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "member_expression_sketch_group",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/member_expression_sketch_group.png",
         &result,
@@ -605,7 +708,7 @@ async fn serial_test_helix_defaults() {
      |> helix({revolutions: 16, angle_start: 0}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("helix_defaults", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/helix_defaults.png", &result, 1.0);
@@ -619,9 +722,13 @@ async fn serial_test_helix_defaults_negative_extrude() {
      |> helix({revolutions: 16, angle_start: 0}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "helix_defaults_negative_extrude",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/helix_defaults_negative_extrude.png",
         &result,
@@ -637,7 +744,7 @@ async fn serial_test_helix_ccw() {
      |> helix({revolutions: 16, angle_start: 0, ccw: true}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("helix_ccw", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/helix_ccw.png", &result, 1.0);
@@ -651,7 +758,7 @@ async fn serial_test_helix_with_length() {
      |> helix({revolutions: 16, angle_start: 0, length: 3}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("helix_with_length", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/helix_with_length.png", &result, 1.0);
@@ -668,7 +775,7 @@ async fn serial_test_dimensions_match() {
   |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("dimensions_match", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/dimensions_match.png", &result, 1.0);
@@ -687,7 +794,7 @@ const body = startSketchOn('XY')
       |> extrude(height, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("close_arc", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/close_arc.png", &result, 0.999);
@@ -715,7 +822,7 @@ box(10, 23, 8)
 let thing = box(-12, -15, 10)
 box(-20, -5, 10)"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("negative_args", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/negative_args.png", &result, 0.999);
@@ -730,7 +837,7 @@ async fn serial_test_basic_tangential_arc() {
     |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("basic_tangential_arc", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/tangential_arc.png", &result, 0.999);
@@ -745,9 +852,13 @@ async fn serial_test_basic_tangential_arc_with_point() {
     |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_tangential_arc_with_point",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/tangential_arc_with_point.png", &result, 0.999);
 }
 
@@ -760,9 +871,13 @@ async fn serial_test_basic_tangential_arc_to() {
     |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "basic_tangential_arc_to",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/tangential_arc_to.png", &result, 0.999);
 }
 
@@ -789,9 +904,13 @@ box(30, 43, 18, '-xy')
 let thing = box(-12, -15, 10, 'yz')
 box(-20, -5, 10, 'xy')"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "different_planes_same_drawing",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/different_planes_same_drawing.png",
         &result,
@@ -853,7 +972,7 @@ const part004 = startSketchOn('YZ')
   |> close(%)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("lots_of_planes", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/lots_of_planes.png", &result, 0.999);
@@ -872,7 +991,7 @@ async fn serial_test_holes() {
   |> extrude(2, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("holes", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/holes.png", &result, 0.999);
@@ -893,7 +1012,7 @@ async fn optional_params() {
 
 const thing = other_circle([2, 2], 20)
 "#;
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("optional_params", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/optional_params.png", &result, 0.999);
@@ -931,7 +1050,7 @@ const part = roundedRectangle([0, 0], 20, 20, 4)
   |> extrude(2, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("rounded_with_holes", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/rounded_with_holes.png", &result, 0.999);
@@ -941,7 +1060,7 @@ const part = roundedRectangle([0, 0], 20, 20, 4)
 async fn serial_test_top_level_expression() {
     let code = r#"startSketchOn('XY') |> circle([0,0], 22, %) |> extrude(14, %)"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("top_level_expression", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/top_level_expression.png", &result, 0.999);
@@ -957,9 +1076,13 @@ const part =  startSketchOn('XY')
     |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_linear_basic_with_math",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/patterns_linear_basic_with_math.png",
         &result,
@@ -975,7 +1098,7 @@ async fn serial_test_patterns_linear_basic() {
     |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("patterns_linear_basic", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/patterns_linear_basic.png", &result, 0.999);
@@ -993,9 +1116,13 @@ async fn serial_test_patterns_linear_basic_3d() {
     |> patternLinear3d({axis: [1, 0, 1], repetitions: 3, distance: 6}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_linear_basic_3d",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/patterns_linear_basic_3d.png", &result, 0.999);
 }
 
@@ -1007,9 +1134,13 @@ async fn serial_test_patterns_linear_basic_negative_distance() {
     |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_linear_basic_negative_distance",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/patterns_linear_basic_negative_distance.png",
         &result,
@@ -1025,9 +1156,13 @@ async fn serial_test_patterns_linear_basic_negative_axis() {
     |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_linear_basic_negative_axis",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/patterns_linear_basic_negative_axis.png",
         &result,
@@ -1052,9 +1187,13 @@ const rectangle = startSketchOn('XY')
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_linear_basic_holes",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/patterns_linear_basic_holes.png", &result, 0.999);
 }
 
@@ -1066,9 +1205,13 @@ async fn serial_test_patterns_circular_basic_2d() {
     |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_circular_basic_2d",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/patterns_circular_basic_2d.png", &result, 0.999);
 }
 
@@ -1084,9 +1227,13 @@ async fn serial_test_patterns_circular_basic_3d() {
     |> patternCircular3d({axis: [0,0, 1], center: [-20, -20, -20], repetitions: 40, arcDegrees: 360, rotateDuplicates: false}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_circular_basic_3d",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/patterns_circular_basic_3d.png", &result, 0.999);
 }
 
@@ -1102,9 +1249,13 @@ async fn serial_test_patterns_circular_3d_tilted_axis() {
     |> patternCircular3d({axis: [1,1,0], center: [10, 0, 10], repetitions: 10, arcDegrees: 360, rotateDuplicates: true}, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "patterns_circular_3d_tilted_axis",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/patterns_circular_3d_tilted_axis.png",
         &result,
@@ -1116,7 +1267,12 @@ async fn serial_test_patterns_circular_3d_tilted_axis() {
 async fn serial_test_import_file_doesnt_exist() {
     let code = r#"const model = import("thing.obj")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "import_file_doesnt_exist",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -1128,7 +1284,7 @@ async fn serial_test_import_file_doesnt_exist() {
 async fn serial_test_import_obj_with_mtl() {
     let code = r#"const model = import("tests/executor/inputs/cube.obj")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("import_obj_with_mtl", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_obj_with_mtl.png", &result, 0.999);
@@ -1138,9 +1294,13 @@ async fn serial_test_import_obj_with_mtl() {
 async fn serial_test_import_obj_with_mtl_units() {
     let code = r#"const model = import("tests/executor/inputs/cube.obj", {type: "obj", units: "m"})"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "import_obj_with_mtl_units",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_obj_with_mtl_units.png", &result, 0.999);
 }
 
@@ -1148,7 +1308,7 @@ async fn serial_test_import_obj_with_mtl_units() {
 async fn serial_test_import_gltf_with_bin() {
     let code = r#"const model = import("tests/executor/inputs/cube.gltf")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("import_gltf_with_bin", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_gltf_with_bin.png", &result, 0.999);
@@ -1158,7 +1318,7 @@ async fn serial_test_import_gltf_with_bin() {
 async fn serial_test_import_gltf_embedded() {
     let code = r#"const model = import("tests/executor/inputs/cube-embedded.gltf")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("import_gltf_embedded", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_gltf_embedded.png", &result, 0.999);
@@ -1168,7 +1328,7 @@ async fn serial_test_import_gltf_embedded() {
 async fn serial_test_import_glb() {
     let code = r#"const model = import("tests/executor/inputs/cube.glb")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("import_glb", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_glb.png", &result, 0.999);
@@ -1178,7 +1338,7 @@ async fn serial_test_import_glb() {
 async fn serial_test_import_glb_no_assign() {
     let code = r#"import("tests/executor/inputs/cube.glb")"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("import_glb_no_assign", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/import_glb_no_assign.png", &result, 0.999);
@@ -1188,7 +1348,12 @@ async fn serial_test_import_glb_no_assign() {
 async fn serial_test_import_ext_doesnt_match() {
     let code = r#"const model = import("tests/executor/inputs/cube.gltf", {type: "obj", units: "m"})"#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "import_ext_doesnt_match",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -1213,7 +1378,7 @@ async fn serial_test_cube_mm() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("cube_mm", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_mm.png", &result, 1.0);
@@ -1236,7 +1401,7 @@ async fn serial_test_cube_cm() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Cm)
+    let result = execute_and_snapshot("cube_cm", code, kcl_lib::settings::types::UnitLength::Cm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_cm.png", &result, 1.0);
@@ -1259,7 +1424,7 @@ async fn serial_test_cube_m() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::M)
+    let result = execute_and_snapshot("cube_m", code, kcl_lib::settings::types::UnitLength::M)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_m.png", &result, 1.0);
@@ -1282,7 +1447,7 @@ async fn serial_test_cube_in() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::In)
+    let result = execute_and_snapshot("cube_in", code, kcl_lib::settings::types::UnitLength::In)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_in.png", &result, 1.0);
@@ -1305,7 +1470,7 @@ async fn serial_test_cube_ft() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Ft)
+    let result = execute_and_snapshot("cube_ft", code, kcl_lib::settings::types::UnitLength::Ft)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_ft.png", &result, 1.0);
@@ -1328,7 +1493,7 @@ async fn serial_test_cube_yd() {
 const myCube = cube([0,0], 10)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Yd)
+    let result = execute_and_snapshot("cube_yd", code, kcl_lib::settings::types::UnitLength::Yd)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/cube_yd.png", &result, 1.0);
@@ -1358,7 +1523,12 @@ const part002 = startSketchOn(part001, "here")
   |> extrude(1, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "error_sketch_on_arc_face",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
 
     assert!(result.is_err());
     assert_eq!(
@@ -1399,7 +1569,7 @@ const part003 = startSketchOn(part002, "end")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("sketch_on_face_of_face", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face_of_face.png", &result, 1.0);
@@ -1418,7 +1588,12 @@ async fn serial_test_stdlib_kcl_error_right_code_path() {
   |> extrude(2, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "stdlib_kcl_error_right_code_path",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -1446,7 +1621,7 @@ const part002 = startSketchOn(part001, "end")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("sketch_on_face_circle", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face_circle.png", &result, 1.0);
@@ -1472,9 +1647,13 @@ const part002 = startSketchOn(part001, "end")
   |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "sketch_on_face_circle_tagged",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/sketch_on_face_circle_tagged.png", &result, 1.0);
 }
 
@@ -1516,7 +1695,12 @@ const part = rectShape([0, 0], 20, 20)
      }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "stdlib_kcl_error_circle",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),
@@ -1537,9 +1721,13 @@ async fn serial_test_big_number_angle_to_match_length_x() {
   |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "big_number_angle_to_match_length_x",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/big_number_angle_to_match_length_x.png",
         &result,
@@ -1560,9 +1748,13 @@ async fn serial_test_big_number_angle_to_match_length_y() {
   |> extrude(10, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "big_number_angle_to_match_length_y",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image(
         "tests/executor/outputs/big_number_angle_to_match_length_y.png",
         &result,
@@ -1586,7 +1778,7 @@ async fn serial_test_simple_revolve() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("simple_revolve", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve.png", &result, 1.0);
@@ -1608,9 +1800,13 @@ async fn serial_test_simple_revolve_uppercase() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "simple_revolve_uppercase",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve_uppercase.png", &result, 1.0);
 }
 
@@ -1630,9 +1826,13 @@ async fn serial_test_simple_revolve_negative() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "simple_revolve_negative",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve_negative.png", &result, 1.0);
 }
 
@@ -1652,7 +1852,7 @@ async fn serial_test_revolve_bad_angle_low() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot("revolve_bad_angle_low", code, kcl_lib::settings::types::UnitLength::Mm).await;
 
     assert!(result.is_err());
     assert_eq!(
@@ -1677,7 +1877,7 @@ async fn serial_test_revolve_bad_angle_high() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot("revolve_bad_angle_high", code, kcl_lib::settings::types::UnitLength::Mm).await;
 
     assert!(result.is_err());
     assert_eq!(
@@ -1702,9 +1902,13 @@ async fn serial_test_simple_revolve_custom_angle() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "simple_revolve_custom_angle",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve_custom_angle.png", &result, 1.0);
 }
 
@@ -1724,9 +1928,13 @@ async fn serial_test_simple_revolve_custom_axis() {
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "simple_revolve_custom_axis",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve_custom_axis.png", &result, 1.0);
 }
 
@@ -1750,7 +1958,7 @@ const sketch001 = startSketchOn(box, "end")
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("revolve_on_edge", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/revolve_on_edge.png", &result, 1.0);
@@ -1776,7 +1984,12 @@ const sketch001 = startSketchOn(box, "revolveAxis")
 
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "revolve_on_edge_get_edge",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
 
     assert!(result.is_err());
     assert_eq!(
@@ -1803,9 +2016,13 @@ const sketch001 = startSketchOn(box, "END")
     }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "revolve_on_face_circle_edge",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/revolve_on_face_circle_edge.png", &result, 1.0);
 }
 
@@ -1827,7 +2044,7 @@ const sketch001 = startSketchOn(box, "END")
     }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("revolve_on_face_circle", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/revolve_on_face_circle.png", &result, 1.0);
@@ -1855,7 +2072,7 @@ const sketch001 = startSketchOn(box, "end")
   }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("revolve_on_face", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/revolve_on_face.png", &result, 1.0);
@@ -1871,7 +2088,7 @@ async fn serial_test_basic_revolve_circle() {
     }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("basic_revolve_circle", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/basic_revolve_circle.png", &result, 1.0);
@@ -1900,9 +2117,13 @@ const part002 = startSketchOn(part001, 'end')
     |> extrude(5, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "simple_revolve_sketch_on_edge",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/simple_revolve_sketch_on_edge.png", &result, 1.0);
 }
 
@@ -1963,7 +2184,7 @@ const plumbus0 = make_circle(p, 'a', [0, 0], 2.5)
 //      }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
+    let result = execute_and_snapshot("plumbus_fillets", code, kcl_lib::settings::types::UnitLength::Mm)
         .await
         .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/plumbus_fillets.png", &result, 1.0);
@@ -1973,7 +2194,7 @@ const plumbus0 = make_circle(p, 'a', [0, 0], 2.5)
 async fn serial_test_empty_file_is_ok() {
     let code = r#""#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot("empty_file_is_ok", code, kcl_lib::settings::types::UnitLength::Mm).await;
     assert!(result.is_ok());
 }
 
@@ -2003,9 +2224,13 @@ async fn serial_test_member_expression_in_params() {
 capScrew([0, 0.5, 0], 50, 37.5, 50, 25)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm)
-        .await
-        .unwrap();
+    let result = execute_and_snapshot(
+        "member_expression_in_params",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await
+    .unwrap();
     twenty_twenty::assert_image("tests/executor/outputs/member_expression_in_params.png", &result, 1.0);
 }
 
@@ -2050,7 +2275,12 @@ const bracket = startSketchOn('XY')
      }, %)
 "#;
 
-    let result = execute_and_snapshot(code, kcl_lib::settings::types::UnitLength::Mm).await;
+    let result = execute_and_snapshot(
+        "bracket_with_fillets_ensure_fail_on_flush_source_ranges",
+        code,
+        kcl_lib::settings::types::UnitLength::Mm,
+    )
+    .await;
     assert!(result.is_err());
     assert_eq!(
         result.err().unwrap().to_string(),